@@ -1,82 +1,113 @@
 use crate::models::{AcceptedAssignment, Assignment, Classroom};
-use anyhow::{Context, Result};
+use crate::error::FetchError;
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, USER_AGENT};
 use serde::de::DeserializeOwned;
-
-const API_BASE: &str = "https://api.github.com";
+use std::sync::{Arc, RwLock};
 
 #[derive(Clone)]
 pub struct ClassroomClient {
     client: reqwest::Client,
-    token: String,
+    /// Base URL of the REST API, without a trailing slash
+    api_base: String,
+    user_agent: HeaderValue,
+    /// Shared between clones so a refreshed token reaches background tasks too
+    token: Arc<RwLock<String>>,
 }
 
 impl ClassroomClient {
-    pub fn new(token: String) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(120)) // 2 minute timeout
-            .connect_timeout(std::time::Duration::from_secs(30))
-            .build()
-            .expect("Failed to build HTTP client");
-        Self { client, token }
+    pub fn new(token: String, api_base: String, user_agent: &str) -> Self {
+        let client = super::http_client(super::API_TIMEOUT, None);
+        Self {
+            client,
+            api_base,
+            user_agent: HeaderValue::from_str(user_agent).expect("Invalid User-Agent"),
+            token: Arc::new(RwLock::new(token)),
+        }
+    }
+
+    /// Send requests through `client`, e.g. one from `api_http_client`
+    /// shared with the other API client
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
     }
 
-    fn build_headers(&self) -> HeaderMap {
+    /// Replace the token used for all subsequent requests
+    pub fn set_token(&self, token: String) {
+        *self.token.write().unwrap() = token;
+    }
+
+    fn build_headers(&self) -> Result<HeaderMap, FetchError> {
         let mut headers = HeaderMap::new();
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", self.token)).unwrap(),
-        );
+        headers.insert(AUTHORIZATION, super::bearer_header(&self.token.read().unwrap())?);
         headers.insert(
             ACCEPT,
             HeaderValue::from_static("application/vnd.github+json"),
         );
-        headers.insert(
-            USER_AGENT,
-            HeaderValue::from_static("gh-autograder-fetcher"),
-        );
+        headers.insert(USER_AGENT, self.user_agent.clone());
         headers.insert(
             "X-GitHub-Api-Version",
             HeaderValue::from_static("2022-11-28"),
         );
-        headers
+        Ok(headers)
     }
 
-    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
-        let url = format!("{}{}", API_BASE, path);
-        let response = self
-            .client
-            .get(&url)
-            .headers(self.build_headers())
-            .send()
-            .await
-            .context(format!("Failed to send request to {}", url))?;
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, FetchError> {
+        let url = format!("{}{}", self.api_base, path);
+        let mut attempts = 0;
+
+        let (status, response_text) = loop {
+            let response = self
+                .client
+                .get(&url)
+                .headers(self.build_headers()?)
+                .send()
+                .await
+                .map_err(|source| FetchError::Network {
+                    url: url.clone(),
+                    source,
+                })?;
+
+            let status = response.status();
+            let headers = response.headers().clone();
+
+            // Get the response text for both error and success cases
+            let response_text = response.text().await.map_err(|source| FetchError::Network {
+                url: url.clone(),
+                source,
+            })?;
 
-        let status = response.status();
+            // Back off and retry if GitHub's abuse detection kicked in
+            if let Some(delay) = super::secondary_rate_limit_delay(status, &headers, &response_text) {
+                attempts += 1;
+                if attempts > super::MAX_SECONDARY_RETRIES {
+                    return Err(FetchError::RateLimited);
+                }
+                tokio::time::sleep(delay).await;
+                continue;
+            }
 
-        // Get the response text for both error and success cases
-        let response_text = response.text().await.context("Failed to get response text")?;
+            break (status, response_text);
+        };
 
         if !status.is_success() {
-            anyhow::bail!(
-                "API request failed with status {} for URL {}\nResponse body: {}",
-                status,
-                url,
-                response_text
-            );
+            return Err(FetchError::from_status(&url, status, response_text));
         }
 
         // Try to parse JSON and provide helpful error message
-        serde_json::from_str(&response_text).with_context(|| {
-            format!(
-                "Failed to parse JSON response from {}. Response body (first 500 chars): {}",
-                url,
-                &response_text.chars().take(500).collect::<String>()
+        serde_json::from_str(&response_text).map_err(|e| {
+            FetchError::parse(
+                format!(
+                    "JSON response from {}. Response body (first 500 chars): {}",
+                    url,
+                    &response_text.chars().take(500).collect::<String>()
+                ),
+                e,
             )
         })
     }
 
-    pub async fn list_classrooms(&self) -> Result<Vec<Classroom>> {
+    pub async fn list_classrooms(&self) -> Result<Vec<Classroom>, FetchError> {
         let mut all_classrooms = Vec::new();
         let mut page = 1;
 
@@ -101,7 +132,25 @@ impl ClassroomClient {
         Ok(all_classrooms)
     }
 
-    pub async fn list_assignments(&self, classroom_id: u64) -> Result<Vec<Assignment>> {
+    /// Classrooms belonging to the organization `org` (matched ignoring case).
+    /// The classroom list doesn't say which organization each classroom is in
+    /// and there is no per-organization list, so each classroom is fetched.
+    pub async fn list_classrooms_for_org(&self, org: &str) -> Result<Vec<Classroom>, FetchError> {
+        let mut classrooms = Vec::new();
+        for classroom in self.list_classrooms().await? {
+            let classroom = self.get_classroom(classroom.id).await?;
+            if classroom
+                .organization
+                .as_ref()
+                .is_some_and(|o| o.login.eq_ignore_ascii_case(org))
+            {
+                classrooms.push(classroom);
+            }
+        }
+        Ok(classrooms)
+    }
+
+    pub async fn list_assignments(&self, classroom_id: u64) -> Result<Vec<Assignment>, FetchError> {
         let mut all_assignments = Vec::new();
         let mut page = 1;
 
@@ -128,15 +177,31 @@ impl ClassroomClient {
         Ok(all_assignments)
     }
 
-    pub async fn get_assignment(&self, assignment_id: u64) -> Result<Assignment> {
+    pub async fn get_classroom(&self, classroom_id: u64) -> Result<Classroom, FetchError> {
+        let path = format!("/classrooms/{}", classroom_id);
+        self.get(&path).await
+    }
+
+    pub async fn get_assignment(&self, assignment_id: u64) -> Result<Assignment, FetchError> {
         let path = format!("/assignments/{}", assignment_id);
         self.get(&path).await
     }
 
+    /// Id of the assignment with this slug in the classroom with this name,
+    /// both matched case-insensitively, for scripts that name them instead of
+    /// using numeric ids
+    pub async fn find_assignment(&self, classroom_name: &str, assignment_slug: &str) -> Result<u64, FetchError> {
+        let classrooms = self.list_classrooms().await?;
+        let classroom = find_unique(&classrooms, |c| &c.name, classroom_name, "classroom")?;
+        let assignments = self.list_assignments(classroom.id).await?;
+        let assignment = find_unique(&assignments, |a| &a.slug, assignment_slug, "assignment")?;
+        Ok(assignment.id)
+    }
+
     pub async fn list_accepted_assignments(
         &self,
         assignment_id: u64,
-    ) -> Result<Vec<AcceptedAssignment>> {
+    ) -> Result<Vec<AcceptedAssignment>, FetchError> {
         let mut all_accepted = Vec::new();
         let mut page = 1;
         let per_page = 30; // Smaller page size to avoid timeouts
@@ -152,19 +217,16 @@ impl ClassroomClient {
             let accepted: Vec<AcceptedAssignment> = loop {
                 match self.get(&path).await {
                     Ok(result) => break result,
-                    Err(e) => {
+                    Err(e @ FetchError::Network { .. }) => {
                         retries -= 1;
                         if retries == 0 {
-                            return Err(e).with_context(|| {
-                                format!(
-                                    "Failed to fetch accepted assignments for assignment_id={} after 3 retries",
-                                    assignment_id
-                                )
-                            });
+                            return Err(e);
                         }
                         // Wait a bit before retrying
                         tokio::time::sleep(std::time::Duration::from_secs(2)).await;
                     }
+                    // Retrying won't help with a rejected token or a bad response
+                    Err(e) => return Err(e),
                 }
             };
 
@@ -184,3 +246,148 @@ impl ClassroomClient {
         Ok(all_accepted)
     }
 }
+
+/// The one item whose `key` matches `wanted` ignoring case, or an error
+/// listing the choices if there's none, or saying how many if there are several
+fn find_unique<'a, T>(items: &'a [T], key: impl Fn(&T) -> &str, wanted: &str, what: &'static str) -> Result<&'a T, FetchError> {
+    let wanted_lower = wanted.to_lowercase();
+    let matches: Vec<&T> = items.iter().filter(|item| key(item).to_lowercase() == wanted_lower).collect();
+    match matches[..] {
+        [item] => Ok(item),
+        [] => Err(FetchError::NoMatch {
+            what,
+            wanted: wanted.to_string(),
+            choices: items.iter().map(|item| key(item).to_string()).collect(),
+        }),
+        _ => Err(FetchError::Ambiguous {
+            what,
+            wanted: wanted.to_string(),
+            count: matches.len(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn classroom(id: u64) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "name": format!("Classroom {}", id),
+            "archived": false,
+            "url": format!("https://classroom.github.com/classrooms/{}", id),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_list_classrooms_paginates() {
+        let server = MockServer::start().await;
+        let pages = [vec![classroom(1), classroom(2)], vec![classroom(3)], vec![]];
+        for (page, classrooms) in pages.iter().enumerate() {
+            Mock::given(method("GET"))
+                .and(path("/classrooms"))
+                .and(query_param("page", (page + 1).to_string()))
+                .and(query_param("per_page", "100"))
+                .and(header("authorization", "Bearer test-token"))
+                .and(header("user-agent", "test-agent"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(classrooms))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = ClassroomClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let classrooms = client.list_classrooms().await.unwrap();
+
+        let ids: Vec<u64> = classrooms.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_list_classrooms_for_org() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/classrooms"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json([classroom(1), classroom(2)]))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/classrooms"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(Vec::<serde_json::Value>::new()))
+            .mount(&server)
+            .await;
+        for (id, org) in [(1, "Intro-CS"), (2, "other-org")] {
+            let mut details = classroom(id);
+            details["organization"] = serde_json::json!({ "id": id, "login": org });
+            Mock::given(method("GET"))
+                .and(path(format!("/classrooms/{}", id)))
+                .respond_with(ResponseTemplate::new(200).set_body_json(details))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = ClassroomClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let classrooms = client.list_classrooms_for_org("intro-cs").await.unwrap();
+
+        let ids: Vec<u64> = classrooms.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [1]);
+    }
+
+    #[tokio::test]
+    async fn test_find_assignment() {
+        let server = MockServer::start().await;
+        let mut duplicate = classroom(3);
+        duplicate["name"] = "classroom 2".into();
+        Mock::given(method("GET"))
+            .and(path("/classrooms"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json([classroom(1), classroom(2), duplicate]))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/classrooms/1/assignments"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "id": 11, "title": "Homework 1", "slug": "hw1", "deadline": null, "starter_code_url": null, "classroom": { "id": 1, "name": "Classroom 1" } },
+                { "id": 12, "title": "Homework 2", "slug": "hw2", "deadline": null, "starter_code_url": null, "classroom": { "id": 1, "name": "Classroom 1" } },
+            ])))
+            .mount(&server)
+            .await;
+        for list in ["/classrooms", "/classrooms/1/assignments"] {
+            Mock::given(method("GET"))
+                .and(path(list))
+                .and(query_param("page", "2"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+                .mount(&server)
+                .await;
+        }
+
+        let client = ClassroomClient::new("test-token".to_string(), server.uri(), "test-agent");
+        assert_eq!(client.find_assignment("CLASSROOM 1", "HW2").await.unwrap(), 12);
+
+        let err = client.find_assignment("Classroom 1", "hw3").await.unwrap_err().to_string();
+        assert!(err.contains("the choices are: hw1, hw2"), "{}", err);
+        let err = client.find_assignment("Classroom 2", "hw1").await.unwrap_err().to_string();
+        assert!(err.starts_with("2 classrooms match"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/classrooms"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("Bad credentials"))
+            .mount(&server)
+            .await;
+
+        let client = ClassroomClient::new("expired".to_string(), server.uri(), "test-agent");
+        let err = client.list_classrooms().await.unwrap_err();
+        assert!(matches!(err, FetchError::AuthFailed(_)));
+    }
+}
@@ -1,89 +1,220 @@
-use crate::models::{FileContent, JobsResponse, WorkflowRunsResponse};
-use anyhow::{Context, Result};
+use crate::models::{
+    ApiStatus, CheckRunAnnotation, CheckRunsResponse, FileContent, JobsResponse, RateLimitInfo, Repository, WorkflowRun,
+    WorkflowRunsResponse,
+};
+use crate::error::FetchError;
+use chrono::{DateTime, Utc};
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, USER_AGENT};
+use reqwest::StatusCode;
 use serde::de::DeserializeOwned;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
-const API_BASE: &str = "https://api.github.com";
+/// Pages of workflow runs followed at most. GitHub returns no more than
+/// 1,000 runs for a filtered listing, i.e. 10 pages of 100.
+const MAX_RUN_PAGES: usize = 10;
+
+/// The `rel="next"` URL of a paginated response's `Link` header, if there is a next page
+fn next_page_url(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#)
+            .then(|| url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
+}
 
 #[derive(Clone)]
 pub struct GitHubClient {
     client: reqwest::Client,
-    token: String,
+    /// Base URL of the REST API, without a trailing slash
+    api_base: String,
+    user_agent: HeaderValue,
+    /// Shared between clones so a refreshed token reaches background tasks too
+    token: Arc<RwLock<String>>,
+    rate_limit: Arc<Mutex<Option<RateLimitInfo>>>,
+    /// When the current secondary rate limit backoff ends, if one is running
+    backoff_until: Arc<Mutex<Option<DateTime<Utc>>>>,
+    /// Number of times GitHub's abuse detection has rejected a request
+    secondary_limit_hits: Arc<AtomicUsize>,
 }
 
 impl GitHubClient {
-    pub fn new(token: String) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(120)) // 2 minute timeout
-            .connect_timeout(std::time::Duration::from_secs(30))
-            .build()
-            .expect("Failed to build HTTP client");
-        Self { client, token }
+    pub fn new(token: String, api_base: String, user_agent: &str) -> Self {
+        let client = super::http_client(super::API_TIMEOUT, None);
+        Self {
+            client,
+            api_base,
+            user_agent: HeaderValue::from_str(user_agent).expect("Invalid User-Agent"),
+            token: Arc::new(RwLock::new(token)),
+            rate_limit: Arc::new(Mutex::new(None)),
+            backoff_until: Arc::new(Mutex::new(None)),
+            secondary_limit_hits: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Most recent rate limit figures seen in a response, if any
+    pub fn rate_limit(&self) -> Option<RateLimitInfo> {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    /// Quota and backoff state for the status bar
+    pub fn status(&self) -> ApiStatus {
+        let backoff_until = (*self.backoff_until.lock().unwrap()).filter(|until| *until > Utc::now());
+        ApiStatus {
+            rate_limit: self.rate_limit(),
+            backoff_until,
+        }
+    }
+
+    /// How many times GitHub's abuse detection has kicked in so far
+    pub fn secondary_limit_hits(&self) -> usize {
+        self.secondary_limit_hits.load(Ordering::Relaxed)
     }
 
-    fn build_headers(&self) -> HeaderMap {
+    fn record_rate_limit(&self, headers: &HeaderMap) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<i64>().ok())
+        };
+
+        if let (Some(limit), Some(remaining), Some(reset)) = (
+            header("x-ratelimit-limit"),
+            header("x-ratelimit-remaining"),
+            header("x-ratelimit-reset"),
+        ) && let Some(reset) = chrono::DateTime::from_timestamp(reset, 0)
+        {
+            *self.rate_limit.lock().unwrap() = Some(RateLimitInfo {
+                limit: limit as u32,
+                remaining: remaining as u32,
+                reset,
+            });
+        }
+    }
+
+    /// Send requests through `client`, e.g. one from `api_http_client`
+    /// shared with the other API client
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Replace the token used for all subsequent requests
+    pub fn set_token(&self, token: String) {
+        *self.token.write().unwrap() = token;
+    }
+
+    fn build_headers(&self) -> Result<HeaderMap, FetchError> {
         let mut headers = HeaderMap::new();
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", self.token)).unwrap(),
-        );
+        headers.insert(AUTHORIZATION, super::bearer_header(&self.token.read().unwrap())?);
         headers.insert(
             ACCEPT,
             HeaderValue::from_static("application/vnd.github+json"),
         );
-        headers.insert(
-            USER_AGENT,
-            HeaderValue::from_static("gh-autograder-fetcher"),
-        );
+        headers.insert(USER_AGENT, self.user_agent.clone());
         headers.insert(
             "X-GitHub-Api-Version",
             HeaderValue::from_static("2022-11-28"),
         );
-        headers
+        Ok(headers)
     }
 
-    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
-        let url = format!("{}{}", API_BASE, path);
-        let response = self
-            .client
-            .get(&url)
-            .headers(self.build_headers())
-            .send()
-            .await
-            .context(format!("Failed to send request to {}", url))?;
+    /// Send a GET request, backing off and retrying when GitHub's abuse
+    /// detection (secondary rate limit) rejects it or a transient server error
+    /// (e.g. a 502 from a busy load balancer) comes back
+    async fn send(&self, url: &str) -> Result<reqwest::Response, FetchError> {
+        let mut attempts = 0;
+        let mut server_errors = 0;
+
+        loop {
+            let response = self
+                .client
+                .get(url)
+                .headers(self.build_headers()?)
+                .send()
+                .await
+                .map_err(|source| FetchError::Network {
+                    url: url.to_string(),
+                    source,
+                })?;
+
+            self.record_rate_limit(response.headers());
 
-        let status = response.status();
-        if !status.is_success() {
+            let status = response.status();
+            if status.is_success() {
+                return Ok(response);
+            }
+
+            let headers = response.headers().clone();
             let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("API request failed with status {}: {}", status, error_text);
+            if status == StatusCode::UNAUTHORIZED {
+                return Err(FetchError::AuthFailed(error_text));
+            }
+
+            if let Some(delay) = super::secondary_rate_limit_delay(status, &headers, &error_text) {
+                attempts += 1;
+                if attempts > super::MAX_SECONDARY_RETRIES {
+                    return Err(FetchError::RateLimited);
+                }
+                self.secondary_limit_hits.fetch_add(1, Ordering::Relaxed);
+                let until = Utc::now() + delay;
+                {
+                    // Concurrent requests may be backing off too; show the longest wait
+                    let mut backoff_until = self.backoff_until.lock().unwrap();
+                    if backoff_until.is_none_or(|current| current < until) {
+                        *backoff_until = Some(until);
+                    }
+                }
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+
+            if status.is_server_error() && server_errors < super::MAX_SERVER_ERROR_RETRIES {
+                server_errors += 1;
+                tokio::time::sleep(super::server_error_delay(server_errors)).await;
+                continue;
+            }
+
+            return Err(FetchError::from_status(url, status, error_text));
         }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, FetchError> {
+        let url = format!("{}{}", self.api_base, path);
+        let response = self.send(&url).await?;
 
         response
             .json()
             .await
-            .context("Failed to parse JSON response")
+            .map_err(|e| FetchError::parse(format!("JSON response from {}", url), e))
     }
 
-    /// Get file contents from a repository
-    pub async fn get_file_contents(&self, owner: &str, repo: &str, path: &str) -> Result<String> {
+    /// Get the raw bytes of a file in a repository; decoding them is up to the
+    /// caller, since student files aren't always valid UTF-8
+    pub async fn get_file_bytes(&self, owner: &str, repo: &str, path: &str) -> Result<Vec<u8>, FetchError> {
         let api_path = format!("/repos/{}/{}/contents/{}", owner, repo, path);
         let file_content: FileContent = self.get(&api_path).await?;
 
         // GitHub API returns base64-encoded content
         if file_content.encoding == "base64" {
-            let decoded = base64::Engine::decode(
+            base64::Engine::decode(
                 &base64::engine::general_purpose::STANDARD,
                 file_content.content.replace('\n', ""),
             )
-            .context("Failed to decode base64 content")?;
-
-            String::from_utf8(decoded).context("File content is not valid UTF-8")
+            .map_err(|e| FetchError::parse(format!("base64 content of {}", path), e))
         } else {
-            Ok(file_content.content)
+            Ok(file_content.content.into_bytes())
         }
     }
 
-    /// List workflow runs for a repository
+    /// List workflow runs for a repository, newest first. Further pages are
+    /// followed through the `Link` header until a page has a run matching
+    /// `found` or there are no more runs, so students who re-ran their
+    /// workflow hundreds of times don't lose older runs.
     pub async fn list_workflow_runs(
         &self,
         owner: &str,
@@ -91,7 +222,8 @@ impl GitHubClient {
         event: Option<&str>,
         created: Option<&str>,
         status: Option<&str>,
-    ) -> Result<WorkflowRunsResponse> {
+        found: impl Fn(&WorkflowRun) -> bool,
+    ) -> Result<WorkflowRunsResponse, FetchError> {
         let mut path = format!("/repos/{}/{}/actions/runs?per_page=100", owner, repo);
 
         if let Some(event) = event {
@@ -104,7 +236,29 @@ impl GitHubClient {
             path.push_str(&format!("&status={}", status));
         }
 
-        self.get(&path).await
+        let mut url = format!("{}{}", self.api_base, path);
+        let mut runs = WorkflowRunsResponse {
+            total_count: 0,
+            workflow_runs: Vec::new(),
+        };
+        for _ in 0..MAX_RUN_PAGES {
+            let response = self.send(&url).await?;
+            let next = next_page_url(response.headers());
+            let page: WorkflowRunsResponse = response
+                .json()
+                .await
+                .map_err(|e| FetchError::parse(format!("JSON response from {}", url), e))?;
+
+            runs.total_count = page.total_count;
+            let done = page.workflow_runs.iter().any(&found);
+            runs.workflow_runs.extend(page.workflow_runs);
+            match next {
+                Some(next) if !done => url = next,
+                _ => break,
+            }
+        }
+
+        Ok(runs)
     }
 
     /// Get jobs for a workflow run
@@ -113,36 +267,234 @@ impl GitHubClient {
         owner: &str,
         repo: &str,
         run_id: u64,
-    ) -> Result<JobsResponse> {
+    ) -> Result<JobsResponse, FetchError> {
         let path = format!("/repos/{}/{}/actions/runs/{}/jobs", owner, repo, run_id);
         self.get(&path).await
     }
 
+    /// Get a repository, e.g. for when it was last pushed to
+    pub async fn get_repository(&self, owner: &str, repo: &str) -> Result<Repository, FetchError> {
+        let path = format!("/repos/{}/{}", owner, repo);
+        self.get(&path).await
+    }
+
+    /// List the check runs for a commit (or branch or tag)
+    pub async fn list_check_runs_for_ref(
+        &self,
+        owner: &str,
+        repo: &str,
+        git_ref: &str,
+    ) -> Result<CheckRunsResponse, FetchError> {
+        let path = format!("/repos/{}/{}/commits/{}/check-runs?per_page=100", owner, repo, git_ref);
+        self.get(&path).await
+    }
+
+    /// List the annotations of a check run (for Actions, the job id)
+    pub async fn list_check_run_annotations(
+        &self,
+        owner: &str,
+        repo: &str,
+        check_run_id: u64,
+    ) -> Result<Vec<CheckRunAnnotation>, FetchError> {
+        let path = format!("/repos/{}/{}/check-runs/{}/annotations?per_page=100", owner, repo, check_run_id);
+        self.get(&path).await
+    }
+
     /// Get logs for a job
     pub async fn get_job_logs(
         &self,
         owner: &str,
         repo: &str,
         job_id: u64,
-    ) -> Result<String> {
-        let url = format!("{}/repos/{}/{}/actions/jobs/{}/logs", API_BASE, owner, repo, job_id);
-        let response = self
-            .client
-            .get(&url)
-            .headers(self.build_headers())
-            .send()
+    ) -> Result<String, FetchError> {
+        let url = format!("{}/repos/{}/{}/actions/jobs/{}/logs", self.api_base, owner, repo, job_id);
+        let response = self.send(&url).await?;
+
+        response
+            .text()
             .await
-            .context(format!("Failed to send request to {}", url))?;
+            .map_err(|source| FetchError::Network { url, source })
+    }
+}
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("API request failed with status {}: {}", status, error_text);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_get_file_bytes_decodes_base64() {
+        let server = MockServer::start().await;
+        // GitHub wraps base64 content at 60 characters
+        let encoded = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            "name: Autograding Tests\non: [push, repository_dispatch]\n",
+        );
+        let (first, second) = encoded.split_at(40);
+        Mock::given(method("GET"))
+            .and(path("/repos/org/starter/contents/.github/workflows/classroom.yml"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "classroom.yml",
+                "path": ".github/workflows/classroom.yml",
+                "sha": "abc123",
+                "size": 56,
+                "content": format!("{}\n{}\n", first, second),
+                "encoding": "base64",
+            })))
+            .mount(&server)
+            .await;
+
+        let client = GitHubClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let content = client
+            .get_file_bytes("org", "starter", ".github/workflows/classroom.yml")
+            .await
+            .unwrap();
+        assert_eq!(content, b"name: Autograding Tests\non: [push, repository_dispatch]\n");
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_are_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/org/repo/actions/runs/1/jobs"))
+            .respond_with(ResponseTemplate::new(502).set_body_string("Bad Gateway"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/org/repo/actions/runs/1/jobs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "total_count": 0, "jobs": [] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = GitHubClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let jobs = client.list_jobs_for_run("org", "repo", 1).await.unwrap();
+        assert_eq!(jobs.total_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_missing_file_is_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/org/starter/contents/.github/workflows/classroom.yml"))
+            .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"message":"Not Found"}"#))
+            .mount(&server)
+            .await;
+
+        let client = GitHubClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let err = client
+            .get_file_bytes("org", "starter", ".github/workflows/classroom.yml")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FetchError::NotFound { .. }), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_list_workflow_runs_query() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/org/repo-1/actions/runs"))
+            .and(query_param("per_page", "100"))
+            .and(query_param("event", "repository_dispatch"))
+            .and(query_param("created", ">=2025-01-15T10:00:00Z"))
+            .and(query_param("status", "completed"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "total_count": 0,
+                "workflow_runs": [],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/org/repo-1/actions/runs"))
+            .and(query_param_is_missing("created"))
+            .and(query_param_is_missing("status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "total_count": 0,
+                "workflow_runs": [],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = GitHubClient::new("test-token".to_string(), server.uri(), "test-agent");
+        client
+            .list_workflow_runs(
+                "org",
+                "repo-1",
+                Some("repository_dispatch"),
+                Some(">=2025-01-15T10:00:00Z"),
+                Some("completed"),
+                |_| false,
+            )
+            .await
+            .unwrap();
+        client
+            .list_workflow_runs("org", "repo-1", None, None, None, |_| false)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_workflow_runs_follows_link_header() {
+        let server = MockServer::start().await;
+        let run = |id: u64, branch: &str| {
+            serde_json::json!({
+                "id": id,
+                "name": "GitHub Classroom Workflow",
+                "head_branch": branch,
+                "head_sha": format!("{:040x}", id),
+                "status": "completed",
+                "conclusion": "success",
+                "created_at": "2025-03-01T12:00:00Z",
+                "updated_at": "2025-03-01T12:00:00Z",
+                "run_started_at": null,
+                "event": "repository_dispatch",
+            })
+        };
+        let pages = [vec![run(3, "feature")], vec![run(2, "main")], vec![run(1, "main")]];
+        for (page, runs) in pages.iter().enumerate() {
+            let page = page + 1;
+            let mut response = ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "total_count": 3,
+                "workflow_runs": runs,
+            }));
+            if page < pages.len() {
+                response = response.insert_header(
+                    "link",
+                    format!(
+                        r#"<{}/repos/org/repo-1/actions/runs?per_page=100&page={}>; rel="next", <{}/repos/org/repo-1/actions/runs?per_page=100&page=3>; rel="last""#,
+                        server.uri(),
+                        page + 1,
+                        server.uri()
+                    ),
+                );
+            }
+            let mock = Mock::given(method("GET")).and(path("/repos/org/repo-1/actions/runs"));
+            let mock = if page == 1 {
+                mock.and(query_param_is_missing("page"))
+            } else {
+                mock.and(query_param("page", page.to_string()))
+            };
+            mock.respond_with(response).mount(&server).await;
         }
 
-        response
-            .text()
+        let client = GitHubClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let ids = |runs: WorkflowRunsResponse| runs.workflow_runs.iter().map(|r| r.id).collect::<Vec<_>>();
+
+        // Stops at the first page with a run on main, or reads every page
+        let runs = client
+            .list_workflow_runs("org", "repo-1", None, None, None, |r| r.head_branch == "main")
+            .await
+            .unwrap();
+        assert_eq!(ids(runs), [3, 2]);
+        let runs = client
+            .list_workflow_runs("org", "repo-1", None, None, None, |_| false)
             .await
-            .context("Failed to read log text")
+            .unwrap();
+        assert_eq!(ids(runs), [3, 2, 1]);
     }
 }
@@ -1,5 +1,146 @@
 mod classroom;
 mod github;
+pub mod oauth;
 
 pub use classroom::ClassroomClient;
 pub use github::GitHubClient;
+pub use oauth::DeviceFlowClient;
+
+use crate::error::FetchError;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// GitHub REST API used unless a profile or GITHUB_API_URL points elsewhere
+pub const DEFAULT_API_BASE: &str = "https://api.github.com";
+
+/// User-Agent sent unless USER_AGENT or a profile sets one
+pub const DEFAULT_USER_AGENT: &str = concat!("gh-autograder-fetcher/", env!("CARGO_PKG_VERSION"));
+
+/// Backoff attempts for a single request before giving up on the secondary rate limit
+const MAX_SECONDARY_RETRIES: u32 = 3;
+
+/// Retries for a single request that GitHub answered with a 5xx server error
+const MAX_SERVER_ERROR_RETRIES: u32 = 3;
+
+/// Wait before retry `attempt` (from 1) after a server error: 0.5s, 1s, 2s, ...
+fn server_error_delay(attempt: u32) -> Duration {
+    Duration::from_millis(500 << (attempt - 1).min(6))
+}
+
+/// Timeout for a whole Classroom or GitHub API request
+const API_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Start building the HTTP client behind an API client. Without `proxy`,
+/// reqwest picks up HTTPS_PROXY, HTTP_PROXY, and ALL_PROXY from the
+/// environment, skipping hosts in NO_PROXY.
+fn http_client_builder(timeout: Duration, proxy: Option<reqwest::Proxy>) -> reqwest::ClientBuilder {
+    let mut builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .connect_timeout(Duration::from_secs(30));
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy);
+    }
+    builder
+}
+
+fn http_client(timeout: Duration, proxy: Option<reqwest::Proxy>) -> reqwest::Client {
+    http_client_builder(timeout, proxy)
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+/// HTTP client for both the Classroom and the GitHub API client. They talk to
+/// the same host, so sharing one connection pool (clones of a client do)
+/// saves a TLS handshake per connection. Enough connections stay open for
+/// `concurrency` student fetches plus a Classroom request at the same time.
+pub fn api_http_client(proxy: Option<reqwest::Proxy>, concurrency: usize) -> reqwest::Client {
+    http_client_builder(API_TIMEOUT, proxy)
+        .pool_max_idle_per_host(concurrency + 1)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+/// Reject a token that can't be sent in a header: GitHub tokens are visible ASCII
+pub fn check_token(token: &str) -> Result<(), FetchError> {
+    if token.chars().all(|c| c.is_ascii_graphic()) {
+        Ok(())
+    } else {
+        Err(FetchError::InvalidToken)
+    }
+}
+
+/// The Authorization header value sent with `token`
+fn bearer_header(token: &str) -> Result<HeaderValue, FetchError> {
+    check_token(token)?;
+    HeaderValue::from_str(&format!("Bearer {}", token)).map_err(|_| FetchError::InvalidToken)
+}
+
+/// Whether an error (or anything it wraps) is a rejected token
+pub fn is_unauthorized(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<FetchError>(), Some(FetchError::AuthFailed(_)))
+}
+
+/// How long to back off if this response is GitHub's secondary rate limit: the
+/// `Retry-After` header, else until `x-ratelimit-reset` if the quota is used up,
+/// else one minute as GitHub recommends
+fn secondary_rate_limit_delay(status: StatusCode, headers: &HeaderMap, body: &str) -> Option<Duration> {
+    let is_secondary = (status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS)
+        && body.to_lowercase().contains("secondary rate limit");
+    if !is_secondary {
+        return None;
+    }
+
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<i64>().ok())
+    };
+
+    let seconds = match (header("retry-after"), header("x-ratelimit-remaining"), header("x-ratelimit-reset")) {
+        (Some(retry_after), _, _) => retry_after,
+        (None, Some(0), Some(reset)) => reset - chrono::Utc::now().timestamp(),
+        _ => 60,
+    };
+
+    Some(Duration::from_secs(seconds.max(1) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secondary_rate_limit_delay() {
+        let body = r#"{"message":"You have exceeded a secondary rate limit. Please wait a few minutes before you try again."}"#;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("30"));
+        assert_eq!(
+            secondary_rate_limit_delay(StatusCode::FORBIDDEN, &headers, body),
+            Some(Duration::from_secs(30))
+        );
+
+        // Without Retry-After, fall back to a minute
+        assert_eq!(
+            secondary_rate_limit_delay(StatusCode::FORBIDDEN, &HeaderMap::new(), body),
+            Some(Duration::from_secs(60))
+        );
+
+        // Other 403s (e.g. missing permissions) are reported as-is
+        assert_eq!(
+            secondary_rate_limit_delay(StatusCode::FORBIDDEN, &headers, r#"{"message":"Resource not accessible"}"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_bearer_header_rejects_unsendable_tokens() {
+        assert_eq!(bearer_header("ghp_abc123").unwrap(), "Bearer ghp_abc123");
+        for token in ["ghp_abc 123", "ghp_abc\n", "ghp_abcé"] {
+            assert!(matches!(bearer_header(token), Err(FetchError::InvalidToken)));
+        }
+    }
+}
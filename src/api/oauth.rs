@@ -0,0 +1,154 @@
+use crate::models::{AccessTokenResponse, DeviceCode};
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderValue, ACCEPT, USER_AGENT};
+use std::time::Duration;
+
+const DEVICE_CODE_URL: &str = "https://github.com/login/device/code";
+const ACCESS_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+const DEVICE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Scopes needed to read classrooms (read:org) and student repos/Actions data (repo)
+const SCOPES: &str = "read:org repo";
+
+/// Service and user names for the token entry in the OS credential store
+const KEYRING_SERVICE: &str = "gh_autograder_fetcher";
+const KEYRING_USER: &str = "github_token";
+
+/// Signs in through GitHub's OAuth device authorization flow
+#[derive(Clone)]
+pub struct DeviceFlowClient {
+    client: reqwest::Client,
+    client_id: String,
+}
+
+impl DeviceFlowClient {
+    pub fn new(client_id: String) -> Self {
+        let client = super::http_client(Duration::from_secs(30), None);
+        Self { client, client_id }
+    }
+
+    /// Send all requests through this proxy instead of any from the environment
+    pub fn with_proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.client = super::http_client(Duration::from_secs(30), Some(proxy));
+        self
+    }
+
+    /// Start a sign-in and get the code the user has to enter on GitHub
+    pub async fn request_device_code(&self) -> Result<DeviceCode> {
+        let response = self
+            .client
+            .post(DEVICE_CODE_URL)
+            .header(ACCEPT, HeaderValue::from_static("application/json"))
+            .header(USER_AGENT, HeaderValue::from_static(super::DEFAULT_USER_AGENT))
+            .form(&[("client_id", self.client_id.as_str()), ("scope", SCOPES)])
+            .send()
+            .await
+            .context("Failed to request device code")?;
+
+        let status = response.status();
+        let body = response.text().await.context("Failed to get response text")?;
+        if !status.is_success() {
+            anyhow::bail!("Device code request failed with status {}: {}", status, body);
+        }
+
+        serde_json::from_str(&body)
+            .with_context(|| format!("Failed to parse device code response: {}", body))
+    }
+
+    /// Poll until the user approves (or denies) the sign-in, returning the access token
+    pub async fn poll_for_token(&self, device_code: &DeviceCode) -> Result<String> {
+        let mut interval = device_code.interval.max(1);
+        let expires_at = std::time::Instant::now() + Duration::from_secs(device_code.expires_in);
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+
+            if std::time::Instant::now() >= expires_at {
+                anyhow::bail!("The login code expired before it was entered. Please try again.");
+            }
+
+            let response: AccessTokenResponse = self
+                .client
+                .post(ACCESS_TOKEN_URL)
+                .header(ACCEPT, HeaderValue::from_static("application/json"))
+                .header(USER_AGENT, HeaderValue::from_static(super::DEFAULT_USER_AGENT))
+                .form(&[
+                    ("client_id", self.client_id.as_str()),
+                    ("device_code", device_code.device_code.as_str()),
+                    ("grant_type", DEVICE_GRANT_TYPE),
+                ])
+                .send()
+                .await
+                .context("Failed to poll for access token")?
+                .json()
+                .await
+                .context("Failed to parse access token response")?;
+
+            if let Some(token) = response.access_token {
+                return Ok(token);
+            }
+
+            match response.error.as_deref() {
+                Some("authorization_pending") => {}
+                Some("slow_down") => {
+                    interval = response.interval.unwrap_or(interval + 5);
+                }
+                Some("expired_token") => {
+                    anyhow::bail!("The login code expired before it was entered. Please try again.")
+                }
+                Some("access_denied") => anyhow::bail!("Login was cancelled on GitHub"),
+                Some(error) => anyhow::bail!(
+                    "Login failed: {}",
+                    response.error_description.as_deref().unwrap_or(error)
+                ),
+                None => anyhow::bail!("Unexpected access token response from GitHub"),
+            }
+        }
+    }
+}
+
+fn keyring_entry() -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).context("Failed to open credential store")
+}
+
+/// Token saved by a previous `--login`, if the credential store has one
+pub fn stored_token() -> Option<String> {
+    keyring_entry().ok()?.get_password().ok()
+}
+
+/// Token of the `gh` CLI's logged-in account, for `hostname` (GitHub Enterprise) or github.com
+pub fn gh_cli_token(hostname: Option<&str>) -> Result<String> {
+    let mut command = std::process::Command::new("gh");
+    command.args(["auth", "token"]);
+    if let Some(hostname) = hostname {
+        command.args(["--hostname", hostname]);
+    }
+
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!("The gh CLI isn't installed (see https://cli.github.com), set GITHUB_TOKEN instead")
+        }
+        Err(e) => return Err(e).context("Failed to run `gh auth token`"),
+    };
+    if !output.status.success() {
+        anyhow::bail!(
+            "gh isn't logged in, run `gh auth login` first: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let token = String::from_utf8(output.stdout).context("`gh auth token` printed invalid UTF-8")?;
+    let token = token.trim();
+    if token.is_empty() {
+        anyhow::bail!("`gh auth token` printed no token, run `gh auth login` first");
+    }
+    Ok(token.to_string())
+}
+
+/// Save a token in the OS credential store for later runs
+pub fn store_token(token: &str) -> Result<()> {
+    keyring_entry()?
+        .set_password(token)
+        .context("Failed to save token to credential store")
+}
@@ -0,0 +1,244 @@
+use crate::export::{DecimalSeparator, ExportFormat, Rounding, SortBy};
+use crate::fetcher::{InterruptedRuns, RunPolicy};
+use clap::{ArgGroup, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+#[command(group(ArgGroup::new("headless").args(["assignment_id", "assignment_slug"])))]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Read settings from this TOML file instead of ./gh_autograder_fetcher.toml
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Use the token, API base, time zone, output directory, and User-Agent from
+    /// `[profiles.<NAME>]` in the config file, overriding environment variables
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Output file formats for exported results (comma-separated, e.g. csv,json),
+    /// all written from the same results
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub format: Option<Vec<ExportFormat>>,
+
+    /// Export the latest results of this assignment without the terminal UI and
+    /// print the results file paths, e.g. for scripts
+    #[arg(long, value_name = "ID", conflicts_with_all = ["all_assignments", "login"])]
+    pub assignment_id: Option<u64>,
+
+    /// Like --assignment-id, but find the assignment by its slug (e.g. hw1) in
+    /// the classroom named by --classroom-name, ignoring case
+    #[arg(long, value_name = "SLUG", requires = "classroom_name", conflicts_with_all = ["all_assignments", "login"])]
+    pub assignment_slug: Option<String>,
+
+    /// Name of the classroom to look up --assignment-slug in, ignoring case
+    #[arg(long, value_name = "NAME", requires = "assignment_slug")]
+    pub classroom_name: Option<String>,
+
+    /// With --assignment-id, print only the results file paths: no status log on stderr
+    #[arg(long, short, requires = "headless")]
+    pub quiet: bool,
+
+    /// With --assignment-id, print a JSON summary (stats and failed students)
+    /// on stdout instead of the file paths, e.g. to fail a CI job on errors.
+    /// It names students, so it can't be combined with --anonymize
+    #[arg(long, requires = "headless", conflicts_with = "anonymize")]
+    pub summary_json: bool,
+
+    /// Only grade and export these tests (comma-separated test names)
+    #[arg(long, value_delimiter = ',')]
+    pub tests: Option<Vec<String>>,
+
+    /// Only grade and export this student (GitHub username), e.g. for a grade dispute
+    #[arg(long)]
+    pub student: Option<String>,
+
+    /// Leave these students out (comma-separated GitHub usernames), e.g. staff
+    /// accounts used to test the assignment; added to the config file's `exclude`
+    #[arg(long, value_name = "LOGINS", value_delimiter = ',')]
+    pub exclude: Vec<String>,
+
+    /// Only list the classrooms of this GitHub organization (ignoring case);
+    /// overrides the config file's `org`
+    #[arg(long, value_name = "ORG")]
+    pub org: Option<String>,
+
+    /// Download the latest results of every assignment in the chosen classroom,
+    /// plus a combined file with one row per student
+    #[arg(long)]
+    pub all_assignments: bool,
+
+    /// Grade workflow runs on this branch (e.g. a `submission` branch) instead of
+    /// each student repo's default branch
+    #[arg(long)]
+    pub branch: Option<String>,
+
+    /// Only fetch students Classroom doesn't already report as passing, e.g. to
+    /// check on stragglers in a mastery-based course (regular grading only)
+    #[arg(long)]
+    pub skip_passing: bool,
+
+    /// Warn if the starter repo's workflow has different tests than the first
+    /// graded student's run, e.g. after the starter was edited
+    #[arg(long)]
+    pub check_workflow: bool,
+
+    /// Only re-fetch students who pushed since the last run with this flag, reusing
+    /// the saved results of the rest (regular grading only)
+    #[arg(long)]
+    pub changed_only: bool,
+
+    /// Only fetch the first N students, e.g. to try out settings on a new classroom
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_students: Option<u64>,
+
+    /// When a student who submitted has no workflow run yet (GitHub can lag right
+    /// after a deadline), look again this many times, 10 seconds apart
+    #[arg(long, value_name = "RETRIES", num_args = 0..=1, default_missing_value = "3")]
+    pub wait_for_runs: Option<u32>,
+
+    /// Also write results_<assignment>_<timestamp>.jsonl, appending each student's
+    /// result as soon as it's fetched (regular grading only)
+    #[arg(long, conflicts_with = "anonymize")]
+    pub stream: bool,
+
+    /// Add scaled_points/scaled_available columns with each total rescaled to be
+    /// out of N points, keeping the percentage (raw columns are kept too)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub scale_to: Option<u32>,
+
+    /// TOML file of test name = weight, adding a weighted_percentage column that
+    /// counts each test by its weight instead of its points (regular grading only)
+    #[arg(long, value_name = "FILE", conflicts_with = "all_assignments")]
+    pub weights: Option<PathBuf>,
+
+    /// Give every test that ran (passed or failed) at least N points, or only
+    /// TEST=N for one test; skipped and missing tests stay at 0. Comma-separated
+    /// or repeated, e.g. `--min-points 1,test_style=2`
+    #[arg(long, value_name = "N|TEST=N", value_delimiter = ',')]
+    pub min_points: Option<Vec<String>>,
+
+    /// After fetching, compare each student's total with this earlier results
+    /// file and list the students whose total changed, e.g. to catch flaky
+    /// tests before publishing. Headless runs fail if more than --max-drift changed.
+    /// Students are matched by username, so it can't be combined with --anonymize
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["all_assignments", "anonymize"])]
+    pub baseline: Option<PathBuf>,
+
+    /// Students whose total may differ from --baseline before the run fails
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "baseline")]
+    pub max_drift: usize,
+
+    /// Write the assignment's roster (its accepted assignments) to this JSON
+    /// file, for --load-roster on later runs
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["load_roster", "all_assignments"])]
+    pub save_roster: Option<PathBuf>,
+
+    /// Read the roster from a --save-roster file instead of the Classroom API
+    #[arg(long, value_name = "PATH", conflicts_with = "all_assignments")]
+    pub load_roster: Option<PathBuf>,
+
+    /// Grade each student's last N completed runs instead of one and combine them
+    /// with --run-policy, for flaky tests; adds runs_considered and score_variance
+    /// columns (regular grading only)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(2..))]
+    pub runs: Option<u32>,
+
+    /// How --runs combines the graded runs: the run with the most points, or each
+    /// test's points averaged and rounded
+    #[arg(long, value_enum, default_value_t, requires = "runs")]
+    pub run_policy: RunPolicy,
+
+    /// Don't download job logs and give each test all or none of its points by
+    /// whether its step passed: faster, but partial credit is lost
+    #[arg(long)]
+    pub no_logs: bool,
+
+    /// How to treat a graded run that was cancelled, timed out, etc.: grade the
+    /// tests that finished (the rest score 0), or leave the student out and
+    /// report them as needing manual review
+    #[arg(long, value_enum, default_value_t)]
+    pub interrupted_runs: InterruptedRuns,
+
+    /// How --scale-to rounds scaled scores to whole points
+    #[arg(long, value_enum, default_value_t, requires = "scale_to")]
+    pub scale_rounding: Rounding,
+
+    /// Add commit_sha and run_url columns identifying the workflow run each score came from,
+    /// and a failure_messages column to regular exports
+    #[arg(long, conflicts_with = "anonymize")]
+    pub run_details: bool,
+
+    /// Add needs_review and review_reason columns flagging suspicious scores, such as
+    /// more points than available or 0 points after at least RUNS runs (regular grading only)
+    #[arg(long, value_name = "RUNS", num_args = 0..=1, default_missing_value = "5")]
+    pub needs_review: Option<usize>,
+
+    /// Order rows of results files by score (highest first), name, or username
+    /// instead of roster order; ties are ordered by username
+    #[arg(long, value_enum)]
+    pub sort_by: Option<SortBy>,
+
+    /// Columns to export, in order (comma-separated, e.g. `student_username,tests,percentage`),
+    /// replacing the config file's `column_mapping.columns` for this run
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    pub columns: Option<Vec<String>>,
+
+    /// Add a tag column with this value to every exported row, e.g. a cohort
+    /// name, to tell rows apart after concatenating many exports
+    #[arg(long, value_name = "TAG")]
+    pub tag: Option<String>,
+
+    /// Decimal mark for numbers in CSV files; `comma` also separates fields
+    /// with semicolons, as Excel expects in much of Europe
+    #[arg(long, value_enum)]
+    pub decimal_separator: Option<DecimalSeparator>,
+
+    /// Replace results files of the same name instead of adding a _2, _3, ... suffix
+    #[arg(long, conflicts_with = "append_suffix")]
+    pub overwrite: bool,
+
+    /// Add a _2, _3, ... suffix when a results file of the same name exists (the default)
+    #[arg(long)]
+    pub append_suffix: bool,
+
+    /// Replace student usernames, names, and repo URLs with pseudonyms like student_001
+    #[arg(long)]
+    pub anonymize: bool,
+
+    /// With --anonymize, also write a mapping file to de-anonymize later
+    #[arg(long, requires = "anonymize")]
+    pub keep_mapping: bool,
+
+    /// Log in through GitHub in the browser (OAuth device flow) and save the token
+    #[arg(long)]
+    pub login: bool,
+
+    /// Use the gh CLI's token (`gh auth token`) when GITHUB_TOKEN isn't set
+    #[arg(long)]
+    pub use_gh_cli: bool,
+
+    /// Client id of the GitHub OAuth app used by --login (overrides GITHUB_CLIENT_ID)
+    #[arg(long)]
+    pub client_id: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Compare two exported result CSVs and report who improved or regressed
+    Diff {
+        /// Results from the earlier grading run
+        old: PathBuf,
+        /// Results from the later grading run
+        new: PathBuf,
+    },
+    /// Check that a local autograder workflow (e.g. .github/workflows/classroom.yml)
+    /// parses, and list the tests it defines
+    ValidateWorkflow {
+        /// Path to the workflow YAML file
+        path: PathBuf,
+    },
+}
@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// File in the working directory that remembers the deadlines entered for
+/// each assignment, so the deadline screens can offer them again
+pub const SAVED_DEADLINES_FILE: &str = ".gh_autograder_fetcher_deadlines.json";
+
+/// Late grading settings as last entered for an assignment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LateGradingDeadlines {
+    pub on_time_deadline: DateTime<Utc>,
+    pub late_deadline: DateTime<Utc>,
+    /// Percentage (0-100), as entered
+    pub late_penalty: f64,
+}
+
+/// Deadlines last used for one assignment, which often differ from its
+/// Classroom deadline because of extensions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssignmentDeadlines {
+    /// Deadline of "Download Results After Deadline"
+    pub deadline: Option<DateTime<Utc>>,
+    pub late_grading: Option<LateGradingDeadlines>,
+}
+
+/// Remembered deadlines by assignment id
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SavedDeadlines(HashMap<u64, AssignmentDeadlines>);
+
+impl SavedDeadlines {
+    /// Read the saved deadlines, or none if the file doesn't exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read saved deadlines {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse saved deadlines {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write saved deadlines {}", path.display()))
+    }
+
+    pub fn get(&self, assignment_id: u64) -> AssignmentDeadlines {
+        self.0.get(&assignment_id).cloned().unwrap_or_default()
+    }
+
+    /// Change one assignment's deadlines in the file at `path`
+    pub fn update(path: &Path, assignment_id: u64, change: impl FnOnce(&mut AssignmentDeadlines)) -> Result<()> {
+        let mut saved = Self::load(path)?;
+        change(saved.0.entry(assignment_id).or_default());
+        saved.save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_keeps_other_settings() {
+        let path = std::env::temp_dir().join(format!("deadlines_test_{}.json", std::process::id()));
+        let deadline = "2025-03-01T23:59:00Z".parse().unwrap();
+        let late_grading = LateGradingDeadlines {
+            on_time_deadline: deadline,
+            late_deadline: "2025-03-08T23:59:00Z".parse().unwrap(),
+            late_penalty: 15.0,
+        };
+
+        SavedDeadlines::update(&path, 7, |d| d.deadline = Some(deadline)).unwrap();
+        SavedDeadlines::update(&path, 7, |d| d.late_grading = Some(late_grading.clone())).unwrap();
+        let saved = SavedDeadlines::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(
+            saved.get(7),
+            AssignmentDeadlines {
+                deadline: Some(deadline),
+                late_grading: Some(late_grading),
+            }
+        );
+        assert_eq!(saved.get(8), AssignmentDeadlines::default());
+    }
+}
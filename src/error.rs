@@ -0,0 +1,192 @@
+use reqwest::StatusCode;
+
+/// Why talking to GitHub, or making sense of what it returned, failed.
+/// Returned by the API clients and the fetcher so callers can react to the
+/// kind of failure (re-auth, back off, skip a student) rather than its text.
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    /// GitHub rejected the token (HTTP 401), e.g. because it expired
+    #[error("GitHub rejected the token (401 Unauthorized): {0}")]
+    AuthFailed(String),
+
+    /// GitHub's abuse detection (secondary rate limit) still rejected a
+    /// request after backing off several times
+    #[error("Hit GitHub abuse detection (secondary rate limit) repeatedly; wait a few minutes or lower CONCURRENCY")]
+    RateLimited,
+
+    #[error("Not found (404): {url}")]
+    NotFound { url: String },
+
+    /// Any other unsuccessful response
+    #[error("API request to {url} failed with status {status}: {body}")]
+    Api {
+        url: String,
+        status: StatusCode,
+        body: String,
+    },
+
+    /// The request never got a complete response (connection, timeout, ...)
+    #[error("Failed to send request to {url}")]
+    Network {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// A response or file couldn't be decoded, e.g. unexpected JSON or an invalid workflow
+    #[error("Failed to parse {what}")]
+    ParseFailed {
+        what: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// The student has no completed workflow run to grade
+    #[error("No completed workflow run found for {username}")]
+    NoRuns { username: String, repo_url: String },
+
+    /// The student's relevant workflow run has not finished yet
+    #[error("Grading in progress for {username} (run is {status})")]
+    InProgress { username: String, status: String },
+
+    /// The graded run was cancelled, timed out, etc., and `--interrupted-runs review` was given
+    #[error("Graded run for {username} ended as {conclusion}; needs manual review")]
+    RunInterrupted { username: String, conclusion: String },
+
+    /// The token can't see the organization's student repositories, e.g. because
+    /// it was never authorized for the organization's SSO
+    #[error("The token has no access to {org}'s repositories (tried {repos}); make sure it has the repo scope and is authorized for the {org} organization")]
+    NoRepoAccess { org: String, repos: String },
+
+    /// The token has characters other than visible ASCII, so it can't be sent
+    /// in a header, e.g. a space or a symbol picked up when pasting it
+    #[error("The token may only contain visible ASCII characters; check for spaces or symbols picked up when pasting it")]
+    InvalidToken,
+
+    /// No student has accepted the assignment, so there is nothing to grade
+    #[error("No students have accepted this assignment yet")]
+    NoAcceptedAssignments,
+
+    /// Tests named in `listed_in` (e.g. `--tests`) that the workflow doesn't define
+    #[error("Unknown test name(s) in {listed_in}: {}. Available tests: {}", .unknown.join(", "), .available.join(", "))]
+    UnknownTests {
+        listed_in: String,
+        unknown: Vec<String>,
+        available: Vec<String>,
+    },
+
+    /// The `--student` to grade isn't on the roster
+    #[error("Student '{username}' has not accepted this assignment")]
+    UnknownStudent { username: String },
+
+    /// A classroom or assignment name that matches none of the choices
+    #[error("No {what} matches '{wanted}'; the choices are: {}", .choices.join(", "))]
+    NoMatch {
+        what: &'static str,
+        wanted: String,
+        choices: Vec<String>,
+    },
+
+    /// A classroom or assignment name that matches several of the choices
+    #[error("{count} {what}s match '{wanted}'; use --assignment-id to pick one")]
+    Ambiguous {
+        what: &'static str,
+        wanted: String,
+        count: usize,
+    },
+
+    /// A student repository name that isn't `owner/repo`
+    #[error("Invalid repository name: {name}")]
+    InvalidRepo { name: String },
+
+    /// A result's run URL that doesn't end in `/actions/runs/{id}`
+    #[error("Invalid workflow run URL: {url}")]
+    InvalidRunUrl { url: String },
+
+    /// An assignment's starter code URL that doesn't name a repository
+    #[error("Invalid starter code URL: {url} (expected https://github.com/owner/repo or owner/repo)")]
+    InvalidStarterUrl { url: String },
+
+    /// No starter repository and no student repository had a workflow to read;
+    /// `failures` says why each student repository tried didn't
+    #[error("{}", workflow_sources_message(.failures))]
+    NoWorkflowSource { failures: Vec<String> },
+
+    /// A student's run has no autograding job, e.g. because they renamed it
+    #[error("No '{job}' job found for {username}")]
+    NoAutogradingJob { job: &'static str, username: String },
+
+    /// A request made while grading one student failed
+    #[error("Failed to {action} for {username}: {cause}")]
+    ForStudent {
+        action: &'static str,
+        username: String,
+        cause: Box<FetchError>,
+    },
+
+    /// The autograder workflow couldn't be read from a repository
+    #[error("Failed to fetch workflow file from {repo}: {cause}")]
+    WorkflowFile { repo: String, cause: Box<FetchError> },
+
+    /// A background student fetch panicked or was cancelled
+    #[error("Student fetch task failed")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+impl FetchError {
+    pub fn parse(what: impl Into<String>, source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        FetchError::ParseFailed {
+            what: what.into(),
+            source: source.into(),
+        }
+    }
+
+    /// Say which student this error happened for and what was being fetched. A
+    /// rejected token is kept as it is, so the run can still stop to re-authenticate.
+    pub fn for_student(self, action: &'static str, username: &str) -> Self {
+        match self {
+            FetchError::AuthFailed(_) => self,
+            cause => FetchError::ForStudent {
+                action,
+                username: username.to_string(),
+                cause: Box::new(cause),
+            },
+        }
+    }
+
+    /// Say which repository's workflow file couldn't be read, keeping a
+    /// rejected token as it is like [`FetchError::for_student`]
+    pub fn in_workflow_file(self, repo: String) -> Self {
+        match self {
+            FetchError::AuthFailed(_) => self,
+            cause => FetchError::WorkflowFile {
+                repo,
+                cause: Box::new(cause),
+            },
+        }
+    }
+
+    /// The error for an unsuccessful response that isn't retried
+    pub fn from_status(url: &str, status: StatusCode, body: String) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => FetchError::AuthFailed(body),
+            StatusCode::NOT_FOUND => FetchError::NotFound { url: url.to_string() },
+            _ => FetchError::Api {
+                url: url.to_string(),
+                status,
+                body,
+            },
+        }
+    }
+}
+
+fn workflow_sources_message(failures: &[String]) -> String {
+    if failures.is_empty() {
+        return "No starter repository and no student repositories to read the workflow from".to_string();
+    }
+    format!(
+        "No starter repository, and none of the first {} student repositories has a usable workflow file:\n  {}",
+        failures.len(),
+        failures.join("\n  ")
+    )
+}
@@ -1,21 +1,252 @@
-use crate::models::{LateGradingResult, StudentResult};
+use crate::models::{Assignment, LateGradingResult, NonSubmitter, ResultStats, StudentResult, TestResult, TestStats, TestStatus};
 use anyhow::{Context, Result};
-use chrono::Utc;
-use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-/// Export student results to CSV file
-pub fn export_to_csv(
-    results: &[StudentResult],
-    assignment_name: &str,
+/// Output file format for exported results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Decimal mark for numbers in CSV files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DecimalSeparator {
+    /// `85.50`, with fields separated by commas
+    #[default]
+    Period,
+    /// `85,50`, as Excel expects in much of Europe, with fields separated by
+    /// semicolons so the two can't be confused
+    Comma,
+}
+
+impl DecimalSeparator {
+    fn delimiter(&self) -> u8 {
+        match self {
+            DecimalSeparator::Period => b',',
+            DecimalSeparator::Comma => b';',
+        }
+    }
+
+    /// A number formatted with a period, using this decimal mark instead
+    fn localize(&self, number: String) -> String {
+        match self {
+            DecimalSeparator::Period => number,
+            DecimalSeparator::Comma => number.replace('.', ","),
+        }
+    }
+
+    fn csv_writer(&self, filepath: &Path) -> csv::Result<csv::Writer<std::fs::File>> {
+        csv::WriterBuilder::new().delimiter(self.delimiter()).from_path(filepath)
+    }
+}
+
+/// A typed value so each output format can render it natively
+#[derive(Debug, Clone)]
+enum Cell {
+    Text(String),
+    Number(f64),
+    /// Percentage in the range 0-100
+    Percent(f64),
+    /// Value not available (e.g. the test step was missing from the student's run)
+    NotAvailable,
+}
+
+impl Cell {
+    fn to_csv_field(&self) -> String {
+        match self {
+            Cell::Text(s) => s.clone(),
+            Cell::Number(n) => n.to_string(),
+            Cell::Percent(p) => format!("{:.2}", p),
+            Cell::NotAvailable => "N/A".to_string(),
+        }
+    }
+
+    fn to_json_value(&self) -> serde_json::Value {
+        match self {
+            Cell::Text(s) => serde_json::Value::from(s.as_str()),
+            Cell::Number(n) => serde_json::Value::from(*n),
+            Cell::Percent(p) => serde_json::Value::from((p * 100.0).round() / 100.0),
+            Cell::NotAvailable => serde_json::Value::Null,
+        }
+    }
+}
+
+/// Column headers plus one row of cells per student
+struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<Cell>>,
+    /// Indices of the per-test score columns
+    test_columns: Range<usize>,
+}
+
+impl Table {
+    /// Put columns with the same value in every row first, so rows from many
+    /// exports can be told apart once they're concatenated
+    fn prepend_constants(&mut self, columns: &[(&str, &str)]) {
+        self.headers.splice(0..0, columns.iter().map(|(header, _)| header.to_string()));
+        for row in &mut self.rows {
+            row.splice(0..0, columns.iter().map(|(_, value)| Cell::Text(value.to_string())));
+        }
+        self.test_columns = self.test_columns.start + columns.len()..self.test_columns.end + columns.len();
+    }
+}
+
+/// Score cell for a test, using N/A for steps missing from the student's run
+fn score_cell(test: Option<&TestResult>) -> Cell {
+    match test {
+        Some(t) if t.status == TestStatus::Missing => Cell::NotAvailable,
+        Some(t) => Cell::Number(t.points_awarded as f64),
+        None => Cell::Number(0.0),
+    }
+}
+
+/// `None` when no points were available, which is exported as N/A rather than 0
+fn percentage(awarded: u32, available: u32) -> Option<f64> {
+    (available > 0).then(|| awarded as f64 / available as f64 * 100.0)
+}
+
+fn percent_cell(awarded: u32, available: u32) -> Cell {
+    percentage(awarded, available).map_or(Cell::NotAvailable, Cell::Percent)
+}
+
+/// Placeholders allowed in an output file name template
+const TEMPLATE_PLACEHOLDERS: &[&str] = &["kind", "assignment", "classroom", "date", "time", "ext"];
+
+/// File name pattern for exported results, set with `OUTPUT_TEMPLATE`, e.g.
+/// `CS101_{assignment}_{date}.{ext}`. `{kind}` is `results`, `results_late`,
+/// or `results_all`; `{date}` and `{time}` are the UTC export time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilenameTemplate(String);
+
+impl Default for FilenameTemplate {
+    fn default() -> Self {
+        Self("{kind}_{assignment}_{date}_{time}.{ext}".to_string())
+    }
+}
+
+impl std::str::FromStr for FilenameTemplate {
+    type Err = anyhow::Error;
+
+    /// Reject unknown placeholders and anything that couldn't name a single file
+    fn from_str(template: &str) -> Result<Self> {
+        let mut rest = template;
+        let mut has_ext = false;
+        let mut has_kind = false;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .with_context(|| format!("Unclosed '{{' in output template '{}'", template))?;
+            let name = &rest[start + 1..start + end];
+            if !TEMPLATE_PLACEHOLDERS.contains(&name) {
+                anyhow::bail!(
+                    "Unknown placeholder '{{{}}}' in output template '{}'. Available placeholders: {}",
+                    name,
+                    template,
+                    TEMPLATE_PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(", ")
+                );
+            }
+            has_ext |= name == "ext";
+            has_kind |= name == "kind";
+            rest = &rest[start + end + 1..];
+        }
+        if rest.contains('}') {
+            anyhow::bail!("Unmatched '}}' in output template '{}'", template);
+        }
+
+        // Without the extension, the files of a multi-format export would overwrite each other
+        if !has_ext {
+            anyhow::bail!("Output template '{}' must contain {{ext}}", template);
+        }
+        if template.contains('/') || template.contains('\\') {
+            anyhow::bail!("Output template '{}' can't contain a path separator; use OUTPUT_DIR to choose the directory", template);
+        }
+
+        // Regular, late, and combined results, and the files named after
+        // them, would otherwise share one name
+        if !has_kind {
+            return Ok(Self(format!("{{kind}}_{}", template)));
+        }
+        Ok(Self(template.to_string()))
+    }
+}
+
+impl FilenameTemplate {
+    fn render(&self, kind: &str, assignment: &str, classroom: &str, extension: &str, time: DateTime<Utc>) -> String {
+        self.0
+            .replace("{kind}", kind)
+            .replace("{assignment}", assignment)
+            .replace("{classroom}", &file_name_slug(classroom))
+            .replace("{date}", &time.format("%Y%m%d").to_string())
+            .replace("{time}", &time.format("%H%M%S").to_string())
+            .replace("{ext}", extension)
+    }
+}
+
+/// Output path named by the configured template, like `results_{assignment}_{date}_{time}.csv`
+/// by default, in the configured output directory, creating the directory if needed
+fn output_path(
+    kind: &str,
+    names: &ExportNames,
+    extension: &str,
+    time: DateTime<Utc>,
+    options: &ExportOptions,
 ) -> Result<PathBuf> {
-    if results.is_empty() {
-        anyhow::bail!("No results to export");
+    let filepath = PathBuf::from(options.filename_template.render(
+        kind,
+        names.assignment,
+        names.classroom,
+        extension,
+        time,
+    ));
+
+    match &options.output_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create output directory {}", dir.display()))?;
+            Ok(dir.join(filepath))
+        }
+        None => Ok(filepath),
+    }
+}
+
+/// The `student_name` cell, which marks students whose account was deleted
+/// since their username is really their repository's name
+fn student_name(result: &StudentResult) -> String {
+    match &result.display_name {
+        Some(name) => name.clone(),
+        None if result.account_deleted => "(account deleted)".to_string(),
+        None => String::new(),
     }
+}
 
-    // Generate filename with timestamp
-    let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("results_{}_{}.csv", assignment_name, timestamp);
-    let filepath = PathBuf::from(&filename);
+/// Build the table of regular grading results, with the graded commit and run
+/// page after the timestamp and failure messages at the end if `run_details` is
+/// set, the weighted percentage after the percentage if weights were given,
+/// how many runs were combined after the points source if they were, and
+/// `needs_review`/`review_reason` last if `needs_review` is set
+fn results_table(results: &[StudentResult], run_details: bool, needs_review: Option<usize>) -> Table {
+    let weighted = results.iter().any(|r| r.weighted_percentage.is_some());
+    let combined = results.iter().any(|r| r.combined_runs.is_some());
 
     // Collect all unique test names (preserve order from first student)
     let test_names: Vec<String> = results
@@ -23,15 +254,19 @@ pub fn export_to_csv(
         .map(|r| r.tests.keys().cloned().collect())
         .unwrap_or_default();
 
-    // Build CSV headers
+    // Build headers
     let mut headers = vec![
         "student_username".to_string(),
         "student_name".to_string(),
         "student_repo_url".to_string(),
         "workflow_run_timestamp".to_string(),
     ];
+    if run_details {
+        headers.extend(["commit_sha".to_string(), "run_url".to_string(), "run_conclusion".to_string()]);
+    }
 
     // Add test names as headers
+    let test_columns = headers.len()..headers.len() + test_names.len();
     headers.extend(test_names.clone());
 
     // Add summary columns
@@ -40,76 +275,97 @@ pub fn export_to_csv(
         "total_points_available".to_string(),
         "percentage".to_string(),
     ]);
+    if weighted {
+        headers.push("weighted_percentage".to_string());
+    }
+    headers.push("points_source".to_string());
+    if combined {
+        headers.extend(["runs_considered".to_string(), "score_variance".to_string()]);
+    }
+    if run_details {
+        headers.push("failure_messages".to_string());
+    }
+    if needs_review.is_some() {
+        headers.extend(["needs_review".to_string(), "review_reason".to_string()]);
+    }
 
-    // Create CSV writer
-    let mut wtr = csv::Writer::from_path(&filepath)
-        .context("Failed to create CSV file")?;
+    let rows = results
+        .iter()
+        .map(|student| {
+            let mut row = vec![
+                Cell::Text(student.username.clone()),
+                Cell::Text(student_name(student)),
+                Cell::Text(student.repo_url.clone()),
+                Cell::Text(student.workflow_run_timestamp.to_rfc3339()),
+            ];
+            if run_details {
+                row.push(Cell::Text(student.commit_sha.clone()));
+                row.push(Cell::Text(student.run_url.clone()));
+                row.push(Cell::Text(student.run_conclusion.clone()));
+            }
 
-    // Write headers
-    wtr.write_record(&headers)
-        .context("Failed to write CSV headers")?;
+            // Add test scores
+            for test_name in &test_names {
+                row.push(score_cell(student.tests.get(test_name)));
+            }
 
-    // Write each student's results
-    for student in results {
-        let mut record = vec![
-            student.username.clone(),
-            student.display_name.clone().unwrap_or_default(),
-            student.repo_url.clone(),
-            student.workflow_run_timestamp.to_rfc3339(),
-        ];
+            // Add totals
+            row.push(Cell::Number(student.total_awarded as f64));
+            row.push(Cell::Number(student.total_available as f64));
+            row.push(percent_cell(student.total_awarded, student.total_available));
+            if weighted {
+                row.push(student.weighted_percentage.map_or(Cell::NotAvailable, Cell::Percent));
+            }
+            row.push(Cell::Text(student.points_source.to_string()));
+            if combined {
+                match student.combined_runs {
+                    Some(runs) => {
+                        row.push(Cell::Number(runs.runs_considered as f64));
+                        row.push(Cell::Number((runs.score_variance * 100.0).round() / 100.0));
+                    }
+                    // Reused by --changed-only from a run without --runs
+                    None => row.extend([Cell::NotAvailable, Cell::NotAvailable]),
+                }
+            }
 
-        // Add test scores
-        for test_name in &test_names {
-            let score = student
-                .tests
-                .get(test_name)
-                .map(|t| t.points_awarded.to_string())
-                .unwrap_or_else(|| "0".to_string());
-            record.push(score);
-        }
+            if run_details {
+                let messages: Vec<String> = student
+                    .tests
+                    .iter()
+                    .filter_map(|(name, test)| {
+                        test.failure_message.as_ref().map(|message| format!("{}: {}", name, message))
+                    })
+                    .collect();
+                row.push(Cell::Text(messages.join("; ")));
+            }
 
-        // Add totals
-        record.push(student.total_awarded.to_string());
-        record.push(student.total_available.to_string());
+            if let Some(min_runs) = needs_review {
+                let reason = student.review_reason(min_runs);
+                row.push(Cell::Text(reason.is_some().to_string()));
+                row.push(Cell::Text(reason.unwrap_or_default()));
+            }
 
-        // Calculate percentage
-        let percentage = if student.total_available > 0 {
-            (student.total_awarded as f64 / student.total_available as f64) * 100.0
-        } else {
-            0.0
-        };
-        record.push(format!("{:.2}", percentage));
+            row
+        })
+        .collect();
 
-        wtr.write_record(&record)
-            .context("Failed to write CSV record")?;
+    Table {
+        headers,
+        rows,
+        test_columns,
     }
-
-    wtr.flush().context("Failed to flush CSV writer")?;
-
-    Ok(filepath)
 }
 
-/// Export late grading results to CSV file
-pub fn export_late_grading_to_csv(
-    results: &[LateGradingResult],
-    assignment_name: &str,
-) -> Result<PathBuf> {
-    if results.is_empty() {
-        anyhow::bail!("No results to export");
-    }
-
-    // Generate filename with timestamp
-    let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("results_late_{}_{}.csv", assignment_name, timestamp);
-    let filepath = PathBuf::from(&filename);
-
+/// Build the table of late grading results, with the graded commits and run
+/// pages after the timestamps if `run_details` is set
+fn late_results_table(results: &[LateGradingResult], run_details: bool) -> Table {
     // Collect all unique test names (preserve order from first student)
     let test_names: Vec<String> = results
         .first()
         .map(|r| r.on_time_result.tests.keys().cloned().collect())
         .unwrap_or_default();
 
-    // Build CSV headers
+    // Build headers
     let mut headers = vec![
         "student_username".to_string(),
         "student_name".to_string(),
@@ -117,8 +373,19 @@ pub fn export_late_grading_to_csv(
         "on_time_timestamp".to_string(),
         "late_timestamp".to_string(),
     ];
+    if run_details {
+        headers.extend([
+            "on_time_commit_sha".to_string(),
+            "on_time_run_url".to_string(),
+            "on_time_run_conclusion".to_string(),
+            "late_commit_sha".to_string(),
+            "late_run_url".to_string(),
+            "late_run_conclusion".to_string(),
+        ]);
+    }
 
     // Add test names as headers (will show on-time scores)
+    let test_columns = headers.len()..headers.len() + test_names.len();
     headers.extend(test_names.clone());
 
     // Add summary columns
@@ -128,103 +395,1974 @@ pub fn export_late_grading_to_csv(
         "late_points".to_string(),
         "final_points".to_string(),
         "final_percentage".to_string(),
+        "on_time_points_source".to_string(),
+        "late_points_source".to_string(),
     ]);
 
+    let rows = results
+        .iter()
+        .map(|result| {
+            let mut row = vec![
+                Cell::Text(result.username.clone()),
+                Cell::Text(student_name(&result.on_time_result)),
+                Cell::Text(result.repo_url.clone()),
+                Cell::Text(result.on_time_result.workflow_run_timestamp.to_rfc3339()),
+                Cell::Text(result.late_result.workflow_run_timestamp.to_rfc3339()),
+            ];
+            if run_details {
+                for run in [&result.on_time_result, &result.late_result] {
+                    row.push(Cell::Text(run.commit_sha.clone()));
+                    row.push(Cell::Text(run.run_url.clone()));
+                    row.push(Cell::Text(run.run_conclusion.clone()));
+                }
+            }
+
+            // Add test scores (from on-time submission)
+            for test_name in &test_names {
+                row.push(score_cell(result.on_time_result.tests.get(test_name)));
+            }
+
+            // Add summary data
+            row.push(Cell::Number(result.on_time_result.total_available as f64));
+            row.push(Cell::Number(result.on_time_result.total_awarded as f64));
+            row.push(Cell::Number(result.late_result.total_awarded as f64));
+            row.push(Cell::Number(result.final_score as f64));
+            row.push(percent_cell(result.final_score, result.on_time_result.total_available));
+            row.push(Cell::Text(result.on_time_result.points_source.to_string()));
+            row.push(Cell::Text(result.late_result.points_source.to_string()));
+
+            row
+        })
+        .collect();
+
+    Table {
+        headers,
+        rows,
+        test_columns,
+    }
+}
+
+fn write_csv(table: &Table, filepath: &Path, decimal_separator: DecimalSeparator) -> Result<()> {
     // Create CSV writer
-    let mut wtr = csv::Writer::from_path(&filepath)
+    let mut wtr = decimal_separator
+        .csv_writer(filepath)
         .context("Failed to create CSV file")?;
 
     // Write headers
-    wtr.write_record(&headers)
+    wtr.write_record(&table.headers)
         .context("Failed to write CSV headers")?;
 
     // Write each student's results
-    for result in results {
-        let mut record = vec![
-            result.username.clone(),
-            result.on_time_result.display_name.clone().unwrap_or_default(),
-            result.repo_url.clone(),
-            result.on_time_result.workflow_run_timestamp.to_rfc3339(),
-            result.late_result.workflow_run_timestamp.to_rfc3339(),
-        ];
+    for row in &table.rows {
+        let record: Vec<String> = row
+            .iter()
+            .map(|cell| match cell {
+                Cell::Number(_) | Cell::Percent(_) => decimal_separator.localize(cell.to_csv_field()),
+                _ => cell.to_csv_field(),
+            })
+            .collect();
+        wtr.write_record(&record)
+            .context("Failed to write CSV record")?;
+    }
+
+    wtr.flush().context("Failed to flush CSV writer")?;
+
+    Ok(())
+}
+
+fn write_xlsx(table: &Table, filepath: &Path) -> Result<()> {
+    use rust_xlsxwriter::{Format, Workbook};
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+
+    let header_format = Format::new().set_bold();
+    let percent_format = Format::new().set_num_format("0.00%");
+
+    // Bold header row, frozen so it stays visible while scrolling
+    for (col, header) in table.headers.iter().enumerate() {
+        worksheet
+            .write_string_with_format(0, col as u16, header, &header_format)
+            .context("Failed to write XLSX header")?;
+    }
+    worksheet
+        .set_freeze_panes(1, 0)
+        .context("Failed to freeze XLSX header row")?;
+
+    for (row_idx, row) in table.rows.iter().enumerate() {
+        let xlsx_row = row_idx as u32 + 1;
+        for (col, cell) in row.iter().enumerate() {
+            let col = col as u16;
+            match cell {
+                Cell::Text(s) => worksheet.write_string(xlsx_row, col, s),
+                Cell::Number(n) => worksheet.write_number(xlsx_row, col, *n),
+                Cell::Percent(p) => {
+                    worksheet.write_number_with_format(xlsx_row, col, p / 100.0, &percent_format)
+                }
+                Cell::NotAvailable => worksheet.write_string(xlsx_row, col, "N/A"),
+            }
+            .context("Failed to write XLSX cell")?;
+        }
+    }
+
+    workbook
+        .save(filepath)
+        .context("Failed to save XLSX file")?;
+
+    Ok(())
+}
+
+fn write_json(table: &Table, filepath: &Path) -> Result<()> {
+    // One object per student, keyed by column header in column order
+    let records: Vec<IndexMap<&str, serde_json::Value>> = table
+        .rows
+        .iter()
+        .map(|row| {
+            table
+                .headers
+                .iter()
+                .map(String::as_str)
+                .zip(row.iter().map(Cell::to_json_value))
+                .collect()
+        })
+        .collect();
+
+    let file = std::fs::File::create(filepath).context("Failed to create JSON file")?;
+    let mut writer = std::io::BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, &records)
+        .context("Failed to write JSON file")?;
+    writer.flush().context("Failed to flush JSON file")?;
+
+    Ok(())
+}
+
+fn write_table(table: &Table, filepath: &Path, format: ExportFormat, decimal_separator: DecimalSeparator) -> Result<()> {
+    write_atomic(filepath, |tmp_path| match format {
+        ExportFormat::Csv => write_csv(table, tmp_path, decimal_separator),
+        ExportFormat::Xlsx => write_xlsx(table, tmp_path),
+        ExportFormat::Json => write_json(table, tmp_path),
+    })
+}
+
+/// Write through a temporary file in the same directory and rename it into
+/// place, so a crash mid-write never leaves a truncated file under the final name.
+/// `std::fs::rename` replaces an existing destination on Windows as well.
+fn write_atomic(filepath: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let file_name = filepath
+        .file_name()
+        .context("Invalid output file name")?
+        .to_string_lossy();
+    let tmp_path = filepath.with_file_name(format!(".{}.tmp", file_name));
+
+    let result = write(&tmp_path).and_then(|()| {
+        std::fs::rename(&tmp_path, filepath).with_context(|| {
+            format!(
+                "Failed to move {} into place (is it open in another program?)",
+                filepath.display()
+            )
+        })
+    });
 
-        // Add test scores (from on-time submission)
-        for test_name in &test_names {
-            let score = result
-                .on_time_result
-                .tests
-                .get(test_name)
-                .map(|t| t.points_awarded.to_string())
-                .unwrap_or_else(|| "0".to_string());
-            record.push(score);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+/// Standard columns that a column mapping can reorder or rename. `tests`
+/// stands for all per-test score columns together.
+const MAPPABLE_COLUMNS: &[&str] = &[
+    "tag",
+    "classroom_name",
+    "assignment_title",
+    "assignment_slug",
+    "student_username",
+    "student_name",
+    "student_repo_url",
+    "workflow_run_timestamp",
+    "on_time_timestamp",
+    "late_timestamp",
+    "commit_sha",
+    "run_url",
+    "run_conclusion",
+    "on_time_commit_sha",
+    "on_time_run_url",
+    "on_time_run_conclusion",
+    "late_commit_sha",
+    "late_run_url",
+    "late_run_conclusion",
+    "tests",
+    "total_points_awarded",
+    "total_points_available",
+    "percentage",
+    "weighted_percentage",
+    "points_source",
+    "runs_considered",
+    "score_variance",
+    "failure_messages",
+    "needs_review",
+    "review_reason",
+    "on_time_points",
+    "late_points",
+    "final_points",
+    "final_percentage",
+    "on_time_points_source",
+    "late_points_source",
+    "scaled_points",
+    "scaled_available",
+];
+
+/// Institution-specific column layout, from the `[column_mapping]` config section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnMapping {
+    /// Columns to write, in order; unlisted columns are left out. All columns if unset
+    pub columns: Option<Vec<String>>,
+    /// Header to write instead of the standard column name
+    #[serde(default)]
+    pub rename: IndexMap<String, String>,
+}
+
+impl ColumnMapping {
+    /// Reject column names that don't exist, so typos fail at startup rather than silently
+    pub fn validate(&self) -> Result<()> {
+        for field in self.columns.iter().flatten().chain(self.rename.keys()) {
+            if !MAPPABLE_COLUMNS.contains(&field.as_str()) {
+                anyhow::bail!(
+                    "Unknown column '{}' in column_mapping. Available columns: {}",
+                    field,
+                    MAPPABLE_COLUMNS.join(", ")
+                );
+            }
+        }
+
+        if self.rename.contains_key("tests") {
+            anyhow::bail!("column_mapping can't rename 'tests'; test columns keep their test names");
         }
 
-        // Add summary data
-        record.push(result.on_time_result.total_available.to_string());
-        record.push(result.on_time_result.total_awarded.to_string());
-        record.push(result.late_result.total_awarded.to_string());
-        record.push(result.final_score.to_string());
+        Ok(())
+    }
 
-        // Calculate final percentage
-        let percentage = if result.on_time_result.total_available > 0 {
-            (result.final_score as f64 / result.on_time_result.total_available as f64) * 100.0
-        } else {
-            0.0
+    /// Reorder, drop, and rename the table's columns. Columns named in the
+    /// mapping that this table doesn't have (e.g. late grading columns in a
+    /// regular export) are skipped.
+    fn apply(&self, table: Table) -> Table {
+        let field = |index: usize| {
+            if table.test_columns.contains(&index) {
+                "tests"
+            } else {
+                table.headers[index].as_str()
+            }
         };
-        record.push(format!("{:.2}", percentage));
 
-        wtr.write_record(&record)
-            .context("Failed to write CSV record")?;
+        let order: Vec<usize> = match &self.columns {
+            Some(columns) => columns
+                .iter()
+                .flat_map(|column| (0..table.headers.len()).filter(move |&i| field(i) == column))
+                .collect(),
+            None => (0..table.headers.len()).collect(),
+        };
+
+        let headers = order
+            .iter()
+            .map(|&i| {
+                self.rename
+                    .get(field(i))
+                    .unwrap_or(&table.headers[i])
+                    .clone()
+            })
+            .collect();
+
+        // Test columns stay contiguous, so their new position is where the first one landed
+        let test_count = order.iter().filter(|i| table.test_columns.contains(i)).count();
+        let test_start = order
+            .iter()
+            .position(|i| table.test_columns.contains(i))
+            .unwrap_or(0);
+
+        let rows = table
+            .rows
+            .iter()
+            .map(|row| order.iter().map(|&i| row[i].clone()).collect())
+            .collect();
+
+        Table {
+            headers,
+            rows,
+            test_columns: test_start..test_start + test_count,
+        }
     }
+}
 
-    wtr.flush().context("Failed to flush CSV writer")?;
+/// What to do when an export would replace a file, e.g. one written by another run in the same second
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExistingFiles {
+    /// Add `_2`, `_3`, ... to the new file names
+    #[default]
+    Suffix,
+    Overwrite,
+}
 
-    Ok(filepath)
+/// `paths` with the first counter suffix (`_2`, `_3`, ...) that makes all of
+/// them new, so the files of one export keep sharing a name. Unchanged if
+/// none exist yet or when overwriting.
+fn unused_paths(paths: Vec<PathBuf>, existing_files: ExistingFiles) -> Vec<PathBuf> {
+    if existing_files == ExistingFiles::Overwrite || !paths.iter().any(|p| p.exists()) {
+        return paths;
+    }
+
+    (2..)
+        .map(|counter| {
+            paths
+                .iter()
+                .map(|path| {
+                    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                    let name = match path.extension() {
+                        Some(ext) => format!("{}_{}.{}", stem, counter, ext.to_string_lossy()),
+                        None => format!("{}_{}", stem, counter),
+                    };
+                    path.with_file_name(name)
+                })
+                .collect::<Vec<_>>()
+        })
+        .find(|candidates| !candidates.iter().any(|p| p.exists()))
+        .expect("some counter is unused")
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::models::{TestResult};
-    use chrono::Utc;
-    use indexmap::IndexMap;
+/// What an export is of, for naming its files
+#[derive(Debug, Clone, Copy)]
+pub struct ExportNames<'a> {
+    /// Assignment part of the file name, e.g. the assignment slug
+    pub assignment: &'a str,
+    pub classroom: &'a str,
+    /// The assignment whose results these are, for the classroom and
+    /// assignment columns; unset for exports spanning several assignments
+    pub source: Option<&'a Assignment>,
+}
 
-    #[test]
-    fn test_export_csv() {
-        let mut tests = IndexMap::new();
-        tests.insert(
-            "test_1".to_string(),
-            TestResult {
-                _name: "test_1".to_string(),
-                points_awarded: 5,
-                _points_available: 5,
-                _passed: true,
-            },
-        );
-        tests.insert(
-            "test_2".to_string(),
-            TestResult {
-                _name: "test_2".to_string(),
-                points_awarded: 0,
-                _points_available: 10,
-                _passed: false,
-            },
+/// Options controlling how results are written
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Formats to write, each from the same table; the first is the primary file
+    /// that summaries and other sidecar files are named after
+    pub formats: Vec<ExportFormat>,
+    /// Column order and names for institution-specific importers
+    pub column_mapping: Option<ColumnMapping>,
+    /// Replace student identities with stable pseudonyms
+    pub anonymize: bool,
+    /// Write the pseudonym → student mapping file when anonymizing
+    pub keep_mapping: bool,
+    /// Pseudonyms shared by every file of a run; without them, each file
+    /// numbers its own students
+    pub pseudonyms: Option<Arc<Pseudonyms>>,
+    /// Directory exported files are written to, instead of the working directory
+    pub output_dir: Option<PathBuf>,
+    /// Name pattern for exported files
+    pub filename_template: FilenameTemplate,
+    /// What to do when a results file of the same name already exists
+    pub existing_files: ExistingFiles,
+    /// Also write each student's result to a JSON-lines file as soon as it's fetched
+    pub stream: bool,
+    /// Add columns with the total rescaled to a different maximum
+    pub scale: Option<ScoreScale>,
+    /// Add the graded commit SHA and workflow run URL
+    pub run_details: bool,
+    /// Row order of results files; roster order if unset
+    pub sort_by: Option<SortBy>,
+    /// Add columns flagging suspicious scores, counting a zero after this many
+    /// runs as suspicious (regular grading only)
+    pub needs_review: Option<usize>,
+    /// Value of a `tag` column added to every row, e.g. a cohort name
+    pub tag: Option<String>,
+    /// Decimal mark in CSV files, which also picks their field delimiter
+    pub decimal_separator: DecimalSeparator,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            formats: vec![ExportFormat::default()],
+            column_mapping: None,
+            anonymize: false,
+            keep_mapping: false,
+            pseudonyms: None,
+            output_dir: None,
+            filename_template: FilenameTemplate::default(),
+            existing_files: ExistingFiles::default(),
+            stream: false,
+            scale: None,
+            run_details: false,
+            sort_by: None,
+            needs_review: None,
+            tag: None,
+            decimal_separator: DecimalSeparator::default(),
+        }
+    }
+}
+
+/// Order of the rows in results files, instead of roster order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortBy {
+    /// Highest percentage first (final percentage for late grading), with
+    /// students who had no points available last
+    Score,
+    /// Display name, or username for students without one
+    Name,
+    Username,
+}
+
+impl SortBy {
+    /// Sort `rows` by this key, breaking ties by username so the order is the same every run
+    fn sort<T>(self, rows: &mut [T], student: impl Fn(&T) -> &StudentResult, percentage: impl Fn(&T) -> Option<f64>) {
+        let lowercase_name = |s: &StudentResult| s.display_name.as_deref().unwrap_or(&s.username).to_lowercase();
+        rows.sort_by(|a, b| {
+            let (student_a, student_b) = (student(a), student(b));
+            let order = match self {
+                SortBy::Score => percentage(b).partial_cmp(&percentage(a)).unwrap_or(std::cmp::Ordering::Equal),
+                SortBy::Name => lowercase_name(student_a).cmp(&lowercase_name(student_b)),
+                SortBy::Username => std::cmp::Ordering::Equal,
+            };
+            order.then_with(|| student_a.username.to_lowercase().cmp(&student_b.username.to_lowercase()))
+        });
+    }
+}
+
+/// How scaled scores are rounded to whole points
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Rounding {
+    #[default]
+    Round,
+    Floor,
+    Ceil,
+}
+
+/// Rescale a student's total so it's out of `to` points, keeping the percentage
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreScale {
+    pub to: u32,
+    pub rounding: Rounding,
+}
+
+impl ScoreScale {
+    fn scale(&self, awarded: f64, available: f64) -> f64 {
+        if available <= 0.0 {
+            return 0.0;
+        }
+        let scaled = awarded * self.to as f64 / available;
+        // 7 / 100 * 100 comes out just above 7; a whole number off by float
+        // error must not be rounded up or down to the next one
+        let nearest = scaled.round();
+        if (scaled - nearest).abs() <= 1e-9 * nearest.abs().max(1.0) {
+            return nearest;
+        }
+        match self.rounding {
+            Rounding::Round => scaled.round(),
+            Rounding::Floor => scaled.floor(),
+            Rounding::Ceil => scaled.ceil(),
+        }
+    }
+
+    /// Append `scaled_points` and `scaled_available` columns, computed from the
+    /// final points for late grading and the total points otherwise. Tables
+    /// without those columns (e.g. the combined export) are left alone.
+    fn apply(&self, table: &mut Table) {
+        let column = |name: &str| table.headers.iter().position(|h| h == name);
+        let (Some(awarded_idx), Some(available_idx)) = (
+            column("final_points").or_else(|| column("total_points_awarded")),
+            column("total_points_available"),
+        ) else {
+            return;
+        };
+
+        table.headers.push("scaled_points".to_string());
+        table.headers.push("scaled_available".to_string());
+        for row in &mut table.rows {
+            let scaled = match (&row[awarded_idx], &row[available_idx]) {
+                (Cell::Number(awarded), Cell::Number(available)) => Cell::Number(self.scale(*awarded, *available)),
+                _ => Cell::NotAvailable,
+            };
+            row.push(scaled);
+            row.push(Cell::Number(self.to as f64));
+        }
+    }
+}
+
+/// Export student results, returning the path of each file written (primary first)
+pub fn export_results(
+    results: &[StudentResult],
+    names: ExportNames,
+    options: &ExportOptions,
+) -> Result<Vec<PathBuf>> {
+    if results.is_empty() {
+        anyhow::bail!("No results to export");
+    }
+
+    let mut results = results.to_vec();
+    if let Some(sort_by) = options.sort_by {
+        sort_by.sort(&mut results, |r| r, |r| percentage(r.total_awarded, r.total_available));
+    }
+
+    write_export(
+        results_table(&results, options.run_details, options.needs_review),
+        "results",
+        names,
+        options,
+    )
+}
+
+/// Appends one JSON object per student to a `.jsonl` file as results come in,
+/// so partial output survives an interrupted run. Lines are written whole
+/// behind a mutex, so concurrent fetches can share one writer.
+pub struct JsonLinesWriter {
+    path: PathBuf,
+    /// Added to each line as a `tag` field
+    tag: Option<String>,
+    file: std::sync::Mutex<std::io::LineWriter<std::fs::File>>,
+    /// First write error, reported by `finish`
+    error: std::sync::Mutex<Option<anyhow::Error>>,
+}
+
+impl JsonLinesWriter {
+    /// Create the results file with a `.jsonl` extension
+    pub fn create(names: ExportNames, options: &ExportOptions) -> Result<Self> {
+        let path = output_path("results", &names, "jsonl", Utc::now(), options)?;
+        let path = unused_paths(vec![path], options.existing_files).remove(0);
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+
+        Ok(Self {
+            path,
+            tag: options.tag.clone(),
+            file: std::sync::Mutex::new(std::io::LineWriter::new(file)),
+            error: std::sync::Mutex::new(None),
+        })
+    }
+
+    /// Append a result; write errors are kept for `finish` so one bad write doesn't stop the run
+    pub fn write(&self, result: &StudentResult) {
+        let written = serde_json::to_value(result)
+            .map(|mut value| {
+                if let (Some(tag), Some(object)) = (&self.tag, value.as_object_mut()) {
+                    object.insert("tag".to_string(), tag.as_str().into());
+                }
+                value
+            })
+            .context("Failed to serialize result")
+            .and_then(|line| {
+                let mut file = self.file.lock().unwrap();
+                writeln!(file, "{}", line).with_context(|| format!("Failed to write {}", self.path.display()))
+            });
+
+        if let Err(e) = written {
+            self.error.lock().unwrap().get_or_insert(e);
+        }
+    }
+
+    /// Flush the file and return its path, or the first write error
+    pub fn finish(self) -> Result<PathBuf> {
+        if let Some(e) = self.error.into_inner().unwrap() {
+            return Err(e);
+        }
+        self.file
+            .into_inner()
+            .unwrap()
+            .flush()
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(self.path)
+    }
+}
+
+/// Timestamped record of everything a grading run reported, written a line at
+/// a time so it survives a crash. Shared with every progress snapshot, so it
+/// is closed through `&self`.
+#[derive(Debug)]
+pub struct RunLog {
+    path: PathBuf,
+    file: std::sync::Mutex<std::io::LineWriter<std::fs::File>>,
+    /// First write error, reported by `finish`
+    error: std::sync::Mutex<Option<anyhow::Error>>,
+}
+
+impl RunLog {
+    /// Create the log file with a `.log` extension
+    pub fn create(names: ExportNames, options: &ExportOptions) -> Result<Self> {
+        let path = output_path("run", &names, "log", Utc::now(), options)?;
+        let path = unused_paths(vec![path], options.existing_files).remove(0);
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+
+        Ok(Self {
+            path,
+            file: std::sync::Mutex::new(std::io::LineWriter::new(file)),
+            error: std::sync::Mutex::new(None),
+        })
+    }
+
+    /// Append a line prefixed with the current time; write errors are kept for `finish`
+    pub fn write(&self, message: &str) {
+        let time = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let mut file = self.file.lock().unwrap();
+        if let Err(e) = writeln!(file, "{} {}", time, message) {
+            self.error
+                .lock()
+                .unwrap()
+                .get_or_insert(anyhow::Error::new(e).context(format!("Failed to write {}", self.path.display())));
+        }
+    }
+
+    /// Flush the file and return its path, or the first write error
+    pub fn finish(&self) -> Result<PathBuf> {
+        if let Some(e) = self.error.lock().unwrap().take() {
+            return Err(e);
+        }
+        self.file
+            .lock()
+            .unwrap()
+            .flush()
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(self.path.clone())
+    }
+}
+
+/// Export late grading results, returning the path of each file written (primary first)
+pub fn export_late_grading(
+    results: &[LateGradingResult],
+    names: ExportNames,
+    options: &ExportOptions,
+) -> Result<Vec<PathBuf>> {
+    if results.is_empty() {
+        anyhow::bail!("No results to export");
+    }
+
+    let mut results = results.to_vec();
+    if let Some(sort_by) = options.sort_by {
+        sort_by.sort(
+            &mut results,
+            |r| &r.on_time_result,
+            |r| percentage(r.final_score, r.on_time_result.total_available),
         );
+    }
 
-        let results = vec![StudentResult {
-            username: "student1".to_string(),
-            display_name: Some("Student One".to_string()),
-            repo_url: "https://github.com/org/repo".to_string(),
-            workflow_run_timestamp: Utc::now(),
-            tests,
-            total_awarded: 5,
-            total_available: 15,
-        }];
+    write_export(late_results_table(&results, options.run_details), "results_late", names, options)
+}
 
-        let filepath = export_to_csv(&results, "test_assignment").unwrap();
-        assert!(filepath.exists());
+fn write_export(
+    mut table: Table,
+    kind: &str,
+    names: ExportNames,
+    options: &ExportOptions,
+) -> Result<Vec<PathBuf>> {
+    let primary = options.formats.first().copied().unwrap_or_default();
+    let formats: Vec<ExportFormat> = std::iter::once(primary)
+        .chain(options.formats.iter().skip(1).copied())
+        .collect();
 
-        // Clean up
-        std::fs::remove_file(filepath).ok();
+    // Every format shares the primary file's name and time, so the files of one run sort together
+    let now = Utc::now();
+    let mut paths = Vec::new();
+    for format in &formats {
+        paths.push(output_path(kind, &names, format.extension(), now, options)?);
+    }
+    let paths = unused_paths(paths, options.existing_files);
+    let filepath = paths[0].clone();
+
+    let mut constants = Vec::new();
+    if let Some(tag) = &options.tag {
+        constants.push(("tag", tag.as_str()));
+    }
+    if let Some(assignment) = names.source {
+        constants.extend([
+            ("classroom_name", names.classroom),
+            ("assignment_title", assignment.title.as_str()),
+            ("assignment_slug", assignment.slug.as_str()),
+        ]);
+    }
+    table.prepend_constants(&constants);
+
+    if options.anonymize {
+        let mapping = match &options.pseudonyms {
+            Some(pseudonyms) => anonymize(&mut table, pseudonyms, options.sort_by.is_none()),
+            None => {
+                let pseudonyms = Pseudonyms::new(table_usernames(&table).iter().map(String::as_str));
+                anonymize(&mut table, &pseudonyms, options.sort_by.is_none())
+            }
+        };
+        if options.keep_mapping {
+            let mapping_path = sidecar_path(&filepath, "mapping", "csv")?;
+            write_atomic(&mapping_path, |tmp_path| write_mapping(&mapping, tmp_path, options.decimal_separator))?;
+        }
+    }
+
+    if let Some(scale) = &options.scale {
+        scale.apply(&mut table);
+    }
+
+    // Map columns last, since anonymizing and scaling look columns up by their standard names
+    if let Some(mapping) = &options.column_mapping {
+        table = mapping.apply(table);
+    }
+
+    let mut written = Vec::new();
+    for (path, format) in paths.into_iter().zip(formats) {
+        if !written.contains(&path) {
+            write_table(&table, &path, format, options.decimal_separator)?;
+            written.push(path);
+        }
+    }
+
+    Ok(written)
+}
+
+/// Export one row per student across several assignments, with a
+/// points/available/percentage column group per assignment slug. Students
+/// missing from an assignment get N/A in its columns.
+pub fn export_combined(
+    assignments: &[(String, Vec<StudentResult>)],
+    classroom_name: &str,
+    options: &ExportOptions,
+) -> Result<Vec<PathBuf>> {
+    if assignments.is_empty() {
+        anyhow::bail!("No results to export");
+    }
+
+    let mut headers = vec!["student_username".to_string(), "student_name".to_string()];
+    for (slug, _) in assignments {
+        headers.push(format!("{}_points", slug));
+        headers.push(format!("{}_available", slug));
+        headers.push(format!("{}_percentage", slug));
+    }
+
+    // Join on username, keeping the first display name seen
+    let mut students: IndexMap<&str, (String, Vec<Option<&StudentResult>>)> = IndexMap::new();
+    for (column, (_, results)) in assignments.iter().enumerate() {
+        for result in results {
+            let entry = students.entry(result.username.as_str()).or_insert_with(|| {
+                (String::new(), vec![None; assignments.len()])
+            });
+            if entry.0.is_empty() {
+                entry.0 = student_name(result);
+            }
+            entry.1[column] = Some(result);
+        }
+    }
+    students.sort_keys();
+
+    let rows = students
+        .into_iter()
+        .map(|(username, (name, results))| {
+            let mut row = vec![Cell::Text(username.to_string()), Cell::Text(name)];
+            for result in results {
+                match result {
+                    Some(result) => {
+                        row.push(Cell::Number(result.total_awarded as f64));
+                        row.push(Cell::Number(result.total_available as f64));
+                        row.push(percent_cell(result.total_awarded, result.total_available));
+                    }
+                    None => row.extend([Cell::NotAvailable, Cell::NotAvailable, Cell::NotAvailable]),
+                }
+            }
+            row
+        })
+        .collect();
+
+    let table = Table {
+        headers,
+        rows,
+        test_columns: 2..2,
+    };
+
+    // The column mapping describes single-assignment layouts, so it doesn't apply here
+    let options = ExportOptions {
+        column_mapping: None,
+        ..options.clone()
+    };
+
+    let classroom_slug = file_name_slug(classroom_name);
+    let names = ExportNames {
+        assignment: &classroom_slug,
+        classroom: classroom_name,
+        source: None,
+    };
+    write_export(table, "results_all", names, &options)
+}
+
+/// Lowercase a name and replace anything but letters and digits with `-`, for use in file names
+fn file_name_slug(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect()
+}
+
+/// One pseudonym and the identity it replaces
+struct MappingEntry {
+    pseudonym: String,
+    username: String,
+    name: String,
+    repo_url: String,
+}
+
+/// Pseudonyms like `student_001` for the students of a run. They are assigned
+/// in sorted username order so the same roster always gets the same IDs.
+#[derive(Debug, Clone, Default)]
+pub struct Pseudonyms(IndexMap<String, String>);
+
+impl Pseudonyms {
+    pub fn new<'a>(usernames: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut usernames: Vec<&str> = usernames.into_iter().collect();
+        usernames.sort();
+        usernames.dedup();
+
+        let width = usernames.len().to_string().len().max(3);
+        Self(
+            usernames
+                .into_iter()
+                .enumerate()
+                .map(|(i, username)| (username.to_string(), format!("student_{:0width$}", i + 1, width = width)))
+                .collect(),
+        )
+    }
+}
+
+/// The usernames in a table's `student_username` column
+fn table_usernames(table: &Table) -> Vec<String> {
+    let Some(username_idx) = table.headers.iter().position(|h| h == "student_username") else {
+        return Vec::new();
+    };
+    table
+        .rows
+        .iter()
+        .filter_map(|row| match &row[username_idx] {
+            Cell::Text(username) => Some(username.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replace username, name, and repo URL with their `pseudonyms`. With `reorder`,
+/// the rows are sorted by pseudonym so the original roster order doesn't leak
+/// identities; without it they keep an order the user asked for with `--sort-by`
+fn anonymize(table: &mut Table, pseudonyms: &Pseudonyms, reorder: bool) -> Vec<MappingEntry> {
+    let column = |name: &str| table.headers.iter().position(|h| h == name);
+    let (Some(username_idx), name_idx, repo_idx) = (
+        column("student_username"),
+        column("student_name"),
+        column("student_repo_url"),
+    ) else {
+        return Vec::new();
+    };
+
+    let text = |row: &[Cell], idx: Option<usize>| match idx.map(|i| &row[i]) {
+        Some(Cell::Text(s)) => s.clone(),
+        _ => String::new(),
+    };
+
+    let mut mapping = Vec::new();
+    for row in &mut table.rows {
+        let username = text(row, Some(username_idx));
+        // Every student of a run has one; a student missing from the shared
+        // pseudonyms would be a bug, but must not leak their identity
+        let pseudonym = pseudonyms.0.get(&username).cloned().unwrap_or_else(|| "student_unknown".to_string());
+
+        mapping.push(MappingEntry {
+            pseudonym: pseudonym.clone(),
+            username,
+            name: text(row, name_idx),
+            repo_url: text(row, repo_idx),
+        });
+
+        for idx in [Some(username_idx), name_idx, repo_idx].into_iter().flatten() {
+            row[idx] = Cell::Text(pseudonym.clone());
+        }
+    }
+
+    if reorder {
+        table.rows.sort_by_key(|row| text(row, Some(username_idx)));
+    }
+    mapping.sort_by(|a, b| a.pseudonym.cmp(&b.pseudonym));
+
+    mapping
+}
+
+fn write_mapping(mapping: &[MappingEntry], filepath: &Path, decimal_separator: DecimalSeparator) -> Result<()> {
+    let mut wtr = decimal_separator
+        .csv_writer(filepath)
+        .context("Failed to create mapping file")?;
+
+    wtr.write_record(["pseudonym", "student_username", "student_name", "student_repo_url"])
+        .context("Failed to write mapping headers")?;
+
+    for entry in mapping {
+        wtr.write_record([&entry.pseudonym, &entry.username, &entry.name, &entry.repo_url])
+            .context("Failed to write mapping record")?;
+    }
+
+    wtr.flush().context("Failed to flush mapping writer")?;
+
+    Ok(())
+}
+
+/// Path for a file written next to `results_path`, swapping the leading
+/// `results` in its name for `prefix`, e.g. `summary_<assignment>_<timestamp>.json`.
+/// Names that don't start with a whole `results` word, e.g. from a template
+/// like `{kind}sheet`, get `prefix` in front instead.
+fn sidecar_path(results_path: &Path, prefix: &str, extension: &str) -> Result<PathBuf> {
+    let stem = results_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .context("Invalid results file name")?;
+    let name = match stem.strip_prefix("results") {
+        Some(rest) if rest.is_empty() || rest.starts_with('_') => rest.to_string(),
+        _ => format!("_{}", stem),
+    };
+
+    Ok(results_path.with_file_name(format!("{}{}.{}", prefix, name, extension)))
+}
+
+/// Write the students with no completed grading run as CSV next to
+/// `results_path`, naming it `nonsubmitters_<assignment>_<timestamp>.csv`
+pub fn export_nonsubmitters(
+    nonsubmitters: &[NonSubmitter],
+    results_path: &Path,
+    decimal_separator: DecimalSeparator,
+) -> Result<PathBuf> {
+    let filepath = sidecar_path(results_path, "nonsubmitters", "csv")?;
+
+    write_atomic(&filepath, |tmp_path| {
+        let mut wtr = decimal_separator
+            .csv_writer(tmp_path)
+            .context("Failed to create non-submitters file")?;
+
+        wtr.write_record(["student_username", "repo_url"])
+            .context("Failed to write non-submitters headers")?;
+        for nonsubmitter in nonsubmitters {
+            wtr.write_record([&nonsubmitter.username, &nonsubmitter.repo_url])
+                .context("Failed to write non-submitters record")?;
+        }
+
+        wtr.flush().context("Failed to flush non-submitters writer")?;
+        Ok(())
+    })?;
+
+    Ok(filepath)
+}
+
+/// Aggregate record of a grading run, written alongside the results file
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub assignment_title: String,
+    pub assignment_slug: String,
+    pub classroom: String,
+    pub deadline: Option<DateTime<Utc>>,
+    pub late_deadline: Option<DateTime<Utc>>,
+    /// Which run was graded relative to the deadline, e.g. `latest_before_deadline`
+    pub graded_run: &'static str,
+    pub run_timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub stats: ResultStats,
+}
+
+/// Write the run summary as JSON next to `results_path`, naming it
+/// `summary_<assignment>_<timestamp>.json` to match the results file
+pub fn export_summary(summary: &RunSummary, results_path: &Path) -> Result<PathBuf> {
+    let filepath = sidecar_path(results_path, "summary", "json")?;
+
+    write_atomic(&filepath, |tmp_path| {
+        let file = std::fs::File::create(tmp_path).context("Failed to create summary file")?;
+        let mut writer = std::io::BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, summary)
+            .context("Failed to write summary file")?;
+        writer.flush().context("Failed to flush summary file")
+    })?;
+
+    Ok(filepath)
+}
+
+/// Write per-test pass rates as CSV next to `results_path`, naming it
+/// `test_stats_<assignment>_<timestamp>.csv` to match the results file
+pub fn export_test_stats(
+    stats: &[TestStats],
+    results_path: &Path,
+    decimal_separator: DecimalSeparator,
+) -> Result<PathBuf> {
+    let filepath = sidecar_path(results_path, "test_stats", "csv")?;
+
+    write_atomic(&filepath, |tmp_path| {
+        let mut wtr = decimal_separator
+            .csv_writer(tmp_path)
+            .context("Failed to create test stats file")?;
+
+        wtr.write_record(["test_name", "points_available", "students", "passed", "pass_rate", "average_points"])
+            .context("Failed to write test stats headers")?;
+
+        for test in stats {
+            wtr.write_record([
+                test.test_name.clone(),
+                test.points_available.to_string(),
+                test.students.to_string(),
+                test.passed.to_string(),
+                decimal_separator.localize(format!("{:.2}", test.pass_rate)),
+                decimal_separator.localize(format!("{:.2}", test.average_points)),
+            ])
+            .context("Failed to write test stats record")?;
+        }
+
+        wtr.flush().context("Failed to flush test stats writer")?;
+        Ok(())
+    })?;
+
+    Ok(filepath)
+}
+
+/// How a student's score changed between two result files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Improved,
+    Regressed,
+    Unchanged,
+    OnlyInOld,
+    OnlyInNew,
+}
+
+impl DiffStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DiffStatus::Improved => "improved",
+            DiffStatus::Regressed => "regressed",
+            DiffStatus::Unchanged => "unchanged",
+            DiffStatus::OnlyInOld => "only_in_old",
+            DiffStatus::OnlyInNew => "only_in_new",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffSummary {
+    pub improved: usize,
+    pub regressed: usize,
+    pub unchanged: usize,
+    pub only_in_old: usize,
+    pub only_in_new: usize,
+}
+
+impl DiffSummary {
+    fn record(&mut self, status: DiffStatus) {
+        match status {
+            DiffStatus::Improved => self.improved += 1,
+            DiffStatus::Regressed => self.regressed += 1,
+            DiffStatus::Unchanged => self.unchanged += 1,
+            DiffStatus::OnlyInOld => self.only_in_old += 1,
+            DiffStatus::OnlyInNew => self.only_in_new += 1,
+        }
+    }
+}
+
+/// Read username → score from an exported results CSV or JSON file, picked by
+/// its extension. Uses `total_points_awarded` for regular exports and
+/// `final_points` for late grading exports, under the names `mapping` gives them.
+fn read_scores(path: &Path, mapping: Option<&ColumnMapping>) -> Result<IndexMap<String, f64>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_lowercase();
+    let (headers, rows) = match extension.as_str() {
+        "json" => json_rows(&content, path)?,
+        "xlsx" => anyhow::bail!("Can't read {}; export the results as CSV or JSON to compare them", path.display()),
+        _ => csv_rows(&content, path)?,
+    };
+    if headers.is_empty() && rows.is_empty() {
+        return Ok(IndexMap::new());
+    }
+
+    let username_idx = find_column(&headers, mapping, &["student_username"], path)?;
+    let score_idx = find_column(&headers, mapping, &["total_points_awarded", "final_points"], path)?;
+
+    let mut scores = IndexMap::new();
+    for row in rows {
+        let username = row[username_idx].clone();
+        let score: f64 = row[score_idx]
+            .replace(',', ".")
+            .parse()
+            .with_context(|| format!("Invalid score for {} in {}", username, path.display()))?;
+        scores.insert(username, score);
+    }
+
+    Ok(scores)
+}
+
+/// Headers and rows of a results CSV
+fn csv_rows(content: &str, path: &Path) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    // Files exported with `--decimal-separator comma` are separated by semicolons
+    let header = content.lines().next().unwrap_or_default();
+    let decimal_separator = if header.contains(';') && !header.contains(',') {
+        DecimalSeparator::Comma
+    } else {
+        DecimalSeparator::Period
+    };
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(decimal_separator.delimiter())
+        .from_reader(content.as_bytes());
+
+    let headers = rdr
+        .headers()
+        .with_context(|| format!("Failed to read CSV headers from {}", path.display()))?
+        .iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    let mut rows = Vec::new();
+    for record in rdr.records() {
+        let record = record.context("Failed to read CSV record")?;
+        rows.push((0..headers.len()).map(|i| record.get(i).unwrap_or_default().to_string()).collect());
+    }
+
+    Ok((headers, rows))
+}
+
+/// Headers and rows of a results JSON file, one object per student, with
+/// `null` values as empty cells
+fn json_rows(content: &str, path: &Path) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    let objects: Vec<serde_json::Map<String, serde_json::Value>> = serde_json::from_str(content)
+        .with_context(|| format!("{} is not a JSON results file", path.display()))?;
+
+    let mut headers: Vec<String> = Vec::new();
+    for key in objects.iter().flat_map(|object| object.keys()) {
+        if !headers.contains(key) {
+            headers.push(key.clone());
+        }
+    }
+
+    let rows = objects
+        .iter()
+        .map(|object| {
+            headers
+                .iter()
+                .map(|header| match object.get(header) {
+                    Some(serde_json::Value::String(s)) => s.clone(),
+                    Some(serde_json::Value::Null) | None => String::new(),
+                    Some(value) => value.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok((headers, rows))
+}
+
+/// Index of the first of `columns` found in `headers`, under the name the
+/// column mapping gives it or its standard name
+fn find_column(headers: &[String], mapping: Option<&ColumnMapping>, columns: &[&str], path: &Path) -> Result<usize> {
+    let renamed = |column: &str| mapping.and_then(|m| m.rename.get(column)).map(String::as_str);
+
+    columns
+        .iter()
+        .flat_map(|&column| [renamed(column), Some(column)])
+        .flatten()
+        .find_map(|name| headers.iter().position(|h| h == name))
+        .with_context(|| {
+            let names: Vec<String> = columns
+                .iter()
+                .map(|&column| match renamed(column) {
+                    Some(name) => format!("{} (renamed to {} by column_mapping)", column, name),
+                    None => column.to_string(),
+                })
+                .collect();
+            let hint = if mapping.is_some_and(|m| m.columns.is_some()) {
+                "; column_mapping (or --columns) must keep it for results to be compared"
+            } else {
+                ""
+            };
+            format!("No {} column in {}{}", names.join(" or "), path.display(), hint)
+        })
+}
+
+/// A student whose total differs from an earlier results file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreDrift {
+    pub username: String,
+    /// Total in the earlier file, or `None` if the student wasn't in it
+    pub baseline: Option<f64>,
+    /// Total now, or `None` if the student has no result this time
+    pub current: Option<f64>,
+}
+
+/// Students whose total differs from the one in the `baseline_path` results
+/// file, or who are only in one of the two; baseline students first, in its order.
+/// An anonymized baseline is rejected, since none of its students would match.
+pub fn baseline_drift<'a>(
+    baseline_path: &Path,
+    mapping: Option<&ColumnMapping>,
+    current: impl IntoIterator<Item = (&'a str, f64)>,
+) -> Result<Vec<ScoreDrift>> {
+    let baseline = read_scores(baseline_path, mapping)?;
+    let is_pseudonym = |username: &str| {
+        username
+            .strip_prefix("student_")
+            .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+    };
+    if !baseline.is_empty() && baseline.keys().all(|username| is_pseudonym(username)) {
+        anyhow::bail!(
+            "{} was exported with --anonymize, so its students can't be matched; use an export with real usernames",
+            baseline_path.display()
+        );
+    }
+    let current: IndexMap<&str, f64> = current.into_iter().collect();
+
+    let mut drift: Vec<ScoreDrift> = baseline
+        .iter()
+        .filter(|(username, old)| current.get(username.as_str()) != Some(*old))
+        .map(|(username, old)| ScoreDrift {
+            username: username.clone(),
+            baseline: Some(*old),
+            current: current.get(username.as_str()).copied(),
+        })
+        .collect();
+    drift.extend(
+        current
+            .iter()
+            .filter(|(username, _)| !baseline.contains_key(**username))
+            .map(|(username, new)| ScoreDrift {
+                username: username.to_string(),
+                baseline: None,
+                current: Some(*new),
+            }),
+    );
+
+    Ok(drift)
+}
+
+/// Compare two exported result files by username and write a diff CSV named
+/// after the later file, like the other exports, reading the score columns
+/// under the names the configured column mapping gives them
+pub fn diff_results(old_path: &Path, new_path: &Path, options: &ExportOptions) -> Result<(PathBuf, DiffSummary)> {
+    let old_scores = read_scores(old_path, options.column_mapping.as_ref())?;
+    let new_scores = read_scores(new_path, options.column_mapping.as_ref())?;
+
+    let mut summary = DiffSummary::default();
+    let mut rows = Vec::new();
+    let missing = || Cell::Text(String::new());
+
+    // Students from the old run first (preserving its order), then newcomers
+    for (username, &old) in &old_scores {
+        let (new_cell, delta_cell, status) = match new_scores.get(username) {
+            Some(&new) => {
+                let status = if new > old {
+                    DiffStatus::Improved
+                } else if new < old {
+                    DiffStatus::Regressed
+                } else {
+                    DiffStatus::Unchanged
+                };
+                (Cell::Number(new), Cell::Number(new - old), status)
+            }
+            None => (missing(), missing(), DiffStatus::OnlyInOld),
+        };
+        summary.record(status);
+
+        rows.push(vec![
+            Cell::Text(username.clone()),
+            Cell::Number(old),
+            new_cell,
+            delta_cell,
+            Cell::Text(status.as_str().to_string()),
+        ]);
+    }
+
+    for (username, &new) in new_scores.iter().filter(|(u, _)| !old_scores.contains_key(*u)) {
+        summary.record(DiffStatus::OnlyInNew);
+
+        rows.push(vec![
+            Cell::Text(username.clone()),
+            missing(),
+            Cell::Number(new),
+            missing(),
+            Cell::Text(DiffStatus::OnlyInNew.as_str().to_string()),
+        ]);
+    }
+
+    let table = Table {
+        headers: ["student_username", "old_score", "new_score", "delta", "status"].map(String::from).to_vec(),
+        rows,
+        test_columns: 0..0,
+    };
+
+    // The diff has its own columns, so only the file settings apply to it
+    let options = ExportOptions {
+        formats: vec![ExportFormat::Csv],
+        column_mapping: None,
+        anonymize: false,
+        keep_mapping: false,
+        scale: None,
+        tag: None,
+        ..options.clone()
+    };
+
+    let stem = new_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .context("Invalid results file name")?;
+    let names = ExportNames {
+        assignment: stem.strip_prefix("results_").unwrap_or(stem),
+        classroom: "",
+        source: None,
+    };
+    let filepath = write_export(table, "results_diff", names, &options)?.remove(0);
+
+    Ok((filepath, summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{PointsSource, TestResult, TestStatus, TestWeights};
+    use chrono::Utc;
+    use indexmap::IndexMap;
+
+    const NAMES: ExportNames = ExportNames {
+        assignment: "test_assignment",
+        classroom: "Test Class",
+        source: None,
+    };
+
+    #[test]
+    fn test_export_csv() {
+        let mut tests = IndexMap::new();
+        tests.insert(
+            "test_1".to_string(),
+            TestResult {
+                _name: "test_1".to_string(),
+                points_awarded: 5,
+                points_available: 5,
+                status: TestStatus::Passed,
+                failure_message: None,
+            },
+        );
+        tests.insert(
+            "test_2".to_string(),
+            TestResult {
+                _name: "test_2".to_string(),
+                points_awarded: 0,
+                points_available: 10,
+                status: TestStatus::Failed,
+                failure_message: Some("expected 4, got 5".to_string()),
+            },
+        );
+        tests.insert(
+            "test_3".to_string(),
+            TestResult {
+                _name: "test_3".to_string(),
+                points_awarded: 0,
+                points_available: 5,
+                status: TestStatus::Missing,
+                failure_message: None,
+            },
+        );
+
+        let results = vec![StudentResult {
+            display_name: Some("Student One".to_string()),
+            repo_url: "https://github.com/org/repo".to_string(),
+            commit_sha: "0123456789abcdef0123456789abcdef01234567".to_string(),
+            run_url: "https://github.com/org/repo/actions/runs/42".to_string(),
+            tests,
+            total_awarded: 5,
+            total_available: 20,
+            ..StudentResult::sample("student1")
+        }];
+
+        let csv = ExportOptions::default();
+        let filepath = export_results(&results, NAMES, &csv).unwrap().remove(0);
+        assert!(filepath.exists());
+
+        // Missing steps are exported as N/A rather than 0
+        let contents = std::fs::read_to_string(&filepath).unwrap();
+        assert!(contents.contains(",5,0,N/A,5,20,"));
+
+        // Clean up
+        std::fs::remove_file(filepath).ok();
+
+        // So is the percentage when no points were available
+        let mut zero_available = results.clone();
+        zero_available[0].total_awarded = 0;
+        zero_available[0].total_available = 0;
+        let table = results_table(&zero_available, false, None);
+        assert_eq!(table.headers[9], "percentage");
+        assert_eq!(table.rows[0][9].to_csv_field(), "N/A");
+
+        // A comma decimal mark switches the delimiter to semicolons, and diff still reads the file
+        let comma = ExportOptions {
+            decimal_separator: DecimalSeparator::Comma,
+            ..Default::default()
+        };
+        let filepath = export_results(&results, NAMES, &comma).unwrap().remove(0);
+        let contents = std::fs::read_to_string(&filepath).unwrap();
+        assert!(contents.starts_with("student_username;student_name;"), "{}", contents);
+        assert!(contents.contains(";5;20;25,00;logs"), "{}", contents);
+        assert_eq!(read_scores(&filepath, None).unwrap()["student1"], 5.0);
+        std::fs::remove_file(filepath).ok();
+
+        let xlsx = ExportOptions {
+            formats: vec![ExportFormat::Xlsx],
+            ..Default::default()
+        };
+        let filepath = export_results(&results, NAMES, &xlsx).unwrap().remove(0);
+        assert!(filepath.exists());
+        std::fs::remove_file(filepath).ok();
+
+        let json = ExportOptions {
+            formats: vec![ExportFormat::Json],
+            ..Default::default()
+        };
+        let filepath = export_results(&results, NAMES, &json).unwrap().remove(0);
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&filepath).unwrap()).unwrap();
+        assert_eq!(json[0]["student_username"], "student1");
+        assert_eq!(json[0]["test_3"], serde_json::Value::Null);
+        assert_eq!(json[0]["percentage"], 25.0);
+        std::fs::remove_file(filepath).ok();
+
+        // Several formats are written side by side from the same table, once each
+        let all = ExportOptions {
+            formats: vec![ExportFormat::Csv, ExportFormat::Json, ExportFormat::Csv],
+            ..Default::default()
+        };
+        let filepaths = export_results(&results, NAMES, &all).unwrap();
+        assert_eq!(filepaths.len(), 2);
+        assert_eq!(filepaths[0].with_extension("json"), filepaths[1]);
+        for filepath in filepaths {
+            assert!(filepath.exists());
+            std::fs::remove_file(filepath).ok();
+        }
+
+        // Run details are opt-in and sit before the test columns
+        let table = results_table(&results, true, None);
+        assert_eq!(table.headers[4..7], ["commit_sha", "run_url", "run_conclusion"]);
+        assert_eq!(table.test_columns, 7..10);
+        assert_eq!(table.rows[0][5].to_csv_field(), "https://github.com/org/repo/actions/runs/42");
+        assert_eq!(table.headers.last().unwrap(), "failure_messages");
+        assert_eq!(table.rows[0].last().unwrap().to_csv_field(), "test_2: expected 4, got 5");
+        assert_eq!(results_table(&results, false, None).headers.len(), 11);
+
+        // Every row says whether its points are exact
+        let mut approximate = results.clone();
+        approximate[0].points_source = PointsSource::Approximate(crate::models::LogsProblem::Expired);
+        let table = results_table(&approximate, false, None);
+        assert_eq!(table.headers[10], "points_source");
+        assert_eq!(table.rows[0][10].to_csv_field(), "approximate (logs expired)");
+
+        // Weights count test_1 (full marks) three times as much as test_2 (none)
+        let weights = TestWeights([("test_1".to_string(), 3.0), ("test_2".to_string(), 1.0)].into_iter().collect());
+        let mut weighted = results.clone();
+        weighted[0].weighted_percentage = Some(weights.percentage(&weighted[0]));
+        let table = results_table(&weighted, false, None);
+        assert_eq!(table.headers[9..], ["percentage", "weighted_percentage", "points_source"]);
+        assert_eq!(table.rows[0][10].to_csv_field(), "75.00");
+
+        // Review flags are opt-in and come last
+        let table = results_table(&results, false, Some(5));
+        assert_eq!(table.headers[11..], ["needs_review", "review_reason"]);
+        assert_eq!(table.rows[0][11].to_csv_field(), "false");
+        let mut suspicious = results.clone();
+        suspicious[0].total_awarded = 0;
+        suspicious[0].run_count = 12;
+        suspicious[0].tests.shift_remove("test_3");
+        suspicious[0].tests["test_2"].status = TestStatus::Passed;
+        suspicious[0].tests["test_1"].status = TestStatus::Passed;
+        let table = results_table(&suspicious, false, Some(5));
+        assert_eq!(table.rows[0][10].to_csv_field(), "true");
+        assert_eq!(
+            table.rows[0][11].to_csv_field(),
+            "all tests passed but only 0/20 points; 0 points after 12 runs"
+        );
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let result = |username: &str, name: Option<&str>, total_awarded: u32| StudentResult {
+            display_name: name.map(str::to_string),
+            total_awarded,
+            ..StudentResult::sample(username)
+        };
+        let results = [
+            result("carol", Some("Ann Lee"), 7),
+            result("Bob", None, 9),
+            result("alice", Some("Zoe Park"), 7),
+        ];
+        let order = |sort_by: SortBy| {
+            let mut sorted = results.to_vec();
+            sort_by.sort(&mut sorted, |r| r, |r| percentage(r.total_awarded, r.total_available));
+            sorted.into_iter().map(|r| r.username).collect::<Vec<_>>()
+        };
+
+        assert_eq!(order(SortBy::Score), ["Bob", "alice", "carol"]);
+        assert_eq!(order(SortBy::Name), ["carol", "Bob", "alice"]);
+        assert_eq!(order(SortBy::Username), ["alice", "Bob", "carol"]);
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("atomic_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let filepath = dir.join("results_test.csv");
+
+        // A failed write leaves neither the final file nor the temporary one behind
+        let failed = write_atomic(&filepath, |tmp_path| {
+            std::fs::write(tmp_path, "partial").unwrap();
+            anyhow::bail!("disk full")
+        });
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        // A successful write replaces an existing file
+        std::fs::write(&filepath, "old").unwrap();
+        write_atomic(&filepath, |tmp_path| Ok(std::fs::write(tmp_path, "new")?)).unwrap();
+        assert_eq!(std::fs::read_to_string(&filepath).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unused_paths() {
+        let dir = std::env::temp_dir().join(format!("unused_paths_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = vec![dir.join("results_hw1.csv"), dir.join("results_hw1.json")];
+
+        assert_eq!(unused_paths(paths.clone(), ExistingFiles::Suffix), paths);
+
+        // Same-second reruns collide; every format moves to the same new name
+        std::fs::write(&paths[0], "").unwrap();
+        std::fs::write(dir.join("results_hw1_2.json"), "").unwrap();
+        assert_eq!(
+            unused_paths(paths.clone(), ExistingFiles::Suffix),
+            [dir.join("results_hw1_3.csv"), dir.join("results_hw1_3.json")]
+        );
+        assert_eq!(unused_paths(paths.clone(), ExistingFiles::Overwrite), paths);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_log() {
+        let dir = std::env::temp_dir().join(format!("run_log_test_{}", std::process::id()));
+        let options = ExportOptions {
+            output_dir: Some(dir.clone()),
+            ..ExportOptions::default()
+        };
+        let log = RunLog::create(ExportNames { assignment: "log-test", ..NAMES }, &options).unwrap();
+        log.write("✓ Found 2 students");
+        log.write("  ✗ student2 - Error");
+        let path = log.finish().unwrap();
+
+        assert!(path.file_name().unwrap().to_string_lossy().starts_with("run_log-test_"));
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" ✓ Found 2 students"), "{}", lines[0]);
+        assert!(lines[1].ends_with("   ✗ student2 - Error"), "{}", lines[1]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_json_lines_writer() {
+        let dir = std::env::temp_dir().join(format!("jsonl_test_{}", std::process::id()));
+        let options = ExportOptions {
+            output_dir: Some(dir.clone()),
+            tag: Some("fall-a".to_string()),
+            ..ExportOptions::default()
+        };
+        let writer = JsonLinesWriter::create(ExportNames { assignment: "stream-test", ..NAMES }, &options).unwrap();
+
+        let mut tests = IndexMap::new();
+        tests.insert(
+            "test_1".to_string(),
+            TestResult {
+                _name: "test_1".to_string(),
+                points_awarded: 0,
+                points_available: 5,
+                status: TestStatus::Missing,
+                failure_message: None,
+            },
+        );
+        for username in ["student1", "student2"] {
+            writer.write(&StudentResult {
+                tests: tests.clone(),
+                total_available: 5,
+                ..StudentResult::sample(username)
+            });
+        }
+
+        let path = writer.finish().unwrap();
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["username"], "student2");
+        assert_eq!(lines[0]["tests"]["test_1"]["status"], "missing");
+        assert_eq!(lines[0]["tag"], "fall-a");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_filename_template() {
+        let template: FilenameTemplate = "CS101_{classroom}_{assignment}_{date}.{ext}".parse().unwrap();
+        let time = DateTime::parse_from_rfc3339("2025-03-01T14:05:09Z").unwrap().with_timezone(&Utc);
+        assert_eq!(
+            template.render("results", "hw1", "Fall 2025", "csv", time),
+            "results_CS101_fall-2025_hw1_20250301.csv"
+        );
+        // {kind} goes wherever the template puts it
+        let template: FilenameTemplate = "{assignment}_{kind}.{ext}".parse().unwrap();
+        assert_eq!(template.render("results_late", "hw1", "Fall 2025", "csv", time), "hw1_results_late.csv");
+        assert_eq!(
+            FilenameTemplate::default().render("results_late", "hw1", "Fall 2025", "xlsx", time),
+            "results_late_hw1_20250301_140509.xlsx"
+        );
+
+        assert!("{assignment}_{week}.{ext}".parse::<FilenameTemplate>().is_err());
+        assert!("{assignment}.csv".parse::<FilenameTemplate>().is_err());
+        assert!("{assignment.{ext}".parse::<FilenameTemplate>().is_err());
+        assert!("grades/{assignment}.{ext}".parse::<FilenameTemplate>().is_err());
+
+        let results_path = Path::new("results_CS101_hw1_20250301.csv");
+        assert_eq!(
+            sidecar_path(results_path, "summary", "json").unwrap(),
+            PathBuf::from("summary_CS101_hw1_20250301.json")
+        );
+        let results_path = Path::new("hw1_results.csv");
+        assert_eq!(
+            sidecar_path(results_path, "summary", "json").unwrap(),
+            PathBuf::from("summary_hw1_results.json")
+        );
+        // Only a whole leading `results` word is swapped
+        let results_path = Path::new("resultsheet_hw1.csv");
+        assert_eq!(
+            sidecar_path(results_path, "summary", "json").unwrap(),
+            PathBuf::from("summary_resultsheet_hw1.json")
+        );
+    }
+
+    #[test]
+    fn test_score_scale() {
+        let mut table = Table {
+            headers: vec![
+                "student_username".to_string(),
+                "total_points_awarded".to_string(),
+                "total_points_available".to_string(),
+            ],
+            rows: vec![
+                vec![Cell::Text("a".to_string()), Cell::Number(87.0), Cell::Number(100.0)],
+                vec![Cell::Text("b".to_string()), Cell::NotAvailable, Cell::Number(100.0)],
+            ],
+            test_columns: 1..1,
+        };
+
+        let scale = |rounding| ScoreScale { to: 10, rounding };
+        assert_eq!(scale(Rounding::Round).scale(87.0, 100.0), 9.0);
+        assert_eq!(scale(Rounding::Floor).scale(87.0, 100.0), 8.0);
+        assert_eq!(scale(Rounding::Ceil).scale(81.0, 100.0), 9.0);
+        assert_eq!(scale(Rounding::Round).scale(0.0, 0.0), 0.0);
+        let percent = |rounding| ScoreScale { to: 100, rounding };
+        assert_eq!(percent(Rounding::Ceil).scale(7.0, 100.0), 7.0);
+        assert_eq!(percent(Rounding::Floor).scale(29.0, 100.0), 29.0);
+        assert_eq!(percent(Rounding::Ceil).scale(7.0, 30.0), 24.0);
+
+        scale(Rounding::Floor).apply(&mut table);
+        assert_eq!(table.headers[3..], ["scaled_points", "scaled_available"]);
+        let csv: Vec<Vec<String>> = table
+            .rows
+            .iter()
+            .map(|row| row[3..].iter().map(Cell::to_csv_field).collect())
+            .collect();
+        assert_eq!(csv, [["8", "10"], ["N/A", "10"]]);
+    }
+
+    #[test]
+    fn test_column_mapping() {
+        let mapping: ColumnMapping = toml::from_str(
+            r#"
+            columns = ["percentage", "student_username", "tests", "final_points"]
+
+            [rename]
+            student_username = "NetID"
+            "#,
+        )
+        .unwrap();
+        mapping.validate().unwrap();
+
+        let table = Table {
+            headers: ["student_username", "student_repo_url", "test_1", "test_2", "percentage"]
+                .map(String::from)
+                .to_vec(),
+            rows: vec![vec![
+                Cell::Text("amy".to_string()),
+                Cell::Text("https://github.com/org/repo-amy".to_string()),
+                Cell::Number(5.0),
+                Cell::Number(3.0),
+                Cell::Percent(80.0),
+            ]],
+            test_columns: 2..4,
+        };
+
+        // final_points only exists in late grading exports, so it is skipped here
+        let mut tagged = Table {
+            headers: table.headers.clone(),
+            rows: table.rows.clone(),
+            test_columns: table.test_columns.clone(),
+        };
+        tagged.prepend_constants(&[("tag", "fall-a"), ("assignment_slug", "hw1")]);
+        assert_eq!(tagged.headers[..4], ["tag", "assignment_slug", "student_username", "student_repo_url"]);
+        assert_eq!(tagged.rows[0][0].to_csv_field(), "fall-a");
+        assert_eq!(tagged.rows[0][1].to_csv_field(), "hw1");
+        assert_eq!(tagged.test_columns, 4..6);
+
+        let mapped = mapping.apply(table);
+        assert_eq!(mapped.headers, ["percentage", "NetID", "test_1", "test_2"]);
+        assert_eq!(mapped.test_columns, 2..4);
+        let fields: Vec<String> = mapped.rows[0].iter().map(Cell::to_csv_field).collect();
+        assert_eq!(fields, ["80.00", "amy", "5", "3"]);
+
+        let unknown: ColumnMapping = toml::from_str(r#"columns = ["netid"]"#).unwrap();
+        assert!(unknown.validate().unwrap_err().to_string().contains("'netid'"));
+    }
+
+    #[test]
+    fn test_deleted_accounts_marked() {
+        let deleted = StudentResult { account_deleted: true, ..StudentResult::sample("hw1-alice") };
+        let table = results_table(&[deleted], false, None);
+        assert!(matches!(&table.rows[0][1], Cell::Text(name) if name == "(account deleted)"));
+    }
+
+    #[test]
+    fn test_export_combined() {
+        let result = |username: &str, awarded: u32| StudentResult {
+            total_awarded: awarded,
+            ..StudentResult::sample(username)
+        };
+
+        let assignments = vec![
+            ("hw1".to_string(), vec![result("bob", 10), result("amy", 5)]),
+            ("hw2".to_string(), vec![result("amy", 8)]),
+        ];
+        let filepath = export_combined(&assignments, "Test Class", &ExportOptions::default()).unwrap().remove(0);
+        let content = std::fs::read_to_string(&filepath).unwrap();
+        std::fs::remove_file(&filepath).unwrap();
+
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines[0],
+            "student_username,student_name,hw1_points,hw1_available,hw1_percentage,hw2_points,hw2_available,hw2_percentage"
+        );
+        assert_eq!(lines[1], "amy,,5,10,50.00,8,10,80.00");
+        assert_eq!(lines[2], "bob,,10,10,100.00,N/A,N/A,N/A");
+        assert!(filepath.to_string_lossy().starts_with("results_all_test-class_"));
+    }
+
+    #[test]
+    fn test_export_test_stats() {
+        let student = |username: &str, passed: bool| {
+            let mut tests = IndexMap::new();
+            tests.insert(
+                "test_1".to_string(),
+                TestResult {
+                    _name: "test_1".to_string(),
+                    points_awarded: if passed { 4 } else { 0 },
+                    points_available: 4,
+                    status: if passed { TestStatus::Passed } else { TestStatus::Failed },
+                    failure_message: None,
+                },
+            );
+            StudentResult {
+                tests,
+                total_awarded: if passed { 4 } else { 0 },
+                total_available: 4,
+                ..StudentResult::sample(username)
+            }
+        };
+
+        let stats = TestStats::calculate(&[student("amy", true), student("bob", false), student("cat", true)]);
+        let results_path = PathBuf::from("results_stats-test_20250101_000000.csv");
+        let filepath = export_test_stats(&stats, &results_path, DecimalSeparator::Comma).unwrap();
+        let content = std::fs::read_to_string(&filepath).unwrap();
+        std::fs::remove_file(&filepath).unwrap();
+
+        assert_eq!(filepath, PathBuf::from("test_stats_stats-test_20250101_000000.csv"));
+        assert_eq!(content.lines().nth(1), Some("test_1;4;3;2;66,67;2,67"));
+    }
+
+    #[test]
+    fn test_anonymize() {
+        let row = |username: &str, name: &str| {
+            vec![
+                Cell::Text(username.to_string()),
+                Cell::Text(name.to_string()),
+                Cell::Text(format!("https://github.com/org/hw1-{}", username)),
+                Cell::Number(3.0),
+            ]
+        };
+        let mut table = Table {
+            headers: vec![
+                "student_username".to_string(),
+                "student_name".to_string(),
+                "student_repo_url".to_string(),
+                "total_points_awarded".to_string(),
+            ],
+            rows: vec![row("zed", "Zed Z"), row("amy", "Amy A")],
+            test_columns: 3..3,
+        };
+        let mut other_assignment = Table {
+            headers: table.headers.clone(),
+            rows: vec![row("zed", "Zed Z")],
+            test_columns: 3..3,
+        };
+
+        let pseudonyms = Pseudonyms::new(table_usernames(&table).iter().map(String::as_str));
+        let mapping = anonymize(&mut table, &pseudonyms, true);
+
+        // Pseudonyms follow sorted username order, independent of input order
+        assert_eq!(mapping[0].pseudonym, "student_001");
+        assert_eq!(mapping[0].username, "amy");
+        assert_eq!(mapping[1].username, "zed");
+
+        let fields: Vec<String> = table.rows[0].iter().map(Cell::to_csv_field).collect();
+        assert_eq!(fields, ["student_001", "student_001", "student_001", "3"]);
+
+        // With a run's shared pseudonyms, a student keeps theirs in every file
+        let run = Pseudonyms::new(["zed", "bob", "amy"]);
+        let mapping = anonymize(&mut other_assignment, &run, true);
+        assert_eq!((mapping[0].pseudonym.as_str(), mapping[0].username.as_str()), ("student_003", "zed"));
+
+        // An explicit --sort-by order survives anonymizing
+        let result = |username: &str, total_awarded: u32| StudentResult {
+            total_awarded,
+            ..StudentResult::sample(username)
+        };
+        let results = [result("amy", 2), result("zed", 9), result("bob", 5)];
+        let options = ExportOptions {
+            sort_by: Some(SortBy::Score),
+            anonymize: true,
+            ..Default::default()
+        };
+        let filepath = export_results(&results, ExportNames { assignment: "anon-sort", ..NAMES }, &options)
+            .unwrap()
+            .remove(0);
+        let contents = std::fs::read_to_string(&filepath).unwrap();
+        let order: Vec<_> = contents.lines().skip(1).map(|line| line.split(',').next().unwrap()).collect();
+        assert_eq!(order, ["student_003", "student_002", "student_001"]);
+        std::fs::remove_file(filepath).ok();
+    }
+
+    #[test]
+    fn test_baseline_drift() {
+        let path = std::env::temp_dir().join(format!("baseline_test_{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            "student_username,total_points_awarded\nalice,5\nbob,8\ncarol,3\n",
+        )
+        .unwrap();
+
+        let drift = baseline_drift(&path, None, [("alice", 5.0), ("bob", 6.0), ("dave", 4.0)]).unwrap();
+
+        // A baseline exported with renamed columns is read through the same mapping
+        std::fs::write(&path, "NetID,Total\nalice,5\nbob,6\n").unwrap();
+        let mapping: ColumnMapping = toml::from_str(
+            r#"
+            [rename]
+            student_username = "NetID"
+            total_points_awarded = "Total"
+            "#,
+        )
+        .unwrap();
+        assert!(baseline_drift(&path, Some(&mapping), [("alice", 5.0), ("bob", 6.0)]).unwrap().is_empty());
+
+        // An anonymized baseline would report every student as changed
+        std::fs::write(&path, "student_username,total_points_awarded\nstudent_001,5\nstudent_002,8\n").unwrap();
+        let error = baseline_drift(&path, None, [("alice", 5.0)]).unwrap_err().to_string();
+        assert!(error.contains("was exported with --anonymize"), "{}", error);
+        std::fs::remove_file(&path).ok();
+
+        let drift: Vec<_> = drift.iter().map(|d| (d.username.as_str(), d.baseline, d.current)).collect();
+        assert_eq!(
+            drift,
+            [
+                ("bob", Some(8.0), Some(6.0)),
+                ("carol", Some(3.0), None),
+                ("dave", None, Some(4.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_results() {
+        let dir = std::env::temp_dir().join(format!("diff_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let old_path = dir.join("results_hw1_old.csv");
+        let new_path = dir.join("results_hw1_new.csv");
+
+        std::fs::write(
+            &old_path,
+            "student_username,total_points_awarded\nalice,5\nbob,8\ncarol,3\ndave,4\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &new_path,
+            "student_username,total_points_awarded\nalice,7\nbob,6\ncarol,3\nerin,9\n",
+        )
+        .unwrap();
+
+        let options = ExportOptions {
+            output_dir: Some(dir.join("out")),
+            ..Default::default()
+        };
+        let (filepath, summary) = diff_results(&old_path, &new_path, &options).unwrap();
+        assert_eq!(filepath.parent(), Some(dir.join("out").as_path()));
+        assert!(filepath.file_name().unwrap().to_string_lossy().starts_with("results_diff_hw1_new_"));
+        assert_eq!(summary.improved, 1);
+        assert_eq!(summary.regressed, 1);
+        assert_eq!(summary.unchanged, 1);
+        assert_eq!(summary.only_in_old, 1);
+        assert_eq!(summary.only_in_new, 1);
+
+        let contents = std::fs::read_to_string(&filepath).unwrap();
+        assert!(contents.contains("alice,5,7,2,improved"));
+        assert!(contents.contains("dave,4,,,only_in_old"));
+        assert!(contents.contains("erin,,9,,only_in_new"));
+
+        // JSON exports are read too, and renamed columns are found through the column mapping
+        let json_path = dir.join("results_hw1_new.json");
+        std::fs::write(
+            &json_path,
+            r#"[{"NetID": "alice", "Score": 7, "percentage": 70.0}, {"NetID": "bob", "Score": 6, "percentage": null}]"#,
+        )
+        .unwrap();
+        let mapping: ColumnMapping = toml::from_str(
+            r#"
+            [rename]
+            student_username = "NetID"
+            total_points_awarded = "Score"
+            "#,
+        )
+        .unwrap();
+        let scores = read_scores(&json_path, Some(&mapping)).unwrap();
+        assert_eq!(scores.into_iter().collect::<Vec<_>>(), [("alice".to_string(), 7.0), ("bob".to_string(), 6.0)]);
+
+        // Without the mapping, the error names the column that's missing
+        let error = read_scores(&json_path, None).unwrap_err().to_string();
+        assert!(error.starts_with("No student_username column in"), "{}", error);
+
+        // Files with the standard names still work under a mapping, but one that
+        // leaves out the total can't be compared
+        assert_eq!(read_scores(&old_path, Some(&mapping)).unwrap()["bob"], 8.0);
+        let columns = ColumnMapping {
+            columns: Some(vec!["student_username".to_string(), "percentage".to_string()]),
+            rename: IndexMap::new(),
+        };
+        std::fs::write(&new_path, "student_username,percentage\nalice,70.00\n").unwrap();
+        let error = read_scores(&new_path, Some(&columns)).unwrap_err().to_string();
+        assert!(error.ends_with("column_mapping (or --columns) must keep it for results to be compared"), "{}", error);
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -1,17 +1,245 @@
 use crate::api::{ClassroomClient, GitHubClient};
-use crate::models::{AcceptedAssignment, StudentResult, TestDefinition, TestResult};
+use crate::deadlines::LateGradingDeadlines;
+use crate::models::{
+    AcceptedAssignment, Assignment, CheckRun, CheckRunAnnotation, CombinedRuns, Job, JobStep, LateGradingResult, LogsProblem,
+    PointsSource,
+    StudentResult, TestDefinition,
+    PointsFloor, TestResult, TestStatus, TestWeights, WorkflowRun,
+};
+use crate::error::FetchError;
 use crate::parser;
-use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinSet;
 
-/// Parse repository URL to extract owner and repo name
+/// Pause between `--wait-for-runs` retries while GitHub indexes new workflow runs
+const RUN_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// What to do with a graded run that was cancelled, timed out, or otherwise
+/// ended without succeeding or failing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum InterruptedRuns {
+    /// Grade it from the steps that finished; tests that didn't run score 0
+    #[default]
+    Grade,
+    /// Leave the student out and report them as needing manual review
+    Review,
+}
+
+impl InterruptedRuns {
+    fn check(self, result: StudentResult) -> Result<StudentResult, FetchError> {
+        if self == InterruptedRuns::Review && result.run_interrupted() {
+            return Err(FetchError::RunInterrupted {
+                username: result.username,
+                conclusion: result.run_conclusion,
+            });
+        }
+        Ok(result)
+    }
+}
+
+/// How the runs graded by `--runs` are combined into one score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RunPolicy {
+    /// The run with the most points
+    #[default]
+    Max,
+    /// Each test's points averaged over the runs, rounded to whole points
+    Mean,
+}
+
+impl RunPolicy {
+    /// One result from `graded`, which starts with the run that would be
+    /// graded on its own. `Max` keeps the best run (the first on a tie);
+    /// `Mean` keeps the first run's details with averaged points.
+    fn combine(self, mut graded: Vec<StudentResult>) -> StudentResult {
+        let totals: Vec<f64> = graded.iter().map(|r| r.total_awarded as f64).collect();
+        let mean = totals.iter().sum::<f64>() / totals.len() as f64;
+        let combined_runs = CombinedRuns {
+            runs_considered: graded.len(),
+            score_variance: totals.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / totals.len() as f64,
+        };
+
+        let mut result = match self {
+            RunPolicy::Max => {
+                let best = (0..graded.len())
+                    .max_by_key(|&i| (graded[i].total_awarded, std::cmp::Reverse(i)))
+                    .unwrap_or(0);
+                graded.swap_remove(best)
+            }
+            RunPolicy::Mean => {
+                let mut result = graded[0].clone();
+                for (name, test) in &mut result.tests {
+                    let points: Vec<u32> = graded
+                        .iter()
+                        .filter_map(|r| r.tests.get(name).map(|t| t.points_awarded))
+                        .collect();
+                    test.points_awarded =
+                        (points.iter().sum::<u32>() as f64 / points.len() as f64).round() as u32;
+                }
+                result.total_awarded = result.tests.values().map(|t| t.points_awarded).sum();
+                // Averaging in an approximate run makes the average approximate
+                if let Some(source) = graded.iter().map(|r| r.points_source).find(|s| *s != PointsSource::Logs) {
+                    result.points_source = source;
+                }
+                result
+            }
+        };
+        result.combined_runs = Some(combined_runs);
+        result
+    }
+}
+
+/// Grade this many recent runs per student and combine them with `policy`,
+/// for test suites too flaky to trust a single run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultiRun {
+    pub runs: usize,
+    pub policy: RunPolicy,
+}
+
+/// Students fetched at the same time unless `CONCURRENCY` says otherwise
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Settings that apply to every student of a fetch
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// Only grade and export these tests (by name), if set
+    pub test_filter: Option<Vec<String>>,
+    /// Rubric weights for a weighted_percentage column, if set (regular grading only)
+    pub weights: Option<TestWeights>,
+    /// Least points a test that ran is given, if set
+    pub points_floor: Option<PointsFloor>,
+    /// How graded runs that were cancelled, timed out, etc. are treated
+    pub interrupted_runs: InterruptedRuns,
+    /// Never grade these GitHub usernames, e.g. instructors' and TAs' test accounts
+    pub exclude: Vec<String>,
+    /// Only grade this student (by GitHub username), if set
+    pub student: Option<String>,
+    /// Only grade workflow runs on this branch instead of each repo's default branch
+    pub branch: Option<String>,
+    /// Leave out students Classroom already reports as passing (regular grading only)
+    pub skip_passing: bool,
+    /// Compare the starter repo's workflow with the first graded student's run
+    pub check_workflow: bool,
+    /// Only fetch the first this many students (after roster filtering), if set
+    pub max_students: Option<usize>,
+    /// Maximum number of students fetched at the same time (regular grading only)
+    pub concurrency: usize,
+    /// Extra times to look for a workflow run when a student who submitted has none yet
+    pub wait_for_runs: u32,
+    /// Grade several recent runs per student and combine them, if set (regular grading only)
+    pub multi_run: Option<MultiRun>,
+    /// Score tests from their job steps alone instead of downloading job logs
+    pub skip_logs: bool,
+    /// Look up failed tests' messages in their jobs' annotations
+    pub failure_messages: bool,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            test_filter: None,
+            weights: None,
+            points_floor: None,
+            interrupted_runs: InterruptedRuns::default(),
+            exclude: Vec::new(),
+            student: None,
+            branch: None,
+            skip_passing: false,
+            check_workflow: false,
+            max_students: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            wait_for_runs: 0,
+            multi_run: None,
+            skip_logs: false,
+            failure_messages: false,
+        }
+    }
+}
+
+/// What every fetch of a run shares: the API clients, the settings, and the
+/// workflows already parsed
+#[derive(Clone, Copy)]
+pub struct FetchContext<'a> {
+    pub classroom_client: &'a ClassroomClient,
+    pub github_client: &'a GitHubClient,
+    pub options: &'a FetchOptions,
+    pub test_definition_cache: &'a TestDefinitionCache,
+}
+
+/// Which completed workflow run on the graded branch counts for each student
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RunSelection {
+    /// The newest run
+    Latest,
+    /// The first run created at or after the deadline
+    FirstAfter(DateTime<Utc>),
+    /// The newest run created at or before the deadline
+    LatestBefore(DateTime<Utc>),
+}
+
+impl RunSelection {
+    pub fn deadline(&self) -> Option<DateTime<Utc>> {
+        match self {
+            RunSelection::Latest => None,
+            RunSelection::FirstAfter(deadline) | RunSelection::LatestBefore(deadline) => Some(*deadline),
+        }
+    }
+
+    /// Short name recorded in the run summary
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunSelection::Latest => "latest",
+            RunSelection::FirstAfter(_) => "first_after_deadline",
+            RunSelection::LatestBefore(_) => "latest_before_deadline",
+        }
+    }
+
+    /// `created` qualifier limiting the Actions API to runs on the right side of the deadline
+    fn created_filter(&self) -> Option<String> {
+        // A `Z` suffix rather than `+00:00`, since a raw `+` in a query string decodes as a space
+        let timestamp = |dt: &DateTime<Utc>| dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        match self {
+            RunSelection::Latest => None,
+            RunSelection::FirstAfter(deadline) => Some(format!(">={}", timestamp(deadline))),
+            RunSelection::LatestBefore(deadline) => Some(format!("<={}", timestamp(deadline))),
+        }
+    }
+}
+
+/// Owner and name of a repository given as `owner/repo` or as a GitHub URL
+/// (`https://github.com/owner/repo/tree/main`, `github.com/owner/repo`,
+/// `git@github.com:owner/repo.git`, ...). Both are empty if it doesn't name
+/// a repository.
 pub fn parse_repo_url(full_name: &str) -> (&str, &str) {
-    let parts: Vec<&str> = full_name.split('/').collect();
-    if parts.len() >= 2 {
-        (parts[0], parts[1])
-    } else {
-        ("", "")
+    let mut path = full_name.trim();
+    path = path.split(['?', '#']).next().unwrap_or_default();
+    if let Some((_, rest)) = path.split_once("://") {
+        // Drop the host, keeping "owner/repo/..."
+        path = rest.split_once('/').map_or("", |(_, rest)| rest);
+    } else if let Some((_, rest)) = path.split_once(':') {
+        // SSH form: git@github.com:owner/repo
+        path = rest;
+    } else if let Some((host, rest)) = path.split_once('/')
+        && host.contains('.')
+    {
+        // A URL without a scheme; logins can't contain dots, so this is the host
+        path = rest;
+    }
+
+    let mut parts = path.split('/').filter(|part| !part.is_empty());
+    match (parts.next(), parts.next()) {
+        (Some(owner), Some(repo)) => {
+            let repo = repo.strip_suffix(".git").unwrap_or(repo);
+            if repo.is_empty() { ("", "") } else { (owner, repo) }
+        }
+        _ => ("", ""),
     }
 }
 
@@ -41,261 +269,2007 @@ fn parse_test_scores_from_logs(logs: &str) -> std::collections::HashMap<String,
     scores
 }
 
-/// Fetch test definitions from the assignment's starter repository
-pub async fn fetch_test_definitions(
+/// Parse per-test scores from the summary and text of the autograding job's
+/// check run (same id as the job), or of any check run if that one has none
+fn scores_from_check_runs(check_runs: &[CheckRun], job_id: u64) -> std::collections::HashMap<String, u32> {
+    let scores_of = |check_run: &CheckRun| {
+        let output = &check_run.output;
+        let text = [output.summary.as_deref(), output.text.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n");
+        parse_test_scores_from_logs(&text)
+    };
+
+    let job_scores = check_runs
+        .iter()
+        .find(|c| c.id == job_id)
+        .map(scores_of)
+        .unwrap_or_default();
+    if !job_scores.is_empty() {
+        return job_scores;
+    }
+
+    check_runs
+        .iter()
+        .map(scores_of)
+        .find(|scores| !scores.is_empty())
+        .unwrap_or_default()
+}
+
+/// The job step that ran a test, matched by name. The Jobs API doesn't report
+/// step ids, and a step's position proves nothing: a student may have deleted
+/// the test and put another step in its place. A renamed test is still graded
+/// from the reporter's log line for its step id, which also tells whether it
+/// passed; without logs it counts as missing.
+fn find_step<'a>(steps: &'a [JobStep], test_def: &TestDefinition) -> Option<&'a JobStep> {
+    steps.iter().find(|s| s.name == test_def.name)
+}
+
+/// Whether a job ran the autograding steps: the autograding job itself, or
+/// one of its matrix-expanded copies like `run-autograding-tests (1)`
+fn is_autograding_job(name: &str) -> bool {
+    name.strip_prefix(parser::AUTOGRADING_JOB)
+        .is_some_and(|rest| rest.is_empty() || (rest.starts_with(" (") && rest.ends_with(')')))
+}
+
+/// The step that ran a test across the autograding jobs. In a matrix, each
+/// job has the test's step but the entries not running it usually skip it,
+/// so a step that wasn't skipped wins.
+fn find_job_step<'a>(jobs: &'a [Job], test_def: &TestDefinition) -> Option<&'a JobStep> {
+    jobs.iter()
+        .filter_map(|job| find_step(&job.steps, test_def))
+        .min_by_key(|step| step.conclusion.as_deref() == Some("skipped"))
+}
+
+/// Why a job log couldn't be downloaded
+fn logs_problem_of(error: &FetchError) -> LogsProblem {
+    match error {
+        FetchError::Api { status, .. } if *status == reqwest::StatusCode::GONE => LogsProblem::Expired,
+        FetchError::Api { status, .. } if *status == reqwest::StatusCode::FORBIDDEN => LogsProblem::Forbidden,
+        _ => LogsProblem::Unavailable,
+    }
+}
+
+/// Add one job's scores to those of the other jobs. A test that ran in
+/// several matrix entries keeps its best score rather than counting twice.
+fn merge_scores(scores: &mut HashMap<String, u32>, job_scores: HashMap<String, u32>) {
+    for (id, score) in job_scores {
+        let entry = scores.entry(id).or_default();
+        *entry = (*entry).max(score);
+    }
+}
+
+/// Determine a test's status from its job step (if present) and its score from the logs (if any)
+fn test_status(step: Option<&JobStep>, log_score: Option<u32>) -> TestStatus {
+    let Some(step) = step else {
+        // Without a step, a score in the logs still proves the test ran
+        return match log_score {
+            Some(score) if score > 0 => TestStatus::Passed,
+            Some(_) => TestStatus::Failed,
+            None => TestStatus::Missing,
+        };
+    };
+
+    if step.conclusion.as_deref() == Some("skipped") {
+        return TestStatus::Skipped;
+    }
+
+    match log_score {
+        Some(score) if score > 0 => TestStatus::Passed,
+        Some(_) => TestStatus::Failed,
+        None if step.conclusion.as_deref() == Some("success") => TestStatus::Passed,
+        None => TestStatus::Failed,
+    }
+}
+
+/// Give each failed test the first annotation whose title or message names
+/// it (by test name or step id)
+fn attach_failure_messages(
+    tests: &mut IndexMap<String, TestResult>,
+    test_definitions: &[TestDefinition],
+    annotations: &[CheckRunAnnotation],
+) {
+    for test_def in test_definitions {
+        let Some(test) = tests.get_mut(&test_def.name) else {
+            continue;
+        };
+        if test.status != TestStatus::Failed {
+            continue;
+        }
+        test.failure_message = annotations
+            .iter()
+            .find(|a| {
+                let mentions = |text: &str| mentions_word(text, &test_def.name) || mentions_word(text, &test_def.id);
+                a.title.as_deref().is_some_and(mentions) || mentions(&a.message)
+            })
+            .map(|a| a.message.trim().to_string());
+    }
+}
+
+/// Whether `text` has `word` in it as a whole word, so `test_1` isn't found
+/// in `test_10`
+fn mentions_word(text: &str, word: &str) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    text.match_indices(word).any(|(start, _)| {
+        !text[..start].chars().next_back().is_some_and(is_word_char)
+            && !text[start + word.len()..].chars().next().is_some_and(is_word_char)
+    })
+}
+
+/// Where GitHub Classroom autograder workflows live
+const WORKFLOW_PATH: &str = ".github/workflows/classroom.yml";
+
+/// Student repositories tried for the workflow when an assignment has no starter repository
+const MAX_WORKFLOW_SOURCES: usize = 5;
+
+/// Test definitions parsed from each workflow, keyed by the repo and path they
+/// came from, so assignments sharing a starter repo fetch its workflow once
+#[derive(Debug, Clone, Default)]
+pub struct TestDefinitionCache(Arc<Mutex<HashMap<String, Vec<TestDefinition>>>>);
+
+impl TestDefinitionCache {
+    fn get(&self, key: &str) -> Option<Vec<TestDefinition>> {
+        self.0.lock().unwrap().get(key).cloned()
+    }
+
+    fn insert(&self, key: String, test_definitions: Vec<TestDefinition>) {
+        self.0.lock().unwrap().insert(key, test_definitions);
+    }
+}
+
+/// Owner and name of the starter repository, rejecting a URL that doesn't
+/// name one rather than looking up the wrong repository
+fn parse_starter_url(starter_code_url: &str) -> Result<(&str, &str), FetchError> {
+    match parse_repo_url(starter_code_url) {
+        ("", _) | (_, "") => Err(FetchError::InvalidStarterUrl {
+            url: starter_code_url.to_string(),
+        }),
+        owner_and_repo => Ok(owner_and_repo),
+    }
+}
+
+/// Load test definitions from the starter repo, or if there is no starter, from the first
+/// of the first few student repos that has a readable workflow. `on_not_utf8` is
+/// given the path of a workflow that had to be decoded lossily.
+pub async fn load_test_definitions(
     github_client: &GitHubClient,
-    starter_code_url: &str,
-) -> Result<Vec<TestDefinition>> {
-    // Extract owner/repo from starter code URL
-    // URL format: https://github.com/owner/repo
-    let url_parts: Vec<&str> = starter_code_url
-        .trim_end_matches('/')
-        .split('/')
-        .collect();
+    assignment: &Assignment,
+    accepted_assignments: &[AcceptedAssignment],
+    cache: &TestDefinitionCache,
+    on_not_utf8: impl Fn(&str),
+) -> Result<Vec<TestDefinition>, FetchError> {
+    if let Some(starter_url) = assignment.starter_repo() {
+        let (owner, repo) = parse_starter_url(starter_url)?;
+        return load_workflow(github_client, owner, repo, cache, &on_not_utf8).await;
+    }
+
+    if accepted_assignments.is_empty() {
+        return Err(FetchError::NoWorkflowSource { failures: Vec::new() });
+    }
+
+    // A student may have deleted or broken their copy, so try the next few
+    let mut failures = Vec::new();
+    for student in accepted_assignments.iter().take(MAX_WORKFLOW_SOURCES) {
+        let full_name = &student.repository.full_name;
+        let (owner, repo) = parse_repo_url(full_name);
+        if owner.is_empty() || repo.is_empty() {
+            failures.push(format!("{}: invalid repository name", full_name));
+            continue;
+        }
+
+        match load_workflow(github_client, owner, repo, cache, &on_not_utf8).await {
+            Ok(test_definitions) => return Ok(test_definitions),
+            // Other repos won't fare better with a rejected token
+            Err(e @ FetchError::AuthFailed(_)) => return Err(e),
+            Err(e) => failures.push(format!("{}: {}", full_name, error_chain(&e))),
+        }
+    }
+
+    Err(FetchError::NoWorkflowSource { failures })
+}
 
-    if url_parts.len() < 2 {
-        anyhow::bail!("Invalid starter code URL: {}", starter_code_url);
+/// An error and its causes on one line, e.g. "Failed to parse ...: missing field"
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
     }
+    message
+}
 
-    let repo = url_parts[url_parts.len() - 1];
-    let owner = url_parts[url_parts.len() - 2];
+/// Read and parse the autograder workflow of one repository, or take it from the cache
+async fn load_workflow(
+    github_client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    cache: &TestDefinitionCache,
+    on_not_utf8: &impl Fn(&str),
+) -> Result<Vec<TestDefinition>, FetchError> {
+    let key = format!("{}/{}/{}", owner, repo, WORKFLOW_PATH);
+    if let Some(test_definitions) = cache.get(&key) {
+        return Ok(test_definitions);
+    }
 
-    // Fetch workflow YAML file
-    let workflow_content = github_client
-        .get_file_contents(owner, repo, ".github/workflows/classroom.yml")
+    let workflow_bytes = github_client
+        .get_file_bytes(owner, repo, WORKFLOW_PATH)
         .await
-        .context("Failed to fetch workflow file from starter repository")?;
+        .map_err(|e| e.in_workflow_file(format!("{}/{}", owner, repo)))?;
+    // A stray byte (e.g. a Latin-1 comment) shouldn't fail the whole run; it
+    // becomes U+FFFD, which only matters if it's in a test name
+    let workflow_content = String::from_utf8_lossy(&workflow_bytes);
+    if let std::borrow::Cow::Owned(_) = workflow_content {
+        on_not_utf8(&key);
+    }
 
-    // Parse workflow to extract test definitions
-    parser::parse_workflow(&workflow_content)
-        .context("Failed to parse workflow file")
+    let test_definitions = parser::parse_workflow(&workflow_content)
+        .map_err(|e| FetchError::parse(format!("workflow file {}/{}/{}", owner, repo, WORKFLOW_PATH), e))?;
+    cache.insert(key, test_definitions.clone());
+    Ok(test_definitions)
+}
+
+/// Restrict test definitions to the named subset, preserving workflow order
+pub fn filter_test_definitions(
+    test_definitions: Vec<TestDefinition>,
+    names: &[String],
+) -> Result<Vec<TestDefinition>, FetchError> {
+    check_test_names(names.iter(), &test_definitions, "--tests")?;
+
+    Ok(test_definitions
+        .into_iter()
+        .filter(|t| names.contains(&t.name))
+        .collect())
 }
 
-/// Fetch results for a single student
+/// Reject weights for tests the workflow doesn't define, so a typo can't
+/// silently drop a test from the weighted percentage
+pub fn check_weights(weights: &TestWeights, test_definitions: &[TestDefinition]) -> Result<(), FetchError> {
+    check_test_names(weights.0.keys(), test_definitions, "the weights file")
+}
+
+/// Reject per-test floors for tests the workflow doesn't define
+pub fn check_points_floor(floor: &PointsFloor, test_definitions: &[TestDefinition]) -> Result<(), FetchError> {
+    check_test_names(floor.per_test.keys(), test_definitions, "--min-points")
+}
+
+fn check_test_names<'a>(
+    names: impl Iterator<Item = &'a String>,
+    test_definitions: &[TestDefinition],
+    listed_in: &str,
+) -> Result<(), FetchError> {
+    let unknown: Vec<String> = names
+        .filter(|name| !test_definitions.iter().any(|t| &t.name == *name))
+        .cloned()
+        .collect();
+
+    if !unknown.is_empty() {
+        return Err(FetchError::UnknownTests {
+            listed_in: listed_in.to_string(),
+            unknown,
+            available: test_definitions.iter().map(|t| t.name.clone()).collect(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Pick the completed runs to grade from those on `branch`: up to `count`
+/// runs starting with the earliest one for `FirstAfter`, or with the latest
+/// one otherwise (the runs were already limited to the right side of any
+/// deadline by the API). Runs are matched by the branch they ran on, and
+/// everything after this works from the run itself (its jobs and logs), so
+/// renamed default branches are fine.
+fn select_graded_runs(
+    runs: Vec<WorkflowRun>,
+    selection: RunSelection,
+    branch: &str,
+    count: usize,
+) -> Vec<WorkflowRun> {
+    let mut completed: Vec<WorkflowRun> = runs
+        .into_iter()
+        .filter(|r| r.conclusion.is_some() && r.head_branch == branch)
+        .collect();
+
+    // created_at only has second resolution, so runs created in the same second
+    // are ordered by id, which GitHub assigns in increasing order
+    completed.sort_by_key(|r| (r.created_at, r.id));
+    match selection {
+        // Get first run after deadline (minimum created_at)
+        RunSelection::FirstAfter(_) => {}
+        // Get latest run (maximum created_at)
+        RunSelection::Latest | RunSelection::LatestBefore(_) => completed.reverse(),
+    }
+    completed.truncate(count);
+    completed
+}
+
+/// Fetch results for a single student from runs on the `branch` option (the
+/// repo's default branch if not given). If a student who submitted has no run
+/// yet, the run list is checked up to `wait_for_runs` more times. With
+/// `multi_run`, several runs are graded and combined into one result. With
+/// `skip_logs`, points come from the job steps alone (see [`PointsSource::Steps`]).
 pub async fn fetch_student_results(
     github_client: &GitHubClient,
     student: &AcceptedAssignment,
-    deadline: Option<DateTime<Utc>>,
+    selection: RunSelection,
     test_definitions: &[TestDefinition],
-) -> Result<StudentResult> {
+    options: &FetchOptions,
+) -> Result<StudentResult, FetchError> {
     let (owner, repo) = parse_repo_url(&student.repository.full_name);
 
     if owner.is_empty() || repo.is_empty() {
-        anyhow::bail!("Invalid repository name: {}", student.repository.full_name);
+        return Err(FetchError::InvalidRepo {
+            name: student.repository.full_name.clone(),
+        });
     }
 
-    // Get the student username and display name (first student in the list)
-    let username = student
-        .students
-        .first()
-        .map(|s| s.login.clone())
-        .unwrap_or_else(|| "unknown".to_string());
-    let display_name = student
-        .students
-        .first()
-        .and_then(|s| s.name.clone());
+    let username = first_login(student);
 
     // Build filter for workflow runs
-    let created_filter = deadline.map(|dt| format!(">={}", dt.to_rfc3339()));
-
-    // Get workflow runs
-    let runs_response = github_client
-        .list_workflow_runs(
-            owner,
-            repo,
-            Some("repository_dispatch"),
-            created_filter.as_deref(),
-            Some("completed"),
-        )
-        .await
-        .context(format!("Failed to fetch workflow runs for {}", username))?;
+    let created_filter = selection.created_filter();
+    let branch = options.branch.as_deref().unwrap_or(&student.repository.default_branch);
 
-    // Find the first completed run after deadline (or latest if no deadline)
-    let target_run = if let Some(_deadline) = deadline {
-        // Get first run after deadline (minimum created_at)
-        runs_response
+    // Right after a deadline the Actions API may not list a run yet, so students
+    // who did submit get a few more looks before being reported as missing
+    let mut retries_left = if student.submitted { options.wait_for_runs } else { 0 };
+
+    let wanted = options.multi_run.map_or(1, |m| m.runs);
+    let (runs, run_count) = loop {
+        // Get workflow runs. They come newest first, so the latest runs are on
+        // the first pages that have them, but the first run after a deadline
+        // can be on the last page.
+        let matching = AtomicUsize::new(0);
+        let runs_response = github_client
+            .list_workflow_runs(
+                owner,
+                repo,
+                Some("repository_dispatch"),
+                created_filter.as_deref(),
+                Some("completed"),
+                |r| {
+                    if !matches!(selection, RunSelection::FirstAfter(_)) && r.conclusion.is_some() && r.head_branch == branch {
+                        matching.fetch_add(1, Ordering::Relaxed);
+                    }
+                    matching.load(Ordering::Relaxed) >= wanted
+                },
+            )
+            .await
+            .map_err(|e| e.for_student("fetch workflow runs", &username))?;
+
+        let run_count = runs_response
             .workflow_runs
-            .into_iter()
-            .filter(|r| r.conclusion.is_some())
-            .min_by_key(|r| r.created_at)
-    } else {
-        // Get latest run (maximum created_at)
-        runs_response
+            .iter()
+            .filter(|r| r.conclusion.is_some() && r.head_branch == branch)
+            .count();
+        let target_runs = select_graded_runs(runs_response.workflow_runs, selection, branch, wanted);
+
+        if !target_runs.is_empty() {
+            break (target_runs, run_count);
+        }
+
+        // Distinguish a run that is still queued/running from no submission at all
+        let all_runs = github_client
+            .list_workflow_runs(
+                owner,
+                repo,
+                Some("repository_dispatch"),
+                created_filter.as_deref(),
+                None,
+                |r| r.status != "completed" && r.head_branch == branch,
+            )
+            .await
+            .map_err(|e| e.for_student("fetch workflow runs", &username))?;
+
+        if let Some(pending) = all_runs
             .workflow_runs
             .into_iter()
-            .filter(|r| r.conclusion.is_some())
-            .max_by_key(|r| r.created_at)
+            .filter(|r| r.status != "completed" && r.head_branch == branch)
+            .max_by_key(|r| (r.created_at, r.id))
+        {
+            return Err(FetchError::InProgress {
+                username,
+                status: pending.status,
+            });
+        }
+
+        if retries_left == 0 {
+            return Err(FetchError::NoRuns {
+                username,
+                repo_url: student.repository.html_url.clone(),
+            });
+        }
+        retries_left -= 1;
+        tokio::time::sleep(RUN_RETRY_DELAY).await;
     };
 
-    let run = target_run.context(format!(
-        "No completed workflow run found for {}",
-        username
-    ))?;
+    let mut graded = Vec::with_capacity(runs.len());
+    for run in runs {
+        graded.push(grade_run(github_client, student, run, test_definitions, run_count, options).await?);
+    }
+    Ok(match options.multi_run {
+        Some(multi_run) => multi_run.policy.combine(graded),
+        None => graded.swap_remove(0),
+    })
+}
 
-    // Note: We don't use check runs as they don't contain actual points information
-    // The points are only available in the job logs
+/// Grade one completed run of a student's workflow from its jobs' steps and logs
+async fn grade_run(
+    github_client: &GitHubClient,
+    student: &AcceptedAssignment,
+    run: WorkflowRun,
+    test_definitions: &[TestDefinition],
+    run_count: usize,
+    options: &FetchOptions,
+) -> Result<StudentResult, FetchError> {
+    let (owner, repo) = parse_repo_url(&student.repository.full_name);
+    let username = first_login(student);
+    let display_name = student.students.first().and_then(|s| s.name.clone());
 
     // Initialize tests with pass/fail from job steps
     let jobs_response = github_client
         .list_jobs_for_run(owner, repo, run.id)
         .await
-        .context(format!("Failed to fetch jobs for {}", username))?;
+        .map_err(|e| e.for_student("fetch jobs", &username))?;
 
-    let autograding_job = jobs_response
+    // A matrix workflow splits the tests across several autograding jobs
+    let autograding_jobs: Vec<Job> = jobs_response
         .jobs
         .into_iter()
-        .find(|j| j.name == "run-autograding-tests")
-        .context(format!(
-            "No 'run-autograding-tests' job found for {}",
-            username
-        ))?;
+        .filter(|j| is_autograding_job(&j.name))
+        .collect();
+    if autograding_jobs.is_empty() {
+        return Err(FetchError::NoAutogradingJob {
+            job: parser::AUTOGRADING_JOB,
+            username,
+        });
+    }
+
+    let (log_scores, points_source) = if options.skip_logs {
+        (HashMap::new(), PointsSource::Steps)
+    } else {
+        reporter_scores(github_client, owner, repo, &autograding_jobs, &run.head_sha).await
+    };
 
     let mut tests = IndexMap::new();
 
-    // Initialize all tests with 0 points; scores will be set from job logs below
+    // Tests without a log score keep 0 points; the job step tells us whether
+    // the test actually ran or was missing from the student's workflow
     for test_def in test_definitions {
+        let step = find_job_step(&autograding_jobs, test_def);
+        let log_score = log_scores.get(&test_def.id).copied();
+        let status = test_status(step, log_score);
+        let points_awarded = match log_score {
+            Some(score) => score,
+            // Without logs, a test is taken to be all-or-nothing
+            None if points_source == PointsSource::Steps && status == TestStatus::Passed => test_def.max_score,
+            None => 0,
+        };
+
         tests.insert(
             test_def.name.clone(),
             TestResult {
                 _name: test_def.name.clone(),
-                points_awarded: 0,
-                _points_available: test_def.max_score,
-                _passed: false,
+                points_awarded,
+                points_available: test_def.max_score,
+                status,
+                failure_message: None,
             },
         );
     }
 
-    // Parse per-test scores from job logs using the reporter's
-    // "Total points for {runner-id}: {score}/{max}" lines.
-    // The runner-id matches the workflow step id field.
-    if let Ok(logs) = github_client.get_job_logs(owner, repo, autograding_job.id).await {
-        let log_scores = parse_test_scores_from_logs(&logs);
-
-        for test_def in test_definitions {
-            if let Some(&score) = log_scores.get(&test_def.id) {
-                if let Some(result) = tests.get_mut(&test_def.name) {
-                    result.points_awarded = score;
-                    result._passed = score > 0;
-                }
+    // Annotations often carry the assertion message; like the logs, they're
+    // optional, and only fetched when failure messages are exported
+    if options.failure_messages && tests.values().any(|t| t.status == TestStatus::Failed) {
+        let mut annotations = Vec::new();
+        for job in &autograding_jobs {
+            if let Ok(job_annotations) = github_client.list_check_run_annotations(owner, repo, job.id).await {
+                annotations.extend(job_annotations);
             }
         }
+        attach_failure_messages(&mut tests, test_definitions, &annotations);
     }
 
     let total_awarded: u32 = tests.values().map(|t| t.points_awarded).sum();
 
     let total_available = test_definitions.iter().map(|t| t.max_score).sum();
 
+    let mut reported_test_ids: Vec<String> = log_scores.into_keys().collect();
+    reported_test_ids.sort();
+
     Ok(StudentResult {
         username,
         display_name,
         repo_url: student.repository.html_url.clone(),
         workflow_run_timestamp: run.created_at,
+        run_url: format!("{}/actions/runs/{}", student.repository.html_url, run.id),
+        commit_sha: run.head_sha,
+        run_conclusion: run.conclusion.unwrap_or_default(),
         tests,
         total_awarded,
         total_available,
+        weighted_percentage: None,
+        points_source,
+        reported_test_ids,
+        run_count,
+        combined_runs: None,
+        account_deleted: student.students.is_empty(),
     })
 }
 
-/// Fetch results for late grading (both on-time and late deadlines)
-pub async fn fetch_all_late_results(
-    classroom_client: &ClassroomClient,
+/// The raw logs of a result's autograding jobs, for reading the autograder's
+/// own output. A matrix workflow's jobs follow one another, each under a
+/// header line with its name.
+pub async fn fetch_run_logs(github_client: &GitHubClient, result: &StudentResult) -> Result<String, FetchError> {
+    let (owner, repo) = parse_repo_url(&result.repo_url);
+    let run_id = result
+        .run_url
+        .rsplit_once("/actions/runs/")
+        .and_then(|(_, id)| id.parse().ok())
+        .ok_or_else(|| FetchError::InvalidRunUrl { url: result.run_url.clone() })?;
+
+    let jobs: Vec<Job> = github_client
+        .list_jobs_for_run(owner, repo, run_id)
+        .await?
+        .jobs
+        .into_iter()
+        .filter(|j| is_autograding_job(&j.name))
+        .collect();
+    if jobs.is_empty() {
+        return Err(FetchError::NoAutogradingJob {
+            job: parser::AUTOGRADING_JOB,
+            username: result.username.clone(),
+        });
+    }
+
+    let mut logs = String::new();
+    for job in &jobs {
+        if jobs.len() > 1 {
+            logs.push_str(&format!("── {} ──\n", job.name));
+        }
+        logs.push_str(&github_client.get_job_logs(owner, repo, job.id).await?);
+        if !logs.ends_with('\n') {
+            logs.push('\n');
+        }
+    }
+    Ok(logs)
+}
+
+/// Per-test scores by step id from the reporter's "Total points for
+/// {runner-id}: {score}/{max}" lines, where the runner-id matches the
+/// workflow step id field, and where they were found
+async fn reporter_scores(
     github_client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    autograding_jobs: &[Job],
+    head_sha: &str,
+) -> (HashMap<String, u32>, PointsSource) {
+    let mut log_scores = HashMap::new();
+    let mut logs_problem = None;
+    for job in autograding_jobs {
+        match github_client.get_job_logs(owner, repo, job.id).await {
+            Ok(logs) => merge_scores(&mut log_scores, parse_test_scores_from_logs(&logs)),
+            Err(e) => {
+                logs_problem.get_or_insert(logs_problem_of(&e));
+            }
+        }
+    }
+    if log_scores.is_empty() {
+        logs_problem.get_or_insert(LogsProblem::NoScores);
+    }
+
+    // Logs of older runs expire (410 Gone), but some reporters also put the
+    // same lines in the check run output, which is kept
+    let Some(problem) = logs_problem else {
+        return (log_scores, PointsSource::Logs);
+    };
+    let mut points_source = PointsSource::Approximate(problem);
+    if let Ok(check_runs) = github_client.list_check_runs_for_ref(owner, repo, head_sha).await {
+        let mut check_run_scores = HashMap::new();
+        for job in autograding_jobs {
+            merge_scores(&mut check_run_scores, scores_from_check_runs(&check_runs.check_runs, job.id));
+        }
+        if !check_run_scores.is_empty() {
+            merge_scores(&mut log_scores, check_run_scores);
+            points_source = PointsSource::CheckRuns(problem);
+        }
+    }
+    (log_scores, points_source)
+}
+
+/// Student repositories tried before concluding the token can't read any
+const MAX_ACCESS_CHECKS: usize = 3;
+
+/// Make sure the token can read student repositories before fetching everyone,
+/// so a token without access to the organization fails once, clearly, instead
+/// of once per student. A few repos are tried since a student may have deleted theirs.
+async fn check_repo_access(
+    github_client: &GitHubClient,
+    accepted_assignments: &[AcceptedAssignment],
+) -> Result<(), FetchError> {
+    let mut tried = Vec::new();
+    for student in accepted_assignments.iter().take(MAX_ACCESS_CHECKS) {
+        let (owner, repo) = parse_repo_url(&student.repository.full_name);
+        if owner.is_empty() || repo.is_empty() {
+            continue;
+        }
+        match github_client.get_repository(owner, repo).await {
+            Ok(_) => return Ok(()),
+            Err(FetchError::NotFound { .. }) => {}
+            Err(FetchError::Api { status, .. }) if status == reqwest::StatusCode::FORBIDDEN => {}
+            Err(e) => return Err(e),
+        }
+        tried.push((owner, repo));
+    }
+
+    match tried.first() {
+        Some((org, _)) => Err(FetchError::NoRepoAccess {
+            org: org.to_string(),
+            repos: tried.iter().map(|(_, repo)| *repo).collect::<Vec<_>>().join(", "),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// `previous`, if it was graded with the same tests and the student hasn't
+/// pushed since its run was created. Pushes are what trigger Classroom's
+/// grading runs, so newer results can't exist yet.
+async fn unchanged_result(
+    github_client: &GitHubClient,
+    student: &AcceptedAssignment,
+    previous: StudentResult,
+    test_definitions: &[TestDefinition],
+) -> Result<Option<StudentResult>, FetchError> {
+    if !previous.tests.keys().eq(test_definitions.iter().map(|t| &t.name)) {
+        return Ok(None);
+    }
+
+    let pushed_at = match student.repository.pushed_at {
+        Some(pushed_at) => Some(pushed_at),
+        None => {
+            let (owner, repo) = parse_repo_url(&student.repository.full_name);
+            github_client.get_repository(owner, repo).await?.pushed_at
+        }
+    };
+    Ok(pushed_at
+        .is_some_and(|pushed_at| pushed_at < previous.workflow_run_timestamp)
+        .then_some(previous))
+}
+
+/// Narrow the roster to the accepted assignment of one student, matched by
+/// GitHub username (case-insensitive, including group members)
+pub fn select_student(
+    accepted_assignments: Vec<AcceptedAssignment>,
+    username: &str,
+) -> Result<Vec<AcceptedAssignment>, FetchError> {
+    let selected: Vec<_> = accepted_assignments
+        .into_iter()
+        .filter(|accepted| {
+            accepted
+                .students
+                .iter()
+                .any(|s| s.login.eq_ignore_ascii_case(username))
+        })
+        .collect();
+
+    if selected.is_empty() {
+        return Err(FetchError::UnknownStudent {
+            username: username.to_string(),
+        });
+    }
+
+    Ok(selected)
+}
+
+/// How one student's fetch ended
+#[derive(Debug)]
+pub enum StudentOutcome<'a> {
+    Graded(&'a StudentResult),
+    /// Both of a student's runs for late grading were graded
+    LateGraded(&'a LateGradingResult),
+    /// The student's grading run is still queued or running
+    InProgress,
+    /// The student has no completed grading run, i.e. hasn't submitted
+    NotSubmitted { repo_url: &'a str },
+    Failed(&'a FetchError),
+}
+
+/// Progress reported by [`fetch_all_results`] and [`fetch_all_late_results`]
+#[derive(Debug)]
+pub enum FetchEvent<'a> {
+    /// The roster and test definitions are loaded and student fetches are about to start
+    Loaded {
+        students: usize,
+        /// The students about to be fetched, in roster order
+        usernames: Vec<&'a str>,
+        tests: &'a [TestDefinition],
+        /// Students left out because they're on the `exclude` list, e.g. staff
+        excluded: usize,
+        /// Students left out because Classroom already reports them as passing
+        skipped_passing: usize,
+        /// Students left out by the `max_students` cap
+        over_cap: usize,
+    },
+    /// A student's fetch has started; `index` is their position in the roster
+    Started { index: usize, total: usize, student: &'a str },
+    /// A student's fetch ended; `completed` counts this student
+    Finished {
+        completed: usize,
+        total: usize,
+        student: &'a str,
+        outcome: StudentOutcome<'a>,
+    },
+    /// GitHub's abuse detection kicked in; `concurrency` students are now fetched at once
+    SecondaryRateLimited { concurrency: usize },
+    /// The workflow's tests add up to different points than GitHub Classroom grades out of
+    PointsMismatch { workflow: u32, classroom: u32 },
+    /// The student hasn't pushed since their previous result's run, which is reused
+    Unchanged { student: &'a str },
+    /// The starter repo's workflow disagrees with the first student's graded run:
+    /// `missing` tests have no step in the run, and `unknown` step ids were
+    /// scored in the run but aren't in the workflow
+    WorkflowDrift {
+        student: &'a str,
+        missing: Vec<&'a str>,
+        unknown: Vec<&'a str>,
+    },
+    /// The workflow file (`owner/repo/path`) isn't valid UTF-8 and was read with
+    /// the invalid bytes replaced
+    WorkflowNotUtf8 { workflow: &'a str },
+    /// These students' accounts were deleted, so they're reported under their
+    /// repository names
+    DeletedAccounts { repos: Vec<String> },
+}
+
+pub type FetchCallback = Box<dyn Fn(FetchEvent<'_>) + Send + Sync>;
+
+/// The total points of the workflow's tests and of GitHub Classroom's grades,
+/// if they differ. Classroom's total is the most common one among the
+/// students' grades (e.g. the 10 of "8/10"), so it's unknown until someone
+/// has been graded.
+pub fn points_mismatch(
+    test_definitions: &[TestDefinition],
+    accepted_assignments: &[AcceptedAssignment],
+) -> Option<(u32, u32)> {
+    let mut counts: HashMap<u32, usize> = HashMap::new();
+    for available in accepted_assignments.iter().filter_map(AcceptedAssignment::grade_available) {
+        *counts.entry(available).or_default() += 1;
+    }
+    let classroom = counts
+        .into_iter()
+        .max_by_key(|&(available, count)| (count, available))
+        .map(|(available, _)| available)?;
+
+    let workflow = test_definitions.iter().map(|t| t.max_score).sum();
+    (workflow != classroom).then_some((workflow, classroom))
+}
+
+/// Keep only the first `max_students` students, if set, returning how many were left out
+fn first_students(
+    mut accepted_assignments: Vec<AcceptedAssignment>,
+    max_students: Option<usize>,
+) -> (Vec<AcceptedAssignment>, usize) {
+    let total = accepted_assignments.len();
+    if let Some(max) = max_students {
+        accepted_assignments.truncate(max);
+    }
+    let over_cap = total - accepted_assignments.len();
+    (accepted_assignments, over_cap)
+}
+
+/// Fetch every student's results for an assignment, keeping up to
+/// `concurrency` students in flight. Results come back in roster order;
+/// students that failed or are still being graded are reported through
+/// `on_event` and left out. `roster`, if given, is graded instead of the
+/// accepted assignments the API lists. A student's result in `previous` is
+/// reused instead of fetched if they haven't pushed since its run.
+pub async fn fetch_all_results(
+    context: FetchContext<'_>,
     assignment_id: u64,
-    on_time_deadline: DateTime<Utc>,
-    late_deadline: DateTime<Utc>,
-    late_penalty: f64,
-    progress_callback: Option<Box<dyn Fn(usize, usize, &str) + Send>>,
-) -> Result<Vec<crate::models::LateGradingResult>> {
-    // Get assignment details
-    let assignment = classroom_client
-        .get_assignment(assignment_id)
-        .await
-        .context("Failed to fetch assignment details")?;
+    roster: Option<Vec<AcceptedAssignment>>,
+    selection: RunSelection,
+    previous: Option<&[StudentResult]>,
+    on_event: Option<FetchCallback>,
+) -> Result<Vec<StudentResult>, FetchError> {
+    let FetchContext { classroom_client, github_client, options, test_definition_cache } = context;
+    let emit = |event: FetchEvent<'_>| {
+        if let Some(callback) = &on_event {
+            callback(event);
+        }
+    };
 
-    // Get all accepted assignments (students)
-    let accepted_assignments = classroom_client
-        .list_accepted_assignments(assignment_id)
-        .await
-        .context("Failed to fetch accepted assignments")?;
+    let assignment = classroom_client.get_assignment(assignment_id).await?;
+
+    let accepted_assignments = match roster {
+        Some(roster) => roster,
+        None => classroom_client.list_accepted_assignments(assignment_id).await?,
+    };
 
     if accepted_assignments.is_empty() {
-        anyhow::bail!("No students have accepted this assignment yet");
+        return Err(FetchError::NoAcceptedAssignments);
     }
+    check_repo_access(github_client, &accepted_assignments).await?;
 
-    // Fetch test definitions from starter repo, or from first student's repo if no starter
-    let test_definitions = if let Some(starter_url) = &assignment.starter_code_url {
-        fetch_test_definitions(github_client, starter_url).await?
+    let test_definitions = load_test_definitions(
+        github_client,
+        &assignment,
+        &accepted_assignments,
+        test_definition_cache,
+        |workflow| emit(FetchEvent::WorkflowNotUtf8 { workflow }),
+    ).await?;
+    if let Some((workflow, classroom)) = points_mismatch(&test_definitions, &accepted_assignments) {
+        emit(FetchEvent::PointsMismatch { workflow, classroom });
+    }
+    if let Some(weights) = &options.weights {
+        check_weights(weights, &test_definitions)?;
+    }
+    if let Some(floor) = &options.points_floor {
+        check_points_floor(floor, &test_definitions)?;
+    }
+    // Only a starter workflow can drift from what students run; one read from
+    // a student repo is what that student runs
+    let mut drift_check = (options.check_workflow && assignment.starter_repo().is_some())
+        .then(|| WorkflowDriftCheck::new(&test_definitions));
+    let test_definitions = match &options.test_filter {
+        Some(names) => filter_test_definitions(test_definitions, names)?,
+        None => test_definitions,
+    };
+
+    let (accepted_assignments, excluded) = without_excluded(accepted_assignments, &options.exclude);
+
+    let accepted_assignments = match &options.student {
+        Some(username) => select_student(accepted_assignments, username)?,
+        None => accepted_assignments,
+    };
+
+    let (accepted_assignments, skipped_passing) = if options.skip_passing {
+        without_passing(accepted_assignments)
     } else {
-        // No starter repo, fetch from first student's repository
-        let first_student = &accepted_assignments[0];
-        let (owner, repo) = parse_repo_url(&first_student.repository.full_name);
+        (accepted_assignments, 0)
+    };
 
-        if owner.is_empty() || repo.is_empty() {
-            anyhow::bail!("Invalid repository name: {}", first_student.repository.full_name);
+    let (accepted_assignments, over_cap) = first_students(accepted_assignments, options.max_students);
+
+    let deleted = deleted_accounts(&accepted_assignments);
+    if !deleted.is_empty() {
+        emit(FetchEvent::DeletedAccounts { repos: deleted });
+    }
+
+    let total = accepted_assignments.len();
+    let usernames: Vec<String> = accepted_assignments.iter().map(first_login).collect();
+    emit(FetchEvent::Loaded {
+        students: total,
+        usernames: usernames.iter().map(String::as_str).collect(),
+        tests: &test_definitions,
+        excluded,
+        skipped_passing,
+        over_cap,
+    });
+
+    let test_definitions = Arc::new(test_definitions);
+    let shared_options = Arc::new(options.clone());
+    let mut pending = accepted_assignments.into_iter().enumerate();
+    let mut tasks = JoinSet::new();
+    let mut results = Vec::new();
+    let mut completed = 0;
+    // Halved each time GitHub's abuse detection kicks in
+    let mut concurrency = options.concurrency.max(1);
+    let mut secondary_limit_hits = github_client.secondary_limit_hits();
+
+    loop {
+        while tasks.len() < concurrency {
+            let Some((index, student)) = pending.next() else {
+                break;
+            };
+            let student_name = first_login(&student);
+
+            emit(FetchEvent::Started {
+                index,
+                total,
+                student: &student_name,
+            });
+
+            let github_client = github_client.clone();
+            let test_definitions = Arc::clone(&test_definitions);
+            let options = Arc::clone(&shared_options);
+            let previous = previous
+                .and_then(|previous| previous.iter().find(|r| r.username == student_name))
+                .cloned();
+            tasks.spawn(async move {
+                let unchanged = match previous {
+                    Some(previous) => unchanged_result(&github_client, &student, previous, &test_definitions).await,
+                    None => Ok(None),
+                };
+                let (result, reused) = match unchanged {
+                    Ok(Some(previous)) => (Ok(previous), true),
+                    Ok(None) => {
+                        let result = fetch_student_results(
+                            &github_client,
+                            &student,
+                            selection,
+                            &test_definitions,
+                            &options,
+                        ).await;
+                        (result, false)
+                    }
+                    Err(e) => (Err(e), false),
+                };
+                (index, student_name, result, reused)
+            });
         }
 
-        let workflow_content = github_client
-            .get_file_contents(owner, repo, ".github/workflows/classroom.yml")
-            .await
-            .context("Failed to fetch workflow file from first student's repository")?;
+        let Some(joined) = tasks.join_next().await else {
+            break;
+        };
+        let (index, student_name, result, reused) = joined?;
+        completed += 1;
+        if reused {
+            emit(FetchEvent::Unchanged { student: &student_name });
+        }
 
-        parser::parse_workflow(&workflow_content)
-            .context("Failed to parse workflow file")?
+        let hits = github_client.secondary_limit_hits();
+        if hits > secondary_limit_hits {
+            secondary_limit_hits = hits;
+            concurrency = (concurrency / 2).max(1);
+            emit(FetchEvent::SecondaryRateLimited { concurrency });
+        }
+
+        let result = match result.and_then(|result| options.interrupted_runs.check(result)) {
+            // Abort the run so it can be retried after re-authenticating
+            Err(e @ FetchError::AuthFailed(_)) => return Err(e),
+            Ok(mut result) => {
+                if let Some(floor) = &options.points_floor {
+                    floor.apply(&mut result);
+                }
+                result.weighted_percentage = options.weights.as_ref().map(|w| w.percentage(&result));
+                Ok(result)
+            }
+            result => result,
+        };
+
+        let outcome = match &result {
+            Ok(result) => StudentOutcome::Graded(result),
+            Err(FetchError::InProgress { .. }) => StudentOutcome::InProgress,
+            Err(FetchError::NoRuns { repo_url, .. }) => StudentOutcome::NotSubmitted { repo_url },
+            Err(e) => StudentOutcome::Failed(e),
+        };
+        emit(FetchEvent::Finished {
+            completed,
+            total,
+            student: &student_name,
+            outcome,
+        });
+        if let Ok(result) = &result
+            && let Some(check) = drift_check.take_if(|_| !result.run_interrupted())
+        {
+            check.run(&student_name, result, &emit);
+        }
+
+        if let Ok(result) = result {
+            results.push((index, result));
+        }
+    }
+
+    // Restore roster order, since students finish in arbitrary order
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+/// Compares the starter workflow with the first graded run that finished, to
+/// catch a starter that no longer matches the workflow students were given
+struct WorkflowDriftCheck {
+    /// Step ids of all the workflow's tests, before any `--tests` filter
+    test_ids: Vec<String>,
+}
+
+impl WorkflowDriftCheck {
+    fn new(test_definitions: &[TestDefinition]) -> Self {
+        Self {
+            test_ids: test_definitions.iter().map(|t| t.id.clone()).collect(),
+        }
+    }
+
+    /// Report any tests the workflow and `result`'s run disagree on
+    fn run(self, student: &str, result: &StudentResult, emit: &impl Fn(FetchEvent<'_>)) {
+        let missing = result.missing_tests();
+        let unknown: Vec<&str> = result
+            .reported_test_ids
+            .iter()
+            .filter(|id| !self.test_ids.contains(id))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() || !unknown.is_empty() {
+            emit(FetchEvent::WorkflowDrift { student, missing, unknown });
+        }
+    }
+}
+
+/// Username a student's fetch is reported under: the first one for a group,
+/// or the repository name if their account was deleted, which leaves the
+/// accepted assignment without students. Repository names are unique, so
+/// such students don't collide with each other; the repository id stands in
+/// for a name that can't be read.
+fn first_login(accepted: &AcceptedAssignment) -> String {
+    match accepted.students.first() {
+        Some(student) => student.login.clone(),
+        None => match parse_repo_url(&accepted.repository.full_name).1 {
+            "" => accepted.repository.id.to_string(),
+            name => name.to_string(),
+        },
+    }
+}
+
+/// Identifiers of the students whose account was deleted (see [`first_login`])
+fn deleted_accounts(accepted_assignments: &[AcceptedAssignment]) -> Vec<String> {
+    accepted_assignments
+        .iter()
+        .filter(|accepted| accepted.students.is_empty())
+        .map(first_login)
+        .collect()
+}
+
+/// Leave out accepted assignments with a student in `exclude` (GitHub
+/// usernames, ignoring case), returning the rest and how many were left out
+pub fn without_excluded(
+    accepted_assignments: Vec<AcceptedAssignment>,
+    exclude: &[String],
+) -> (Vec<AcceptedAssignment>, usize) {
+    let total = accepted_assignments.len();
+    let remaining: Vec<_> = accepted_assignments
+        .into_iter()
+        .filter(|accepted| {
+            !accepted
+                .students
+                .iter()
+                .any(|student| exclude.iter().any(|login| login.eq_ignore_ascii_case(&student.login)))
+        })
+        .collect();
+    let excluded = total - remaining.len();
+    (remaining, excluded)
+}
+
+/// Leave out students whose latest submission Classroom already reports as
+/// passing, returning the rest and how many were left out
+pub fn without_passing(accepted_assignments: Vec<AcceptedAssignment>) -> (Vec<AcceptedAssignment>, usize) {
+    let total = accepted_assignments.len();
+    let remaining: Vec<_> = accepted_assignments
+        .into_iter()
+        .filter(|accepted| !accepted.passing)
+        .collect();
+    let skipped = total - remaining.len();
+    (remaining, skipped)
+}
+
+/// Fetch results for late grading (both on-time and late deadlines), one
+/// student at a time. Students that failed or are still being graded are
+/// reported through `on_event` and left out. `roster` is used like in
+/// [`fetch_all_results`]; options that only apply to regular grading are ignored.
+pub async fn fetch_all_late_results(
+    context: FetchContext<'_>,
+    assignment_id: u64,
+    roster: Option<Vec<AcceptedAssignment>>,
+    deadlines: &LateGradingDeadlines,
+    on_event: Option<FetchCallback>,
+) -> Result<Vec<LateGradingResult>, FetchError> {
+    let FetchContext { classroom_client, github_client, test_definition_cache, .. } = context;
+    // Each deadline's run is graded on its own, never combined with others
+    let options = &FetchOptions {
+        multi_run: None,
+        ..context.options.clone()
+    };
+    let emit = |event: FetchEvent<'_>| {
+        if let Some(callback) = &on_event {
+            callback(event);
+        }
     };
 
-    let total_students = accepted_assignments.len();
-    let mut results = Vec::new();
+    // Get assignment details
+    let assignment = classroom_client.get_assignment(assignment_id).await?;
+
+    // Get all accepted assignments (students)
+    let accepted_assignments = match roster {
+        Some(roster) => roster,
+        None => classroom_client.list_accepted_assignments(assignment_id).await?,
+    };
+
+    if accepted_assignments.is_empty() {
+        return Err(FetchError::NoAcceptedAssignments);
+    }
+    check_repo_access(github_client, &accepted_assignments).await?;
+
+    let test_definitions = load_test_definitions(
+        github_client,
+        &assignment,
+        &accepted_assignments,
+        test_definition_cache,
+        |workflow| emit(FetchEvent::WorkflowNotUtf8 { workflow }),
+    ).await?;
+    if let Some((workflow, classroom)) = points_mismatch(&test_definitions, &accepted_assignments) {
+        emit(FetchEvent::PointsMismatch { workflow, classroom });
+    }
+    if let Some(floor) = &options.points_floor {
+        check_points_floor(floor, &test_definitions)?;
+    }
+    // Only a starter workflow can drift from what students run; one read from
+    // a student repo is what that student runs
+    let mut drift_check = (options.check_workflow && assignment.starter_repo().is_some())
+        .then(|| WorkflowDriftCheck::new(&test_definitions));
+    let test_definitions = match &options.test_filter {
+        Some(names) => filter_test_definitions(test_definitions, names)?,
+        None => test_definitions,
+    };
+
+    let (accepted_assignments, excluded) = without_excluded(accepted_assignments, &options.exclude);
+
+    let accepted_assignments = match &options.student {
+        Some(username) => select_student(accepted_assignments, username)?,
+        None => accepted_assignments,
+    };
+
+    let (accepted_assignments, over_cap) = first_students(accepted_assignments, options.max_students);
 
-    // Fetch results for each student
+    let deleted = deleted_accounts(&accepted_assignments);
+    if !deleted.is_empty() {
+        emit(FetchEvent::DeletedAccounts { repos: deleted });
+    }
+
+    let total = accepted_assignments.len();
+    let usernames: Vec<String> = accepted_assignments.iter().map(first_login).collect();
+    emit(FetchEvent::Loaded {
+        students: total,
+        usernames: usernames.iter().map(String::as_str).collect(),
+        tests: &test_definitions,
+        excluded,
+        skipped_passing: 0,
+        over_cap,
+    });
+
+    let test_definitions = &test_definitions;
+    let mut results = Vec::new();
     for (index, student) in accepted_assignments.iter().enumerate() {
-        let student_name = student
-            .students
-            .first()
-            .map(|s| s.login.as_str())
-            .unwrap_or("unknown");
-
-        // Call progress callback if provided
-        if let Some(ref callback) = progress_callback {
-            callback(index + 1, total_students, student_name);
-        }
-
-        // Fetch on-time results
-        let on_time_result = match fetch_student_results(
-            github_client,
-            student,
-            Some(on_time_deadline),
-            &test_definitions
-        ).await {
-            Ok(result) => result,
-            Err(e) => {
-                eprintln!("Error fetching on-time results for {}: {}", student_name, e);
-                continue;
-            }
+        let student_name = &first_login(student);
+
+        emit(FetchEvent::Started {
+            index,
+            total,
+            student: student_name,
+        });
+
+        let fetch = |deadline| async move {
+            fetch_student_results(github_client, student, RunSelection::FirstAfter(deadline), test_definitions, options)
+            .await
+            .and_then(|result| options.interrupted_runs.check(result))
+            .map(|mut result| {
+                if let Some(floor) = &options.points_floor {
+                    floor.apply(&mut result);
+                }
+                result
+            })
+        };
+        let result = match fetch(deadlines.on_time_deadline).await {
+            Ok(on_time_result) => fetch(deadlines.late_deadline)
+                .await
+                .map(|late_result| LateGradingResult::new(on_time_result, late_result, deadlines.late_penalty / 100.0)),
+            Err(e) => Err(e),
         };
 
-        // Fetch late results
-        let late_result = match fetch_student_results(
-            github_client,
-            student,
-            Some(late_deadline),
-            &test_definitions
-        ).await {
-            Ok(result) => result,
-            Err(e) => {
-                eprintln!("Error fetching late results for {}: {}", student_name, e);
-                continue;
+        let result = match result {
+            // Abort the run so it can be retried after re-authenticating
+            Err(e @ FetchError::AuthFailed(_)) => return Err(e),
+            result => result,
+        };
+
+        let outcome = match &result {
+            Ok(result) => StudentOutcome::LateGraded(result),
+            Err(FetchError::InProgress { .. }) => StudentOutcome::InProgress,
+            Err(FetchError::NoRuns { repo_url, .. }) => StudentOutcome::NotSubmitted { repo_url },
+            Err(e) => StudentOutcome::Failed(e),
+        };
+        emit(FetchEvent::Finished {
+            completed: index + 1,
+            total,
+            student: student_name,
+            outcome,
+        });
+        if let Ok(result) = &result
+            && let Some(check) = drift_check.take_if(|_| !result.on_time_result.run_interrupted())
+        {
+            check.run(student_name, &result.on_time_result, &emit);
+        }
+
+        if let Ok(result) = result {
+            results.push(result);
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CheckRunOutput;
+
+    fn definition(name: &str) -> TestDefinition {
+        TestDefinition {
+            name: name.to_string(),
+            id: name.replace('_', "-"),
+            max_score: 5,
+        }
+    }
+
+    #[test]
+    fn test_interrupted_runs() {
+        let result = |conclusion: &str| StudentResult {
+            run_conclusion: conclusion.to_string(),
+            ..StudentResult::sample("alice")
+        };
+
+        assert!(InterruptedRuns::Grade.check(result("cancelled")).is_ok());
+        assert!(InterruptedRuns::Review.check(result("failure")).is_ok());
+        let err = InterruptedRuns::Review.check(result("timed_out")).unwrap_err();
+        assert!(matches!(err, FetchError::RunInterrupted { ref conclusion, .. } if conclusion == "timed_out"));
+    }
+
+    #[test]
+    fn test_workflow_drift_check() {
+        let test = |status: TestStatus| TestResult {
+            _name: String::new(),
+            points_awarded: 0,
+            points_available: 5,
+            status,
+            failure_message: None,
+        };
+        let result = StudentResult {
+            run_conclusion: "failure".to_string(),
+            tests: [("test_1", test(TestStatus::Failed)), ("test_2", test(TestStatus::Missing))]
+                .into_iter()
+                .map(|(name, test)| (name.to_string(), test))
+                .collect(),
+            // test-3 is left out of the export by --tests, test-4 isn't in the starter
+            reported_test_ids: vec!["test-1".to_string(), "test-3".to_string(), "test-4".to_string()],
+            ..StudentResult::sample("alice")
+        };
+        let check = WorkflowDriftCheck::new(&[definition("test_1"), definition("test_2"), definition("test_3")]);
+
+        let drift = Mutex::new(None);
+        check.run("alice", &result, &|event| {
+            if let FetchEvent::WorkflowDrift { missing, unknown, .. } = event {
+                *drift.lock().unwrap() = Some((missing.join(","), unknown.join(",")));
             }
+        });
+        assert_eq!(drift.into_inner().unwrap(), Some(("test_2".to_string(), "test-4".to_string())));
+    }
+
+    #[test]
+    fn test_points_floor() {
+        let test = |points_awarded: u32, status: TestStatus| TestResult {
+            _name: String::new(),
+            points_awarded,
+            points_available: 5,
+            status,
+            failure_message: None,
+        };
+        let mut result = StudentResult {
+            run_conclusion: "failure".to_string(),
+            tests: [
+                ("passed", test(4, TestStatus::Passed)),
+                ("failed", test(0, TestStatus::Failed)),
+                ("style", test(0, TestStatus::Failed)),
+                ("skipped", test(0, TestStatus::Skipped)),
+                ("missing", test(0, TestStatus::Missing)),
+            ]
+            .into_iter()
+            .map(|(name, test)| (name.to_string(), test))
+            .collect(),
+            total_awarded: 4,
+            total_available: 25,
+            ..StudentResult::sample("alice")
+        };
+
+        let floor = crate::config::parse_points_floor(&["2".to_string(), "style=9".to_string()]).unwrap();
+        floor.apply(&mut result);
+        let points: Vec<u32> = result.tests.values().map(|t| t.points_awarded).collect();
+        // Only tests that ran are raised, and never past their own points
+        assert_eq!(points, [4, 2, 5, 0, 0]);
+        assert_eq!(result.total_awarded, 11);
+        assert_eq!(result.tests["failed"].status, TestStatus::Failed);
+
+        assert!(check_points_floor(&floor, &[definition("passed")]).is_err());
+        assert!(crate::config::parse_points_floor(&["style=-1".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_step_in_deleted_tests_place() {
+        let step = |number: u32, name: &str| JobStep {
+            name: name.to_string(),
+            status: "completed".to_string(),
+            conclusion: Some("success".to_string()),
+            number,
+            started_at: None,
+            completed_at: None,
+        };
+        // test_1 was deleted and a setup step put where it was
+        let steps = [
+            step(1, "Set up job"),
+            step(2, "Checkout code"),
+            step(3, "Setup python"),
+            step(4, "test_2"),
+        ];
+        let definitions = [definition("test_1"), definition("test_2")];
+
+        assert!(find_step(&steps, &definitions[0]).is_none());
+        assert_eq!(find_step(&steps, &definitions[1]).unwrap().number, 4);
+        assert_eq!(test_status(find_step(&steps, &definitions[0]), None), TestStatus::Missing);
+        // A renamed test is still found by the reporter's line for its step id
+        assert_eq!(test_status(find_step(&steps, &definitions[0]), Some(5)), TestStatus::Passed);
+    }
+
+    #[test]
+    fn test_parse_repo_url() {
+        for name in [
+            "owner/repo",
+            "owner/repo/",
+            "owner/repo.git",
+            "https://github.com/owner/repo",
+            "https://github.com/owner/repo/",
+            "https://github.com/owner/repo.git",
+            "https://github.com/owner/repo/tree/main",
+            "https://github.com/owner/repo/blob/main/.github/workflows/classroom.yml",
+            "https://github.com/owner/repo?tab=readme-ov-file",
+            "https://github.com/owner/repo#readme",
+            "http://www.github.com/owner/repo",
+            "github.com/owner/repo",
+            "github.com/owner/repo.git",
+            "ssh://git@github.com/owner/repo.git",
+            "git@github.com:owner/repo.git",
+            "git@github.com:owner/repo",
+            "  owner/repo\n",
+        ] {
+            assert_eq!(parse_repo_url(name), ("owner", "repo"), "{}", name);
+        }
+
+        for name in ["", "repo", "https://github.com/owner", "https://github.com/", "github.com/owner", "owner/.git"] {
+            assert_eq!(parse_repo_url(name), ("", ""), "{}", name);
+        }
+        assert!(parse_starter_url("https://github.com/owner").is_err());
+        assert_eq!(parse_starter_url("https://github.com/org/starter/").unwrap(), ("org", "starter"));
+
+        // The repository object wins over the URL
+        let assignment: Assignment = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "title": "Homework 1",
+            "slug": "hw1",
+            "deadline": null,
+            "starter_code_url": "https://github.com/org/old-starter",
+            "starter_code_repository": { "id": 5, "full_name": "org/starter", "default_branch": "main" },
+            "classroom": { "id": 1, "name": "Classroom 1" },
+        }))
+        .unwrap();
+        assert_eq!(assignment.starter_repo(), Some("org/starter"));
+    }
+
+    #[test]
+    fn test_student_errors_say_what_failed() {
+        let not_found = FetchError::NotFound { url: "https://api.github.com/repos/org/repo-1/actions/runs/7/jobs".to_string() };
+        assert_eq!(
+            not_found.for_student("fetch jobs", "alice").to_string(),
+            "Failed to fetch jobs for alice: Not found (404): https://api.github.com/repos/org/repo-1/actions/runs/7/jobs"
+        );
+        // Still recognizable so the run can stop to re-authenticate
+        let rejected = FetchError::AuthFailed("Bad credentials".to_string()).for_student("fetch jobs", "alice");
+        assert!(matches!(rejected, FetchError::AuthFailed(_)));
+    }
+
+    #[test]
+    fn test_filter_test_definitions() {
+        let definitions = vec![definition("test_a"), definition("test_b"), definition("test_c")];
+
+        let filtered = filter_test_definitions(
+            definitions.clone(),
+            &["test_c".to_string(), "test_a".to_string()],
+        )
+        .unwrap();
+        let names: Vec<&str> = filtered.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["test_a", "test_c"]);
+
+        let err = filter_test_definitions(definitions, &["test_z".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("test_z"));
+        assert!(err.to_string().contains("test_a, test_b, test_c"));
+    }
+
+    fn select_graded_run(runs: Vec<WorkflowRun>, selection: RunSelection, branch: &str) -> Option<WorkflowRun> {
+        select_graded_runs(runs, selection, branch, 1).pop()
+    }
+
+    fn run(id: u64, branch: &str, created_at: &str, conclusion: Option<&str>) -> WorkflowRun {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": "GitHub Classroom Workflow",
+            "head_branch": branch,
+            "head_sha": format!("{:040x}", id),
+            "status": if conclusion.is_some() { "completed" } else { "in_progress" },
+            "conclusion": conclusion,
+            "created_at": created_at,
+            "updated_at": created_at,
+            "run_started_at": null,
+            "event": "repository_dispatch",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_select_graded_run_on_renamed_default_branch() {
+        // A student repo whose default branch was renamed from main to trunk
+        let runs = || {
+            vec![
+                run(1, "main", "2025-01-10T12:00:00Z", Some("success")),
+                run(2, "trunk", "2025-01-11T12:00:00Z", Some("failure")),
+                run(3, "trunk", "2025-01-12T12:00:00Z", Some("success")),
+                run(4, "trunk", "2025-01-13T12:00:00Z", None),
+            ]
         };
 
-        // Create late grading result
-        let late_grading_result = crate::models::LateGradingResult::new(
-            on_time_result,
-            late_result,
-            late_penalty,
+        let latest = select_graded_run(runs(), RunSelection::Latest, "trunk").unwrap();
+        assert_eq!(latest.id, 3);
+
+        // With a deadline the API only returns runs created after it
+        let deadline = "2025-01-11T00:00:00Z".parse().unwrap();
+        let first_after_deadline =
+            select_graded_run(runs()[1..].to_vec(), RunSelection::FirstAfter(deadline), "trunk").unwrap();
+        assert_eq!(first_after_deadline.id, 2);
+
+        assert!(select_graded_run(runs(), RunSelection::Latest, "submission").is_none());
+    }
+
+    #[test]
+    fn test_combine_runs() {
+        let runs = vec![
+            run(1, "main", "2025-01-10T12:00:00Z", Some("success")),
+            run(2, "main", "2025-01-11T12:00:00Z", Some("failure")),
+            run(3, "main", "2025-01-12T12:00:00Z", Some("success")),
+            run(4, "other", "2025-01-13T12:00:00Z", Some("success")),
+        ];
+        let ids = |runs: Vec<WorkflowRun>| runs.iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids(select_graded_runs(runs.clone(), RunSelection::Latest, "main", 2)), [3, 2]);
+        let deadline = "2025-01-10T00:00:00Z".parse().unwrap();
+        assert_eq!(ids(select_graded_runs(runs, RunSelection::FirstAfter(deadline), "main", 5)), [1, 2, 3]);
+
+        // Runs of a flaky test_b worth 5 points: latest 5 + 0, then 5 + 5, then 5 + 0
+        let graded = |points_b: [u32; 3]| {
+            points_b
+                .iter()
+                .enumerate()
+                .map(|(i, &b)| {
+                    let tests: IndexMap<String, TestResult> = [("test_a", 5), ("test_b", b)]
+                        .into_iter()
+                        .map(|(name, points)| {
+                            (
+                                name.to_string(),
+                                TestResult {
+                                    _name: name.to_string(),
+                                    points_awarded: points,
+                                    points_available: 5,
+                                    status: if points == 5 { TestStatus::Passed } else { TestStatus::Failed },
+                                    failure_message: None,
+                                },
+                            )
+                        })
+                        .collect();
+                    StudentResult {
+                        run_url: format!("run-{}", i),
+                        total_awarded: tests.values().map(|t| t.points_awarded).sum(),
+                        tests,
+                        run_count: 3,
+                        ..StudentResult::sample("alice")
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let best = RunPolicy::Max.combine(graded([0, 5, 0]));
+        assert_eq!((best.total_awarded, best.run_url.as_str()), (10, "run-1"));
+        let combined = best.combined_runs.unwrap();
+        assert_eq!(combined.runs_considered, 3);
+        assert!((combined.score_variance - 50.0 / 9.0).abs() < 1e-9);
+
+        // 5/3 points for test_b rounds to 2; the latest run's details are kept
+        let mean = RunPolicy::Mean.combine(graded([0, 5, 0]));
+        assert_eq!(mean.tests["test_b"].points_awarded, 2);
+        assert_eq!((mean.total_awarded, mean.run_url.as_str()), (7, "run-0"));
+
+        // A tie keeps the run that would have been graded alone
+        let tie = RunPolicy::Max.combine(graded([5, 5, 0]));
+        assert_eq!(tie.run_url, "run-0");
+    }
+
+    #[test]
+    fn test_select_graded_run_tie_break() {
+        // Same created_at, listed in either order: the higher id is the later run
+        let runs = vec![
+            run(8, "main", "2025-01-10T12:00:00Z", Some("failure")),
+            run(7, "main", "2025-01-10T12:00:00Z", Some("success")),
+        ];
+        let mut reversed = runs.clone();
+        reversed.reverse();
+
+        for runs in [runs, reversed] {
+            assert_eq!(select_graded_run(runs.clone(), RunSelection::Latest, "main").unwrap().id, 8);
+            let deadline = "2025-01-10T00:00:00Z".parse().unwrap();
+            assert_eq!(select_graded_run(runs, RunSelection::FirstAfter(deadline), "main").unwrap().id, 7);
+        }
+    }
+
+    #[test]
+    fn test_latest_before_deadline() {
+        let deadline = "2025-01-12T00:00:00Z".parse().unwrap();
+        let selection = RunSelection::LatestBefore(deadline);
+        assert_eq!(selection.created_filter().as_deref(), Some("<=2025-01-12T00:00:00Z"));
+        assert_eq!(
+            RunSelection::FirstAfter(deadline).created_filter().as_deref(),
+            Some(">=2025-01-12T00:00:00Z")
         );
+        assert_eq!(RunSelection::Latest.created_filter(), None);
 
-        results.push(late_grading_result);
+        // The API only returns runs created before the deadline; the newest one wins
+        let runs = vec![
+            run(1, "main", "2025-01-10T12:00:00Z", Some("success")),
+            run(2, "main", "2025-01-11T12:00:00Z", Some("failure")),
+            run(3, "main", "2025-01-11T18:00:00Z", None),
+        ];
+        assert_eq!(select_graded_run(runs, selection, "main").unwrap().id, 2);
     }
 
-    Ok(results)
+    fn accepted(id: u64, logins: &[&str]) -> AcceptedAssignment {
+        let students: Vec<_> = logins
+            .iter()
+            .map(|login| {
+                serde_json::json!({
+                    "id": 1,
+                    "login": login,
+                    "name": null,
+                    "avatar_url": "",
+                    "html_url": null,
+                })
+            })
+            .collect();
+
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "grade": null,
+            "students": students,
+            "repository": {
+                "id": id,
+                "full_name": format!("org/repo-{}", id),
+                "html_url": format!("https://github.com/org/repo-{}", id),
+                "default_branch": "main",
+            },
+            "assignment": { "id": 1, "title": "Assignment" },
+        }))
+        .unwrap()
+    }
+
+    /// An autograding workflow with one 5-point test, `test_1`
+    const ONE_TEST_WORKFLOW: &str = r#"
+jobs:
+  run-autograding-tests:
+    steps:
+      - name: "test_1"
+        id: "test-1"
+        uses: "classroom-resources/autograding-command-grader@v1"
+        with:
+          test-name: "test_1"
+          max-score: 5
+"#;
+
+    /// Request path of `repo`'s workflow file in the contents API
+    fn workflow_path(repo: &str) -> String {
+        format!("/repos/org/{}/contents/{}", repo, WORKFLOW_PATH)
+    }
+
+    /// A contents API response with `workflow` as the workflow file
+    fn workflow_contents(workflow: &[u8]) -> wiremock::ResponseTemplate {
+        let content = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, workflow);
+        wiremock::ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "name": "classroom.yml",
+            "path": WORKFLOW_PATH,
+            "sha": "abc123",
+            "size": workflow.len(),
+            "content": content,
+            "encoding": "base64",
+        }))
+    }
+
+    /// An assignment with the given starter repository URL, if any
+    fn assignment(id: u64, starter_code_url: Option<&str>) -> Assignment {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": format!("HW {}", id),
+            "slug": format!("hw-{}", id),
+            "deadline": null,
+            "starter_code_url": starter_code_url,
+            "classroom": { "id": 1, "name": "Class" },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_without_passing() {
+        let mut passing = accepted(2, &["bob"]);
+        passing.passing = true;
+        let roster = vec![accepted(1, &["alice"]), passing, accepted(3, &["carol"])];
+
+        let (remaining, skipped) = without_passing(roster);
+        let ids: Vec<u64> = remaining.iter().map(|a| a.id).collect();
+        assert_eq!(ids, [1, 3]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn test_without_excluded() {
+        let roster = vec![accepted(1, &["alice"]), accepted(2, &["ProfSmith"]), accepted(3, &["bob", "ta-jo"])];
+        let exclude = ["profsmith".to_string(), "ta-jo".to_string()];
+
+        let (remaining, excluded) = without_excluded(roster, &exclude);
+        let ids: Vec<u64> = remaining.iter().map(|a| a.id).collect();
+        assert_eq!(ids, [1]);
+        assert_eq!(excluded, 2);
+    }
+
+    #[test]
+    fn test_deleted_accounts_use_repo_names() {
+        let mut roster = vec![accepted(1, &["alice"]), accepted(2, &[]), accepted(3, &[]), accepted(4, &[])];
+        roster[3].repository.full_name = "repo-4".to_string();
+        let usernames: Vec<String> = roster.iter().map(first_login).collect();
+        assert_eq!(usernames, ["alice", "repo-2", "repo-3", "4"]);
+        assert_eq!(deleted_accounts(&roster), ["repo-2", "repo-3", "4"]);
+    }
+
+    #[tokio::test]
+    async fn test_shared_starter_workflow_fetched_once() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(workflow_path("starter")))
+            .respond_with(workflow_contents(ONE_TEST_WORKFLOW.as_bytes()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = GitHubClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let cache = TestDefinitionCache::default();
+        for id in [1, 2] {
+            let assignment = assignment(id, Some("https://github.com/org/starter"));
+            let tests = load_test_definitions(&client, &assignment, &[], &cache, |_| {}).await.unwrap();
+            assert_eq!(tests[0].name, "test_1");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_workflow_falls_back_to_next_student() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer};
+
+        let server = MockServer::start().await;
+        // repo-1 deleted its workflow, repo-3 is never needed
+        Mock::given(method("GET"))
+            .and(path(workflow_path("repo-2")))
+            .respond_with(workflow_contents(ONE_TEST_WORKFLOW.as_bytes()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let assignment = assignment(1, None);
+
+        let client = GitHubClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let cache = TestDefinitionCache::default();
+        let students = [accepted(1, &["alice"]), accepted(2, &["bob"]), accepted(3, &["carol"])];
+        let tests = load_test_definitions(&client, &assignment, &students, &cache, |_| {}).await.unwrap();
+        assert_eq!(tests[0].name, "test_1");
+
+        // With no usable workflow anywhere, every attempt is reported
+        let err = load_test_definitions(&client, &assignment, &students[..1], &cache, |_| {})
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("org/repo-1: Not found"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_check_repo_access() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        // repo-1 was deleted, repo-2 is readable; nothing else is
+        Mock::given(method("GET"))
+            .and(path("/repos/org/repo-2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 2,
+                "full_name": "org/repo-2",
+                "html_url": "https://github.com/org/repo-2",
+                "default_branch": "main",
+                "pushed_at": "2025-03-01T12:00:00Z",
+            })))
+            .mount(&server)
+            .await;
+
+        let client = GitHubClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let students = [accepted(1, &["alice"]), accepted(2, &["bob"])];
+        check_repo_access(&client, &students).await.unwrap();
+
+        let students = [accepted(1, &["alice"]), accepted(3, &["carol"]), accepted(4, &["dan"]), accepted(2, &["bob"])];
+        let err = check_repo_access(&client, &students).await.unwrap_err();
+        assert!(
+            matches!(&err, FetchError::NoRepoAccess { org, repos } if org == "org" && repos == "repo-1, repo-3, repo-4"),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_workflow_with_invalid_utf8() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer};
+
+        let server = MockServer::start().await;
+        // A comment saved as Latin-1
+        let mut workflow = b"# Caf\xe9 autograder\n".to_vec();
+        workflow.extend_from_slice(ONE_TEST_WORKFLOW.as_bytes());
+        Mock::given(method("GET"))
+            .and(path(workflow_path("starter")))
+            .respond_with(workflow_contents(&workflow))
+            .mount(&server)
+            .await;
+
+        let assignment = assignment(1, Some("https://github.com/org/starter"));
+
+        let client = GitHubClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let reported = Mutex::new(Vec::new());
+        let tests = load_test_definitions(&client, &assignment, &[], &TestDefinitionCache::default(), |workflow| {
+            reported.lock().unwrap().push(workflow.to_string())
+        })
+        .await
+        .unwrap();
+        assert_eq!(tests[0].name, "test_1");
+        assert_eq!(reported.into_inner().unwrap(), ["org/starter/.github/workflows/classroom.yml"]);
+    }
+
+    #[test]
+    fn test_scores_from_check_runs() {
+        let check_run = |id: u64, summary: Option<&str>, text: Option<&str>| CheckRun {
+            id,
+            name: "run-autograding-tests".to_string(),
+            head_sha: "abc123".to_string(),
+            status: "completed".to_string(),
+            conclusion: Some("success".to_string()),
+            output: CheckRunOutput {
+                title: None,
+                summary: summary.map(str::to_string),
+                text: text.map(str::to_string),
+                annotations_count: 0,
+            },
+        };
+
+        let check_runs = [
+            check_run(1, Some("Total points for other-test: 1/1"), None),
+            check_run(42, Some("Autograding complete"), Some("Total points for test-add: 2.0/2\nTotal points for test-sub: 0/3")),
+        ];
+        let scores = scores_from_check_runs(&check_runs, 42);
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores["test-add"], 2);
+        assert_eq!(scores["test-sub"], 0);
+
+        // Without a parseable job check run, any other check run with scores is used
+        assert_eq!(scores_from_check_runs(&check_runs, 7)["other-test"], 1);
+        assert!(scores_from_check_runs(&check_runs[..0], 42).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_matrix_jobs_are_merged() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let json = |body| ResponseTemplate::new(200).set_body_json(body);
+        Mock::given(method("GET"))
+            .and(path("/repos/org/repo-1/actions/runs"))
+            .respond_with(json(serde_json::json!({
+                "total_count": 1,
+                "workflow_runs": [run(100, "main", "2025-03-01T12:00:00Z", Some("success"))],
+            })))
+            .mount(&server)
+            .await;
+
+        // Each matrix entry runs one test and skips the other
+        let step = |number: u32, name: &str, conclusion: &str| {
+            serde_json::json!({
+                "name": name,
+                "status": "completed",
+                "conclusion": conclusion,
+                "number": number,
+                "started_at": null,
+                "completed_at": null,
+            })
+        };
+        let job = |id: u64, name: &str, steps: Vec<serde_json::Value>| {
+            serde_json::json!({
+                "id": id,
+                "name": name,
+                "status": "completed",
+                "conclusion": "success",
+                "started_at": null,
+                "completed_at": null,
+                "steps": steps,
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/repos/org/repo-1/actions/runs/100/jobs"))
+            .respond_with(json(serde_json::json!({
+                "total_count": 2,
+                "jobs": [
+                    job(11, "run-autograding-tests (1)", vec![step(2, "test_1", "success"), step(3, "test_2", "skipped")]),
+                    job(12, "run-autograding-tests (2)", vec![step(2, "test_1", "skipped"), step(3, "test_2", "failure")]),
+                ],
+            })))
+            .mount(&server)
+            .await;
+        // Downloaded by the first fetch and for reading, not by the fetch skipping logs
+        for (id, logs) in [(11, "Total points for test-1: 5/5"), (12, "Total points for test-2: 0/5")] {
+            Mock::given(method("GET"))
+                .and(path(format!("/repos/org/repo-1/actions/jobs/{}/logs", id)))
+                .respond_with(ResponseTemplate::new(200).set_body_string(logs))
+                .expect(2)
+                .mount(&server)
+                .await;
+        }
+
+        let definitions = vec![
+            definition("test_1"),
+            definition("test_2"),
+        ];
+        let client = GitHubClient::new("test-token".to_string(), server.uri(), "test-agent");
+        let result = fetch_student_results(&client, &accepted(1, &["alice"]), RunSelection::Latest, &definitions, &FetchOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.tests["test_1"].status, TestStatus::Passed);
+        assert_eq!(result.tests["test_2"].status, TestStatus::Failed);
+        assert_eq!((result.total_awarded, result.total_available), (5, 10));
+        assert_eq!(result.points_source, PointsSource::Logs);
+        assert_eq!(
+            fetch_run_logs(&client, &result).await.unwrap(),
+            "── run-autograding-tests (1) ──\nTotal points for test-1: 5/5\n\
+             ── run-autograding-tests (2) ──\nTotal points for test-2: 0/5\n"
+        );
+
+        let result = fetch_student_results(&client, &accepted(1, &["alice"]), RunSelection::Latest, &definitions, &FetchOptions { skip_logs: true, ..FetchOptions::default() })
+            .await
+            .unwrap();
+        assert_eq!(result.tests["test_1"].points_awarded, 5);
+        assert_eq!((result.total_awarded, result.total_available), (5, 10));
+        assert_eq!(result.points_source, PointsSource::Steps);
+
+        assert!(is_autograding_job("run-autograding-tests"));
+        assert!(is_autograding_job("run-autograding-tests (2, ubuntu-latest)"));
+        assert!(!is_autograding_job("run-autograding-tests-extra"));
+    }
+
+    #[test]
+    fn test_logs_problem_of() {
+        let api_error = |status| FetchError::Api {
+            url: String::new(),
+            status,
+            body: String::new(),
+        };
+        assert_eq!(logs_problem_of(&api_error(reqwest::StatusCode::GONE)), LogsProblem::Expired);
+        assert_eq!(logs_problem_of(&api_error(reqwest::StatusCode::FORBIDDEN)), LogsProblem::Forbidden);
+        assert_eq!(logs_problem_of(&FetchError::RateLimited), LogsProblem::Unavailable);
+        assert_eq!(
+            PointsSource::CheckRuns(LogsProblem::Expired).to_string(),
+            "check_runs (logs expired)"
+        );
+        assert_eq!("steps".parse::<PointsSource>(), Ok(PointsSource::Steps));
+    }
+
+    #[test]
+    fn test_attach_failure_messages() {
+        let definitions = vec![
+            definition("test_add"),
+            definition("test_sub"),
+            definition("test_mul"),
+            definition("test_1"),
+        ];
+        let mut tests: IndexMap<String, TestResult> = definitions
+            .iter()
+            .map(|d| {
+                let status = if d.name == "test_mul" { TestStatus::Passed } else { TestStatus::Failed };
+                (
+                    d.name.clone(),
+                    TestResult {
+                        _name: d.name.clone(),
+                        points_awarded: 0,
+                        points_available: d.max_score,
+                        status,
+                        failure_message: None,
+                    },
+                )
+            })
+            .collect();
+        let annotation = |title: Option<&str>, message: &str| CheckRunAnnotation {
+            path: ".github".to_string(),
+            start_line: 1,
+            annotation_level: Some("failure".to_string()),
+            title: title.map(str::to_string),
+            message: message.to_string(),
+        };
+        let annotations = [
+            annotation(None, "Process completed with exit code 1."),
+            annotation(Some("test-add"), "  assert add(2, 2) == 4 failed\n"),
+            annotation(None, "test_mul: slow"),
+            annotation(None, "test_10: expected 3"),
+        ];
+
+        attach_failure_messages(&mut tests, &definitions, &annotations);
+        assert_eq!(tests["test_add"].failure_message.as_deref(), Some("assert add(2, 2) == 4 failed"));
+        assert_eq!(tests["test_sub"].failure_message, None);
+        // Only failed tests get a message
+        assert_eq!(tests["test_mul"].failure_message, None);
+        // A test isn't matched by a longer name starting with its own
+        assert_eq!(tests["test_1"].failure_message, None);
+    }
+
+    #[test]
+    fn test_first_students() {
+        let roster = || vec![accepted(1, &["alice"]), accepted(2, &["bob"]), accepted(3, &["carol"])];
+
+        let (capped, over_cap) = first_students(roster(), Some(2));
+        let ids: Vec<u64> = capped.iter().map(|a| a.id).collect();
+        assert_eq!(ids, [1, 2]);
+        assert_eq!(over_cap, 1);
+
+        assert_eq!(first_students(roster(), Some(10)).1, 0);
+        assert_eq!(first_students(roster(), None).0.len(), 3);
+    }
+
+    #[test]
+    fn test_points_mismatch() {
+        let graded = |id, grade: Option<&str>| AcceptedAssignment {
+            grade: grade.map(String::from),
+            ..accepted(id, &["student"])
+        };
+        let definitions = vec![definition("test_a"), definition("test_b")];
+
+        // Ungraded students don't count, and the most common total wins
+        let roster = vec![
+            graded(1, Some("8/10")),
+            graded(2, Some("3/15")),
+            graded(3, Some("10/10")),
+            graded(4, None),
+        ];
+        assert_eq!(roster[0].grade_available(), Some(10));
+        assert_eq!(points_mismatch(&definitions, &roster), None);
+
+        let roster = vec![graded(1, Some("12/15")), graded(2, Some("15/15"))];
+        assert_eq!(points_mismatch(&definitions, &roster), Some((10, 15)));
+
+        assert_eq!(points_mismatch(&definitions, &[graded(1, None)]), None);
+    }
+
+    #[test]
+    fn test_select_student() {
+        let roster = vec![accepted(1, &["alice"]), accepted(2, &["bob", "carol"])];
+
+        let selected = select_student(roster.clone(), "Carol").unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id, 2);
+
+        let err = select_student(roster, "dave").unwrap_err();
+        assert!(err.to_string().contains("'dave'"));
+    }
 }
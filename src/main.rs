@@ -1,26 +1,169 @@
 mod api;
+mod cli;
 mod config;
+mod deadlines;
+mod error;
 mod export;
 mod fetcher;
 mod models;
 mod parser;
+mod results_cache;
+mod roster;
 mod ui;
 
 use anyhow::{Context, Result};
+use clap::Parser;
+use cli::{Cli, Command};
 use config::Config;
+use fetcher::MultiRun;
 use ui::App;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Load configuration
-    let config = Config::load().context("Failed to load configuration")?;
+    let cli = Cli::parse();
 
-    // Initialize API clients
-    let classroom_client = api::ClassroomClient::new(config.github_token.clone());
-    let github_client = api::GitHubClient::new(config.github_token);
+    // Subcommands work on local files and don't need a token
+    if let Some(Command::ValidateWorkflow { path }) = &cli.command {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let tests = parser::parse_workflow(&content)
+            .with_context(|| format!("{} is not a valid autograder workflow", path.display()))?;
+        for test in &tests {
+            println!("{} (step id: {}, max score: {})", test.name, test.id, test.max_score);
+        }
+        println!(
+            "{} tests, {} points total",
+            tests.len(),
+            tests.iter().map(|t| t.max_score).sum::<u32>()
+        );
+        return Ok(());
+    }
+
+    // Load configuration, with command-line flags taking precedence
+    let mut config = Config::load(cli.config.as_deref(), cli.profile.as_deref(), cli.use_gh_cli).context("Failed to load configuration")?;
+    if let Some(formats) = cli.format {
+        config.export.formats = formats;
+    }
+    config.export.anonymize = cli.anonymize;
+    config.export.keep_mapping = cli.keep_mapping;
+    config.export.stream = cli.stream;
+    config.export.run_details = cli.run_details;
+    config.fetch.failure_messages = cli.run_details;
+    config.export.needs_review = cli.needs_review;
+    config.export.sort_by = cli.sort_by;
+    if let Some(decimal_separator) = cli.decimal_separator {
+        config.export.decimal_separator = decimal_separator;
+    }
+    if cli.tag.is_some() {
+        config.export.tag = cli.tag;
+    }
+    if cli.columns.is_some() {
+        let mapping = config.export.column_mapping.get_or_insert_default();
+        mapping.columns = cli.columns;
+        mapping.validate().context("Invalid --columns")?;
+    }
+    if cli.overwrite {
+        config.export.existing_files = export::ExistingFiles::Overwrite;
+    }
+    config.export.scale = cli.scale_to.map(|to| export::ScoreScale {
+        to,
+        rounding: cli.scale_rounding,
+    });
+    if cli.tests.is_some() {
+        config.fetch.test_filter = cli.tests;
+    }
+    config.fetch.interrupted_runs = cli.interrupted_runs;
+    if let Some(path) = cli.save_roster {
+        config.roster = roster::RosterSource::Save(path);
+    } else if let Some(path) = cli.load_roster {
+        config.roster = roster::RosterSource::Load(path);
+    }
+    if let Some(values) = &cli.min_points {
+        config.fetch.points_floor = Some(config::parse_points_floor(values)?);
+    }
+    if let Some(path) = &cli.weights {
+        config.fetch.weights = Some(config::load_weights(path)?);
+    }
+    config.baseline = cli.baseline;
+    config.max_drift = cli.max_drift;
+    if cli.student.is_some() {
+        config.fetch.student = cli.student;
+    }
+    config.fetch.exclude.extend(cli.exclude);
+    if cli.org.is_some() {
+        config.org = cli.org;
+    }
+    if cli.client_id.is_some() {
+        config.oauth_client_id = cli.client_id;
+    }
+    if cli.branch.is_some() {
+        config.fetch.branch = cli.branch;
+    }
+    if let Some(retries) = cli.wait_for_runs {
+        config.fetch.wait_for_runs = retries;
+    }
+    config.fetch.multi_run = cli.runs.map(|runs| MultiRun {
+        runs: runs as usize,
+        policy: cli.run_policy,
+    });
+    config.fetch.skip_passing = cli.skip_passing;
+    config.fetch.check_workflow = cli.check_workflow;
+    config.fetch.skip_logs = cli.no_logs;
+    config.changed_only = cli.changed_only;
+    config.fetch.max_students = cli.max_students.map(|n| n as usize);
+
+    // Diffs are written like the other exports, so they use the export settings
+    if let Some(Command::Diff { old, new }) = cli.command {
+        let (filepath, summary) = export::diff_results(&old, &new, &config.export)?;
+        println!("Diff written to {}", filepath.display());
+        println!(
+            "Improved: {} | Regressed: {} | Unchanged: {} | Only in old: {} | Only in new: {}",
+            summary.improved,
+            summary.regressed,
+            summary.unchanged,
+            summary.only_in_old,
+            summary.only_in_new
+        );
+        return Ok(());
+    }
+
+    config.all_assignments = cli.all_assignments;
+    config.login = cli.login;
+    config.resolve_login()?;
+    let needs_login = config.login;
+
+    // Initialize API clients, sharing one connection pool
+    let http_client = api::api_http_client(config.proxy.clone(), config.fetch.concurrency);
+    let classroom_client = api::ClassroomClient::new(
+        config.github_token.clone(),
+        config.api_base.clone(),
+        &config.user_agent,
+    )
+    .with_http_client(http_client.clone());
+    let github_client = api::GitHubClient::new(
+        config.github_token.clone(),
+        config.api_base.clone(),
+        &config.user_agent,
+    )
+    .with_http_client(http_client);
+
+    // The browser login needs the terminal UI
+    if needs_login && (cli.assignment_id.is_some() || cli.assignment_slug.is_some()) {
+        anyhow::bail!("--assignment-id and --assignment-slug need a token: set GITHUB_TOKEN or log in once with --login");
+    }
+    let assignment_id = match (&cli.classroom_name, &cli.assignment_slug) {
+        (Some(classroom_name), Some(assignment_slug)) => {
+            Some(classroom_client.find_assignment(classroom_name, assignment_slug).await?)
+        }
+        _ => cli.assignment_id,
+    };
+
+    let mut app = App::new(classroom_client, github_client, config);
+    if let Some(assignment_id) = assignment_id {
+        return app.run_headless(assignment_id, cli.quiet, cli.summary_json).await;
+    }
 
     // Start TUI application
-    let mut app = App::new(classroom_client, github_client);
     app.run().await?;
 
     Ok(())
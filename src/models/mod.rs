@@ -13,6 +13,22 @@ pub struct Classroom {
     pub name: String,
     pub archived: bool,
     pub url: String,
+    /// Only returned when fetching a single classroom, not by the classroom list
+    #[serde(default)]
+    pub organization: Option<Organization>,
+}
+
+/// The GitHub organization a classroom belongs to
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Organization {
+    pub login: String,
+}
+
+impl Classroom {
+    /// Classroom web UI page of one of this classroom's assignments
+    pub fn assignment_url(&self, assignment_slug: &str) -> String {
+        format!("{}/assignments/{}", self.url.trim_end_matches('/'), assignment_slug)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -28,9 +44,28 @@ pub struct Assignment {
     pub passing: u32,
     pub deadline: Option<DateTime<Utc>>,
     pub starter_code_url: Option<String>,
+    /// The starter repository as an object, which the API returns instead of
+    /// (or as well as) `starter_code_url`
+    #[serde(default, alias = "template_repository")]
+    pub starter_code_repository: Option<StarterRepository>,
     pub classroom: SimpleClassroom,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StarterRepository {
+    pub full_name: String,
+}
+
+impl Assignment {
+    /// The starter repository as `owner/repo` or a URL, if the assignment has one
+    pub fn starter_repo(&self) -> Option<&str> {
+        self.starter_code_repository
+            .as_ref()
+            .map(|r| r.full_name.as_str())
+            .or(self.starter_code_url.as_deref())
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SimpleClassroom {
     pub id: u64,
@@ -52,6 +87,15 @@ pub struct AcceptedAssignment {
     pub assignment: AssignmentInfo,
 }
 
+impl AcceptedAssignment {
+    /// Points GitHub Classroom grades out of, from a grade like "8/10"
+    pub fn grade_available(&self) -> Option<u32> {
+        let (_, available) = self.grade.as_deref()?.split_once('/')?;
+        let available: f64 = available.trim().parse().ok()?;
+        Some(available.round() as u32)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Student {
     pub id: u64,
@@ -67,6 +111,8 @@ pub struct Repository {
     pub full_name: String,
     pub html_url: String,
     pub default_branch: String,
+    /// Last push to any branch; the Classroom API may leave it out, the repository API doesn't
+    pub pushed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -140,8 +186,91 @@ pub struct FileContent {
     pub encoding: String,
 }
 
+// ============================================================================
+// GitHub Rate Limit Models
+// ============================================================================
+
+/// Latest rate limit figures reported by the `x-ratelimit-*` response headers
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitInfo {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: DateTime<Utc>,
+}
+
+/// GitHub API state shown in the fetching screen's status bar
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiStatus {
+    pub rate_limit: Option<RateLimitInfo>,
+    /// Set while backing off from GitHub's abuse detection (secondary rate limit)
+    pub backoff_until: Option<DateTime<Utc>>,
+}
+
+// ============================================================================
+// GitHub OAuth Device Flow Models
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Seconds until the device and user codes expire
+    pub expires_in: u64,
+    /// Minimum number of seconds between token polls
+    pub interval: u64,
+}
+
+/// Token endpoint response; either a token or an `error` code while the user hasn't finished
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccessTokenResponse {
+    pub access_token: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+    /// New polling interval, sent along with a `slow_down` error
+    pub interval: Option<u64>,
+}
+
 // ============================================================================
 // GitHub Checks API Models
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CheckRunsResponse {
+    pub total_count: u32,
+    pub check_runs: Vec<CheckRun>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CheckRun {
+    pub id: u64,
+    pub name: String,
+    pub head_sha: String,
+    pub status: String,
+    pub conclusion: Option<String>,
+    pub output: CheckRunOutput,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CheckRunOutput {
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub text: Option<String>,
+    #[serde(default)]
+    pub annotations_count: u32,
+}
+
+/// A message attached to a check run, such as an assertion failure from a
+/// test step. In Actions, a job's check run id is the job id.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CheckRunAnnotation {
+    pub path: String,
+    pub start_line: u32,
+    pub annotation_level: Option<String>,
+    pub title: Option<String>,
+    pub message: String,
+}
+
 // ============================================================================
 // Workflow YAML Models
 // ============================================================================
@@ -153,9 +282,16 @@ pub struct WorkflowFile {
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct WorkflowJob {
+    pub strategy: Option<WorkflowStrategy>,
     pub steps: Vec<WorkflowStep>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorkflowStrategy {
+    /// Usually a mapping of variable → list of values, but can be an expression
+    pub matrix: Option<serde_yaml::Value>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct WorkflowStep {
     pub name: String,
@@ -184,15 +320,301 @@ pub struct TestDefinition {
     pub max_score: u32,
 }
 
-#[derive(Debug, Clone)]
+/// A student with no completed grading run, e.g. to send a reminder
+#[derive(Debug, Clone, Serialize)]
+pub struct NonSubmitter {
+    pub username: String,
+    pub repo_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StudentResult {
     pub username: String,
     pub display_name: Option<String>,
     pub repo_url: String,
     pub workflow_run_timestamp: DateTime<Utc>,
+    /// Commit the graded run ran on
+    pub commit_sha: String,
+    /// Page of the graded workflow run
+    pub run_url: String,
+    /// How the graded run ended, e.g. `success`, `failure`, or `cancelled`
+    pub run_conclusion: String,
     pub tests: IndexMap<String, TestResult>,
     pub total_awarded: u32,
     pub total_available: u32,
+    /// Percentage with each test counted by its rubric weight, if weights were given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weighted_percentage: Option<f64>,
+    /// Whether the points are exact (read from the reporter's output) or approximate
+    pub points_source: PointsSource,
+    /// Step ids the reporter logged scores for in the graded run, including
+    /// any the loaded workflow doesn't define
+    #[serde(skip)]
+    pub reported_test_ids: Vec<String>,
+    /// Completed runs on the graded branch (and side of the deadline) that were
+    /// listed while picking the graded one; the first page's if there were more
+    #[serde(skip)]
+    pub run_count: usize,
+    /// Set when several runs were graded and combined into this result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub combined_runs: Option<CombinedRuns>,
+    /// The student's GitHub account was deleted, so `username` is the
+    /// repository name and the row has to be attributed by hand
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub account_deleted: bool,
+}
+
+/// How many runs a combined result came from and how much their totals varied
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CombinedRuns {
+    pub runs_considered: usize,
+    /// Population variance of the runs' total points
+    pub score_variance: f64,
+}
+
+/// Why a student's job log couldn't be used for their points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogsProblem {
+    /// GitHub deleted the log (410 Gone) after its retention period
+    Expired,
+    /// The token isn't allowed to read Actions logs (403)
+    Forbidden,
+    /// Downloading the log failed some other way
+    Unavailable,
+    /// The log has no `Total points for ...` lines
+    NoScores,
+}
+
+impl LogsProblem {
+    const ALL: [LogsProblem; 4] = [
+        LogsProblem::Expired,
+        LogsProblem::Forbidden,
+        LogsProblem::Unavailable,
+        LogsProblem::NoScores,
+    ];
+}
+
+impl std::fmt::Display for LogsProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LogsProblem::Expired => "logs expired",
+            LogsProblem::Forbidden => "no permission to read logs",
+            LogsProblem::Unavailable => "logs unavailable",
+            LogsProblem::NoScores => "no scores in logs",
+        })
+    }
+}
+
+/// Where a student's per-test points were read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointsSource {
+    /// The reporter's `Total points for ...` lines in the job log
+    Logs,
+    /// The same lines in the check run output, because of a problem with the log
+    CheckRuns(LogsProblem),
+    /// No score lines anywhere, so tests without one score 0 and only their
+    /// pass/fail status comes from the job steps
+    Approximate(LogsProblem),
+    /// Logs weren't read (`--no-logs`), so a test whose step passed gets all
+    /// its points and any other test none
+    Steps,
+}
+
+impl PointsSource {
+    pub fn logs_problem(&self) -> Option<LogsProblem> {
+        match self {
+            PointsSource::Logs | PointsSource::Steps => None,
+            PointsSource::CheckRuns(problem) | PointsSource::Approximate(problem) => Some(*problem),
+        }
+    }
+}
+
+/// Written as `logs`, `check_runs (logs expired)`, `approximate (logs expired)`, ...
+impl std::fmt::Display for PointsSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PointsSource::Logs => f.write_str("logs"),
+            PointsSource::CheckRuns(problem) => write!(f, "check_runs ({})", problem),
+            PointsSource::Approximate(problem) => write!(f, "approximate ({})", problem),
+            PointsSource::Steps => f.write_str("steps"),
+        }
+    }
+}
+
+impl Serialize for PointsSource {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Parses what `Display` writes
+impl std::str::FromStr for PointsSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "logs" => return Ok(PointsSource::Logs),
+            "steps" => return Ok(PointsSource::Steps),
+            _ => {}
+        }
+        let problem = |rest: &str| {
+            LogsProblem::ALL
+                .into_iter()
+                .find(|problem| rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')) == Some(&problem.to_string()))
+                .ok_or_else(|| format!("Unknown points source '{}'", s))
+        };
+        if let Some(rest) = s.strip_prefix("check_runs ") {
+            Ok(PointsSource::CheckRuns(problem(rest)?))
+        } else if let Some(rest) = s.strip_prefix("approximate ") {
+            Ok(PointsSource::Approximate(problem(rest)?))
+        } else {
+            Err(format!("Unknown points source '{}'", s))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PointsSource {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+impl StudentResult {
+    /// A successful run with no tests and 10 points available, for tests to
+    /// adjust with struct update syntax
+    pub fn sample(username: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            display_name: None,
+            repo_url: String::new(),
+            workflow_run_timestamp: Utc::now(),
+            commit_sha: String::new(),
+            run_url: String::new(),
+            run_conclusion: "success".to_string(),
+            tests: IndexMap::new(),
+            total_awarded: 0,
+            total_available: 10,
+            weighted_percentage: None,
+            points_source: PointsSource::Logs,
+            reported_test_ids: Vec::new(),
+            run_count: 1,
+            combined_runs: None,
+            account_deleted: false,
+        }
+    }
+}
+
+impl StudentResult {
+    /// Share of the available points awarded, or `None` when no points were
+    /// available (every test has `max_score: 0`, or no test reached the student)
+    pub fn percentage(&self) -> Option<f64> {
+        (self.total_available > 0).then(|| self.total_awarded as f64 / self.total_available as f64 * 100.0)
+    }
+
+    /// Whether the graded run ended without succeeding or failing (cancelled,
+    /// timed out, ...), so some tests may not have run at all
+    pub fn run_interrupted(&self) -> bool {
+        !matches!(self.run_conclusion.as_str(), "success" | "failure")
+    }
+
+    /// Why the score looks suspicious enough for a human to check, if it
+    /// does. A zero after at least `min_runs` runs is suspicious too, since
+    /// the student likely broke something in their last push.
+    pub fn review_reason(&self, min_runs: usize) -> Option<String> {
+        let mut reasons = Vec::new();
+        if self.total_awarded > self.total_available {
+            reasons.push(format!(
+                "{} points awarded out of {}",
+                self.total_awarded, self.total_available
+            ));
+        }
+        if !self.tests.is_empty()
+            && self.tests.values().all(|t| t.status == TestStatus::Passed)
+            && self.total_awarded < self.total_available
+        {
+            reasons.push(format!(
+                "all tests passed but only {}/{} points",
+                self.total_awarded, self.total_available
+            ));
+        }
+        if self.total_awarded == 0 && self.run_count >= min_runs {
+            reasons.push(format!("0 points after {} runs", self.run_count));
+        }
+        if self.account_deleted {
+            reasons.push("account deleted; attribute by repository".to_string());
+        }
+        (!reasons.is_empty()).then(|| reasons.join("; "))
+    }
+
+    /// Names of tests whose step was absent from the graded run
+    pub fn missing_tests(&self) -> Vec<&str> {
+        self.tests
+            .iter()
+            .filter(|(_, t)| t.status == TestStatus::Missing)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+/// Rubric weight of each test by name, read from a `--weights` file. Tests
+/// without a weight don't count toward the weighted percentage.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct TestWeights(pub IndexMap<String, f64>);
+
+impl TestWeights {
+    /// Each weighted test's share of its points, averaged by weight, as a percentage
+    pub fn percentage(&self, result: &StudentResult) -> f64 {
+        let mut weighted = 0.0;
+        let mut total_weight = 0.0;
+        for (name, weight) in &self.0 {
+            let Some(test) = result.tests.get(name) else {
+                continue;
+            };
+            if test.points_available > 0 {
+                weighted += weight * test.points_awarded as f64 / test.points_available as f64;
+            }
+            total_weight += weight;
+        }
+
+        if total_weight > 0.0 {
+            weighted / total_weight * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Least points a test that ran is given, from `--min-points`: one floor
+/// for every test, overridden for tests named in `per_test`
+#[derive(Debug, Clone, Default)]
+pub struct PointsFloor {
+    pub default: Option<u32>,
+    pub per_test: IndexMap<String, u32>,
+}
+
+impl PointsFloor {
+    fn floor(&self, test_name: &str) -> Option<u32> {
+        self.per_test.get(test_name).copied().or(self.default)
+    }
+
+    /// Raise every test that ran (passed or failed) to its floor, capped at
+    /// the test's points, and recompute the total. Skipped and missing tests
+    /// weren't attempted, so they keep 0. Statuses are left as they are.
+    pub fn apply(&self, result: &mut StudentResult) {
+        for (name, test) in &mut result.tests {
+            if !matches!(test.status, TestStatus::Passed | TestStatus::Failed) {
+                continue;
+            }
+            if let Some(floor) = self.floor(name) {
+                test.points_awarded = test.points_awarded.max(floor.min(test.points_available));
+            }
+        }
+        result.total_awarded = result.tests.values().map(|t| t.points_awarded).sum();
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -235,22 +657,98 @@ impl LateGradingResult {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TestResult {
+    #[serde(skip)]
     pub _name: String,
     pub points_awarded: u32,
-    pub _points_available: u32,
-    pub _passed: bool,
+    pub points_available: u32,
+    pub status: TestStatus,
+    /// First check run annotation mentioning a failed test, e.g. its assertion message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_message: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+/// Outcome of a single test step in a student's workflow run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    /// The step was not present in the student's run (deleted or renamed)
+    Missing,
+    Skipped,
+}
+
+/// How one test went across all students
+#[derive(Debug, Clone, Serialize)]
+pub struct TestStats {
+    pub test_name: String,
+    pub points_available: u32,
+    /// Students whose results include this test
+    pub students: usize,
+    pub passed: usize,
+    /// Percentage of students who passed, 0-100
+    pub pass_rate: f64,
+    pub average_points: f64,
+}
+
+impl TestStats {
+    /// Stats for every test, in the test order of the first student
+    pub fn calculate(results: &[StudentResult]) -> Vec<Self> {
+        let test_names: Vec<&String> = results
+            .first()
+            .map(|r| r.tests.keys().collect())
+            .unwrap_or_default();
+
+        test_names
+            .into_iter()
+            .map(|name| {
+                let tests: Vec<&TestResult> = results.iter().filter_map(|r| r.tests.get(name)).collect();
+                let students = tests.len();
+                let passed = tests.iter().filter(|t| t.status == TestStatus::Passed).count();
+                let total_points: u32 = tests.iter().map(|t| t.points_awarded).sum();
+
+                let (pass_rate, average_points) = if students > 0 {
+                    (
+                        passed as f64 / students as f64 * 100.0,
+                        total_points as f64 / students as f64,
+                    )
+                } else {
+                    (0.0, 0.0)
+                };
+
+                Self {
+                    test_name: name.clone(),
+                    points_available: tests.first().map(|t| t.points_available).unwrap_or(0),
+                    students,
+                    passed,
+                    pass_rate,
+                    average_points,
+                }
+            })
+            .collect()
+    }
+
+    /// The `count` tests with the lowest pass rates, hardest first
+    pub fn hardest(stats: &[Self], count: usize) -> Vec<Self> {
+        let mut sorted = stats.to_vec();
+        sorted.sort_by(|a, b| a.pass_rate.total_cmp(&b.pass_rate));
+        sorted.truncate(count);
+        sorted
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ResultStats {
-    pub _total_students: usize,
+    pub total_students: usize,
     pub total_tests: usize,
-    pub average_score: f64,
-    pub median_score: f64,
+    /// Average and median percentage of the students with points available;
+    /// `None` when no student had any
+    pub average_score: Option<f64>,
+    pub median_score: Option<f64>,
     pub students_processed: usize,
-    pub _errors: usize,
+    pub errors: usize,
 }
 
 impl ResultStats {
@@ -261,52 +759,32 @@ impl ResultStats {
             .map(|r| r.tests.len())
             .unwrap_or(0);
 
-        let average_score = if total_students > 0 {
-            let sum: f64 = results
-                .iter()
-                .map(|r| {
-                    if r.total_available > 0 {
-                        (r.total_awarded as f64 / r.total_available as f64) * 100.0
-                    } else {
-                        0.0
-                    }
-                })
-                .sum();
-            sum / total_students as f64
-        } else {
-            0.0
-        };
-
-        let mut scores: Vec<f64> = results
-            .iter()
-            .map(|r| {
-                if r.total_available > 0 {
-                    (r.total_awarded as f64 / r.total_available as f64) * 100.0
-                } else {
-                    0.0
-                }
-            })
-            .collect();
+        let mut scores: Vec<f64> = results.iter().filter_map(StudentResult::percentage).collect();
         scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
-        let median_score = if !scores.is_empty() {
+        let average_score = (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64);
+
+        let median_score = (!scores.is_empty()).then(|| {
             let mid = scores.len() / 2;
-            if scores.len() % 2 == 0 {
+            if scores.len().is_multiple_of(2) {
                 (scores[mid - 1] + scores[mid]) / 2.0
             } else {
                 scores[mid]
             }
-        } else {
-            0.0
-        };
+        });
 
         Self {
-            _total_students: total_students,
+            total_students,
             total_tests,
             average_score,
             median_score,
             students_processed: total_students,
-            _errors: 0,
+            errors: 0,
         }
     }
+
+    /// A score as shown to the user, `N/A` when there is none
+    pub fn format_score(score: Option<f64>) -> String {
+        score.map_or_else(|| "N/A".to_string(), |s| format!("{:.2}%", s))
+    }
 }
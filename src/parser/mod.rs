@@ -1,5 +1,78 @@
 use crate::models::{TestDefinition, WorkflowFile};
 use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+
+/// Name of the job that runs the autograding steps. A matrix expands it into
+/// jobs named like `run-autograding-tests (1)`.
+pub const AUTOGRADING_JOB: &str = "run-autograding-tests";
+
+/// One entry of a job's matrix: each variable and its value in this entry
+type MatrixEntry = Vec<(String, String)>;
+
+/// Every combination of a matrix's list-valued variables, e.g.
+/// `part: [1, 2]` gives one entry with `part = 1` and one with `part = 2`.
+/// `include`, `exclude`, and matrices built from expressions aren't
+/// expanded, so a job without a usable matrix has a single empty entry.
+fn matrix_entries(matrix: Option<&serde_yaml::Value>) -> Vec<MatrixEntry> {
+    let mut entries = vec![Vec::new()];
+    let Some(serde_yaml::Value::Mapping(matrix)) = matrix else {
+        return entries;
+    };
+
+    for (key, values) in matrix {
+        let (Some(key), serde_yaml::Value::Sequence(values)) = (key.as_str(), values) else {
+            continue;
+        };
+        if key == "include" || key == "exclude" {
+            continue;
+        }
+        let values: Vec<String> = values.iter().filter_map(scalar_string).collect();
+        entries = entries
+            .into_iter()
+            .flat_map(|entry| {
+                values.iter().map(move |value| {
+                    let mut entry = entry.clone();
+                    entry.push((key.to_string(), value.clone()));
+                    entry
+                })
+            })
+            .collect();
+    }
+
+    entries
+}
+
+fn scalar_string(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Replace `${{ matrix.<name> }}` expressions with the entry's values,
+/// leaving any other expression as it is
+fn expand_matrix(text: &str, entry: &MatrixEntry) -> String {
+    let mut expanded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end + 2) else {
+            break;
+        };
+        let expression = rest[start + 3..end - 2].trim();
+        let value = expression
+            .strip_prefix("matrix.")
+            .and_then(|name| entry.iter().find(|(key, _)| key == name))
+            .map(|(_, value)| value.as_str());
+
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(value.unwrap_or(&rest[start..end]));
+        rest = &rest[end..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
 
 /// Parse workflow YAML content and extract test definitions
 pub fn parse_workflow(yaml_content: &str) -> Result<Vec<TestDefinition>> {
@@ -13,12 +86,23 @@ pub fn parse_workflow(yaml_content: &str) -> Result<Vec<TestDefinition>> {
 fn extract_test_definitions(workflow: &WorkflowFile) -> Result<Vec<TestDefinition>> {
     let job = workflow
         .jobs
-        .get("run-autograding-tests")
-        .context("Job 'run-autograding-tests' not found in workflow")?;
+        .get(AUTOGRADING_JOB)
+        .with_context(|| format!("Job '{}' not found in workflow", AUTOGRADING_JOB))?;
+
+    // A matrix job can split the tests across its entries by naming steps
+    // after matrix variables, e.g. `test_${{ matrix.part }}`. Steps that
+    // don't use the matrix are the same test in every entry.
+    let entries = matrix_entries(job.strategy.as_ref().and_then(|s| s.matrix.as_ref()));
+    let mut expanded = HashSet::new();
 
     let mut tests = Vec::new();
 
-    for step in &job.steps {
+    for ((position, step), entry) in job
+        .steps
+        .iter()
+        .enumerate()
+        .flat_map(|step| entries.iter().map(move |entry| (step, entry)))
+    {
         // Only process steps that use autograding-command-grader
         let uses_autograder = step
             .uses
@@ -30,14 +114,19 @@ fn extract_test_definitions(workflow: &WorkflowFile) -> Result<Vec<TestDefinitio
             continue;
         }
 
-        if let (Some(id), Some(with)) = (&step.id, &step.with) {
-            if let (Some(_test_name), Some(max_score)) = (&with.test_name, &with.max_score) {
-                tests.push(TestDefinition {
-                    name: step.name.clone(),
-                    id: id.clone(),
-                    max_score: *max_score,
-                });
+        if let (Some(id), Some(with)) = (&step.id, &step.with)
+            && let (Some(_test_name), Some(max_score)) = (&with.test_name, &with.max_score)
+        {
+            let name = expand_matrix(&step.name, entry);
+            let id = expand_matrix(id, entry);
+            if !expanded.insert((position, name.clone(), id.clone())) {
+                continue;
             }
+            tests.push(TestDefinition {
+                name,
+                id,
+                max_score: *max_score,
+            });
         }
     }
 
@@ -45,6 +134,22 @@ fn extract_test_definitions(workflow: &WorkflowFile) -> Result<Vec<TestDefinitio
         anyhow::bail!("No autograding tests found in workflow");
     }
 
+    // Test names key the per-test results, so a blank or repeated name would drop a column
+    let mut ids_by_name: HashMap<&str, &str> = HashMap::new();
+    for test in &tests {
+        if test.name.trim().is_empty() {
+            anyhow::bail!("Autograding step '{}' has an empty name", test.id);
+        }
+        if let Some(first_id) = ids_by_name.insert(&test.name, &test.id) {
+            anyhow::bail!(
+                "Autograding steps '{}' and '{}' are both named '{}'; test names must be unique",
+                first_id,
+                test.id,
+                test.name
+            );
+        }
+    }
+
     Ok(tests)
 }
 
@@ -88,4 +193,60 @@ jobs:
         assert_eq!(tests[1].name, "test_2");
         assert_eq!(tests[1].max_score, 10);
     }
+
+    #[test]
+    fn test_parse_matrix_workflow() {
+        let yaml = r#"
+jobs:
+  run-autograding-tests:
+    strategy:
+      matrix:
+        part: [1, 2]
+    steps:
+      - name: "test_${{ matrix.part }}"
+        id: "test-${{matrix.part}}"
+        if: ${{ matrix.part > 0 }}
+        uses: "classroom-resources/autograding-command-grader@v1"
+        with:
+          test-name: "test_${{ matrix.part }}"
+          max-score: 5
+      - name: "style"
+        id: "style"
+        uses: "classroom-resources/autograding-command-grader@v1"
+        with:
+          test-name: "style"
+          max-score: 2
+"#;
+
+        let tests = parse_workflow(yaml).unwrap();
+        let names: Vec<(&str, &str)> = tests.iter().map(|t| (t.name.as_str(), t.id.as_str())).collect();
+        assert_eq!(names, [("test_1", "test-1"), ("test_2", "test-2"), ("style", "style")]);
+
+        // Unknown expressions are left alone
+        assert_eq!(expand_matrix("a ${{ matrix.os }} b", &vec![]), "a ${{ matrix.os }} b");
+    }
+
+    #[test]
+    fn test_parse_workflow_duplicate_name() {
+        let yaml = r#"
+jobs:
+  run-autograding-tests:
+    steps:
+      - name: "test_1"
+        id: "test-1"
+        uses: "classroom-resources/autograding-command-grader@v1"
+        with:
+          test-name: "test_1"
+          max-score: 5
+      - name: "test_1"
+        id: "test-1-again"
+        uses: "classroom-resources/autograding-command-grader@v1"
+        with:
+          test-name: "test_1"
+          max-score: 5
+"#;
+
+        let err = parse_workflow(yaml).unwrap_err().to_string();
+        assert!(err.contains("'test-1' and 'test-1-again'"), "{}", err);
+    }
 }
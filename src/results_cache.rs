@@ -0,0 +1,112 @@
+use crate::fetcher::RunSelection;
+use crate::models::StudentResult;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// File in the working directory with each assignment's last results, so
+/// `--changed-only` can reuse them for students who haven't pushed since
+pub const RESULTS_CACHE_FILE: &str = ".gh_autograder_fetcher_results.json";
+
+/// An assignment's results as of its last `--changed-only` run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResults {
+    /// When the run started
+    pub fetched_at: DateTime<Utc>,
+    /// Results are only reused for the same run selection (and deadline)
+    pub selection: RunSelection,
+    pub results: Vec<StudentResult>,
+}
+
+/// Cached results by assignment id
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ResultsCache(HashMap<u64, CachedResults>);
+
+impl ResultsCache {
+    /// Read the cache, or an empty one if the file doesn't exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cached results {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse cached results {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write cached results {}", path.display()))
+    }
+
+    /// An assignment's cached results, if they were graded with `selection`
+    pub fn get(&self, assignment_id: u64, selection: RunSelection) -> Option<&CachedResults> {
+        self.0.get(&assignment_id).filter(|cached| cached.selection == selection)
+    }
+
+    /// Record a run's results in the file at `path`. Students left out of the
+    /// run (e.g. failed, or not picked with `--student`) keep their older
+    /// result, which is only reused while they haven't pushed since.
+    pub fn update(
+        path: &Path,
+        assignment_id: u64,
+        selection: RunSelection,
+        fetched_at: DateTime<Utc>,
+        results: &[StudentResult],
+    ) -> Result<()> {
+        let mut cache = Self::load(path)?;
+        let mut merged = match cache.0.remove(&assignment_id) {
+            Some(cached) if cached.selection == selection => cached.results,
+            _ => Vec::new(),
+        };
+        merged.retain(|old| !results.iter().any(|new| new.username == old.username));
+        merged.extend(results.iter().cloned());
+        cache.0.insert(assignment_id, CachedResults { fetched_at, selection, results: merged });
+        cache.save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{LogsProblem, PointsSource, TestResult, TestStatus};
+
+    #[test]
+    fn test_update_merges_results() {
+        let result = |username: &str, total_awarded: u32| StudentResult {
+            repo_url: format!("https://github.com/org/hw1-{}", username),
+            commit_sha: "abc123".to_string(),
+            tests: [(
+                "test_1".to_string(),
+                TestResult {
+                    _name: "test_1".to_string(),
+                    points_awarded: total_awarded,
+                    points_available: 10,
+                    status: TestStatus::Passed,
+                    failure_message: None,
+                },
+            )]
+            .into_iter()
+            .collect(),
+            total_awarded,
+            points_source: PointsSource::Approximate(LogsProblem::Expired),
+            ..StudentResult::sample(username)
+        };
+        let path = std::env::temp_dir().join(format!("results_cache_test_{}.json", std::process::id()));
+        let fetched_at = "2025-03-02T09:00:00Z".parse().unwrap();
+
+        ResultsCache::update(&path, 7, RunSelection::Latest, fetched_at, &[result("amy", 4), result("bob", 6)]).unwrap();
+        ResultsCache::update(&path, 7, RunSelection::Latest, fetched_at, &[result("bob", 10)]).unwrap();
+        let cache = ResultsCache::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let cached = cache.get(7, RunSelection::Latest).unwrap();
+        let totals: Vec<_> = cached.results.iter().map(|r| (r.username.as_str(), r.total_awarded)).collect();
+        assert_eq!(totals, [("amy", 4), ("bob", 10)]);
+        assert_eq!(cached.results[0].points_source, PointsSource::Approximate(LogsProblem::Expired));
+        assert!(cache.get(7, RunSelection::LatestBefore(fetched_at)).is_none());
+    }
+}
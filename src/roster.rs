@@ -0,0 +1,139 @@
+use crate::api::ClassroomClient;
+use crate::models::AcceptedAssignment;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Snapshots older than this are still used, with a warning that students may be missing
+pub const STALE_AFTER: Duration = Duration::hours(24);
+
+/// An assignment's accepted assignments as saved by `--save-roster`, so
+/// repeated grading can skip the slow, paginated API call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RosterSnapshot {
+    pub assignment_id: u64,
+    pub saved_at: DateTime<Utc>,
+    pub accepted_assignments: Vec<AcceptedAssignment>,
+}
+
+impl RosterSnapshot {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read roster snapshot {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse roster snapshot {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write roster snapshot {}", path.display()))
+    }
+
+    /// A warning if the snapshot is old enough that students may have accepted since
+    pub fn staleness_warning(&self, now: DateTime<Utc>) -> Option<String> {
+        let age = now - self.saved_at;
+        (age > STALE_AFTER).then(|| {
+            format!(
+                "⚠ Roster snapshot is {} hours old; students who accepted since are missing (re-save it with --save-roster)",
+                age.num_hours()
+            )
+        })
+    }
+}
+
+/// Where the roster of accepted assignments comes from
+#[derive(Debug, Clone, Default)]
+pub enum RosterSource {
+    /// Fetched from the Classroom API for every run
+    #[default]
+    Api,
+    /// Fetched from the API and written to this file
+    Save(PathBuf),
+    /// Read from this file instead of the API
+    Load(PathBuf),
+}
+
+impl RosterSource {
+    /// The roster to grade, or `None` to leave fetching it to the fetcher.
+    /// `log` receives the status messages worth showing.
+    pub async fn roster(
+        &self,
+        classroom_client: &ClassroomClient,
+        assignment_id: u64,
+        log: impl Fn(String),
+    ) -> Result<Option<Vec<AcceptedAssignment>>> {
+        match self {
+            RosterSource::Api => Ok(None),
+            RosterSource::Save(path) => {
+                let snapshot = RosterSnapshot {
+                    assignment_id,
+                    saved_at: Utc::now(),
+                    accepted_assignments: classroom_client.list_accepted_assignments(assignment_id).await?,
+                };
+                snapshot.save(path)?;
+                log(format!("✓ Saved the roster to {}", path.display()));
+                Ok(Some(snapshot.accepted_assignments))
+            }
+            RosterSource::Load(path) => {
+                let snapshot = RosterSnapshot::load(path)?;
+                if snapshot.assignment_id != assignment_id {
+                    anyhow::bail!(
+                        "Roster snapshot {} is for assignment {}, not {}",
+                        path.display(),
+                        snapshot.assignment_id,
+                        assignment_id
+                    );
+                }
+                log(format!(
+                    "✓ Loaded the roster from {} (saved {})",
+                    path.display(),
+                    snapshot.saved_at.format("%Y-%m-%d %H:%M UTC")
+                ));
+                if let Some(warning) = snapshot.staleness_warning(Utc::now()) {
+                    log(warning);
+                }
+                Ok(Some(snapshot.accepted_assignments))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let accepted: AcceptedAssignment = serde_json::from_value(serde_json::json!({
+            "id": 7,
+            "grade": null,
+            "students": [{ "id": 1, "login": "alice", "name": null, "avatar_url": "", "html_url": null }],
+            "repository": {
+                "id": 7,
+                "full_name": "org/repo-7",
+                "html_url": "https://github.com/org/repo-7",
+                "default_branch": "main",
+            },
+            "assignment": { "id": 1, "title": "Assignment" },
+        }))
+        .unwrap();
+        let saved_at = Utc::now();
+        let snapshot = RosterSnapshot {
+            assignment_id: 1,
+            saved_at,
+            accepted_assignments: vec![accepted],
+        };
+
+        let path = std::env::temp_dir().join(format!("roster_test_{}.json", std::process::id()));
+        snapshot.save(&path).unwrap();
+        let loaded = RosterSnapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded.accepted_assignments[0].students[0].login, "alice");
+
+        assert!(loaded.staleness_warning(saved_at + Duration::hours(2)).is_none());
+        let warning = loaded.staleness_warning(saved_at + Duration::hours(30)).unwrap();
+        assert!(warning.contains("30 hours old"), "{}", warning);
+    }
+}
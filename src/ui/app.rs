@@ -1,12 +1,19 @@
-use crate::api::{ClassroomClient, GitHubClient};
-use crate::export;
-use crate::fetcher;
-use crate::models::{Assignment, Classroom, ResultStats};
-use crate::parser;
+use crate::api::{self, oauth, ClassroomClient, DeviceFlowClient, GitHubClient};
+use crate::config::Config;
+use crate::deadlines::{AssignmentDeadlines, LateGradingDeadlines, SavedDeadlines, SAVED_DEADLINES_FILE};
+use crate::error::FetchError;
+use crate::export::{self, ExportFormat};
+use crate::fetcher::{self, FetchContext, FetchEvent, RunSelection, StudentOutcome, TestDefinitionCache};
+use crate::models::{Assignment, Classroom, LogsProblem, NonSubmitter, PointsSource, ResultStats, StudentResult, TestStats};
+use crate::results_cache::{ResultsCache, RESULTS_CACHE_FILE};
 use crate::ui::render::render_ui;
-use crate::ui::state::{AppState, DeadlineField, LateGradingField, FetchProgress};
+use crate::ui::state::{
+    parse_deadline, points_mismatch_warning, visible_classrooms, zero_available_warning, AppState, DeadlineField,
+    FetchedResults, JobLog, LateGradingField, FetchProgress, ResumeAction, StudentDetail, StudentFailure, StudentStatus,
+};
 use anyhow::{Context, Result};
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono::Utc;
+use chrono_tz::Tz;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent},
     execute,
@@ -14,28 +21,302 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often the spinner and progress redraw while a background task runs
+const ANIMATION_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to wait for input when nothing is running, before checking again
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Shown on the assignment options when "Before deadline" has no deadline to use
+const NO_DEADLINE_NOTICE: &str = "This assignment has no deadline in GitHub Classroom; use option 2 to enter one";
+
+/// Shown once per run when the token can't download job logs
+const LOGS_FORBIDDEN_WARNING: &str =
+    "⚠ GitHub refused to send job logs (403); the token may lack access to Actions. Points came from check runs or are approximate";
 
 pub struct App {
     classroom_client: ClassroomClient,
     github_client: GitHubClient,
+    config: Config,
     state: AppState,
     spinner_frame: usize,
+    /// Terminal height as of the last draw, used to size page jumps
+    viewport_height: u16,
+    /// Show a table with one row per student instead of the status log while fetching
+    student_table: bool,
     background_task: Option<tokio::task::JoinHandle<Result<AppState>>>,
     progress_rx: Option<tokio::sync::mpsc::UnboundedReceiver<FetchProgress>>,
+    /// Opened on first use and kept, since on X11 copied text only lasts as long as its owner
+    clipboard: Option<arboard::Clipboard>,
+}
+
+/// What a headless run printed with `--summary-json`, e.g. for CI to check the error rate
+#[derive(serde::Serialize)]
+struct HeadlessSummary<'a> {
+    classroom: &'a str,
+    assignment: &'a str,
+    stats: &'a ResultStats,
+    failures: &'a [StudentFailure],
+    /// Results files, primary first
+    files: &'a [String],
 }
 
 impl App {
-    pub fn new(classroom_client: ClassroomClient, github_client: GitHubClient) -> Self {
+    pub fn new(classroom_client: ClassroomClient, github_client: GitHubClient, config: Config) -> Self {
         Self {
             classroom_client,
             github_client,
+            config,
             state: AppState::LoadingClassrooms,
             spinner_frame: 0,
+            viewport_height: 0,
+            student_table: false,
             background_task: None,
             progress_rx: None,
+            clipboard: None,
+        }
+    }
+
+    /// Export the latest results of one assignment without the terminal UI, for
+    /// scripts. The status log goes to stderr unless `quiet`; stdout gets the
+    /// results file paths (primary first), and with `quiet` nothing else. With
+    /// `summary_json`, stdout gets a single [`HeadlessSummary`] line instead.
+    pub async fn run_headless(self, assignment_id: u64, quiet: bool, summary_json: bool) -> Result<()> {
+        let assignment = self.classroom_client.get_assignment(assignment_id).await?;
+        let classroom = self.classroom_client.get_classroom(assignment.classroom.id).await?;
+
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel::<FetchProgress>();
+        let status_log = tokio::spawn(async move {
+            let mut failures = Vec::new();
+            while let Some(progress) = progress_rx.recv().await {
+                // Each update carries only the lines added since the last one
+                if !quiet {
+                    for message in &progress.status_messages {
+                        eprintln!("{}", message);
+                    }
+                }
+                failures = progress.failures;
+            }
+            failures
+        });
+
+        let state = Self::do_fetch_results(
+            self.classroom_client,
+            self.github_client,
+            classroom,
+            assignment,
+            RunSelection::Latest,
+            self.config,
+            progress_tx,
+        )
+        .await;
+        // The sender was dropped with the fetch, so the log is complete once this returns
+        let failures = status_log.await.unwrap_or_default();
+
+        let AppState::ResultsComplete {
+            assignment,
+            classroom,
+            stats,
+            filenames,
+            summary_filename,
+            test_stats_filename,
+            nonsubmitters,
+            nonsubmitters_filename,
+            run_log_filename,
+            drift_error,
+            ..
+        } = state?
+        else {
+            anyhow::bail!("Fetch ended without exporting results");
+        };
+
+        if summary_json {
+            let summary = HeadlessSummary {
+                classroom: &classroom.name,
+                assignment: &assignment.slug,
+                stats: &stats,
+                failures: &failures,
+                files: &filenames,
+            };
+            println!("{}", serde_json::to_string(&summary)?);
+        } else {
+            for filename in &filenames {
+                println!("{}", filename);
+            }
+        }
+        if !quiet {
+            eprintln!("Summary: {}", summary_filename);
+            eprintln!("Test stats: {}", test_stats_filename);
+            if let Some(nonsubmitters_filename) = nonsubmitters_filename {
+                eprintln!("Not submitted ({}): {}", nonsubmitters, nonsubmitters_filename);
+            }
+            if let Some(run_log_filename) = run_log_filename {
+                eprintln!("Run log: {}", run_log_filename);
+            }
+        }
+        // The results are exported either way, so they can be inspected
+        if let Some(drift_error) = drift_error {
+            anyhow::bail!(drift_error);
+        }
+        Ok(())
+    }
+
+    /// Keys of the completion screen's per-student view, and of the raw log
+    /// opened from it
+    fn handle_student_detail_key(
+        &mut self,
+        code: KeyCode,
+        detail: &mut Option<StudentDetail>,
+        results: &FetchedResults,
+        action_status: &mut Option<String>,
+    ) {
+        let Some(view) = detail else {
+            return;
+        };
+
+        if let Some(log) = &mut view.log {
+            let page = self.list_page_size();
+            let bottom = log.lines.len().saturating_sub(page);
+            log.scroll = match code {
+                KeyCode::Up => log.scroll.saturating_sub(1),
+                KeyCode::Down => log.scroll + 1,
+                KeyCode::PageUp => log.scroll.saturating_sub(page),
+                KeyCode::PageDown => log.scroll + page,
+                KeyCode::Home => 0,
+                KeyCode::End => bottom,
+                KeyCode::Esc => {
+                    if log.loading {
+                        self.cancel_background_task();
+                    }
+                    view.log = None;
+                    return;
+                }
+                _ => log.scroll,
+            }
+            .min(bottom);
+            return;
+        }
+
+        let last = results.len().saturating_sub(1);
+        let page = self.list_page_size();
+        match code {
+            KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Down => view.selected = (view.selected + 1).min(last),
+            KeyCode::PageUp => view.selected = view.selected.saturating_sub(page),
+            KeyCode::PageDown => view.selected = (view.selected + page).min(last),
+            KeyCode::Home => view.selected = 0,
+            KeyCode::End => view.selected = last,
+            KeyCode::Tab if matches!(results, FetchedResults::Late(_)) => view.late_run = !view.late_run,
+            KeyCode::Char('o') => {
+                if let Some(run) = results.run(view.selected, view.late_run) {
+                    open_in_browser(&run.run_url);
+                }
+            }
+            KeyCode::Char('c') => {
+                if let Some(run) = results.run(view.selected, view.late_run) {
+                    let run_url = run.run_url.clone();
+                    *action_status = Some(self.copy_text(&run_url));
+                }
+            }
+            KeyCode::Char('l') => {
+                let Some(run) = results.run(view.selected, view.late_run) else {
+                    return;
+                };
+                // Logs can be large, so they download in the background while the pager shows a spinner
+                let title = format!("Log of {}'s run", run.username);
+                view.log = Some(JobLog::loading(title.clone()));
+                let github_client = self.github_client.clone();
+                let run = run.clone();
+                self.background_task = Some(tokio::spawn(async move {
+                    let log = fetcher::fetch_run_logs(&github_client, &run)
+                        .await
+                        .map(|logs| JobLog::new(title, &logs))
+                        .map_err(|e| e.to_string());
+                    Ok(AppState::JobLogLoaded { log })
+                }));
+            }
+            KeyCode::Esc => *detail = None,
+            _ => {}
+        }
+    }
+
+    /// Show a log downloaded in the background in the student view, unless
+    /// that view or its log was closed in the meantime
+    fn show_job_log(&mut self, log: Result<JobLog, String>) {
+        let AppState::ResultsComplete {
+            detail: Some(view),
+            action_status,
+            ..
+        } = &mut self.state
+        else {
+            return;
+        };
+        if !view.log.as_ref().is_some_and(|log| log.loading) {
+            return;
+        }
+
+        match log {
+            Ok(log) => view.log = Some(log),
+            Err(e) => {
+                view.log = None;
+                *action_status = Some(format!("Couldn't fetch the log: {}", e));
+            }
+        }
+    }
+
+    /// Copy a file's full path to the system clipboard, returning a message for the user
+    fn copy_to_clipboard(&mut self, filename: &str) -> String {
+        let path = std::fs::canonicalize(filename)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| filename.to_string());
+        self.copy_text(&path)
+    }
+
+    /// Copy text to the system clipboard, returning a message for the user
+    fn copy_text(&mut self, text: &str) -> String {
+        let copied = match &mut self.clipboard {
+            Some(clipboard) => clipboard.set_text(text),
+            None => arboard::Clipboard::new().and_then(|clipboard| {
+                self.clipboard.insert(clipboard).set_text(text)
+            }),
+        };
+
+        match copied {
+            Ok(()) => format!("Copied {} to the clipboard", text),
+            Err(e) => format!("Couldn't copy to the clipboard: {}", e),
         }
     }
 
+    /// Export the results on the completion screen again in `format`, e.g. a
+    /// JSON copy wanted only after seeing the stats
+    fn export_again(
+        &self,
+        classroom: &Classroom,
+        assignment: &Assignment,
+        results: &FetchedResults,
+        format: ExportFormat,
+    ) -> Result<PathBuf> {
+        let options = export::ExportOptions {
+            formats: vec![format],
+            ..self.config.export.clone()
+        };
+        let file_name = export_name(assignment, &self.config);
+        let names = export::ExportNames {
+            assignment: &file_name,
+            classroom: &classroom.name,
+            source: Some(assignment),
+        };
+        let paths = match results {
+            FetchedResults::Regular(results) => export::export_results(results, names, &options)?,
+            FetchedResults::Late(results) => export::export_late_grading(results, names, &options)?,
+        };
+        paths.into_iter().next().context("Nothing was exported")
+    }
+
     fn spinner_char(&self) -> char {
         const SPINNER_FRAMES: &[char] = &['|', '/', '-', '\\'];
         SPINNER_FRAMES[self.spinner_frame % SPINNER_FRAMES.len()]
@@ -49,8 +330,12 @@ impl App {
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
 
-        // Load initial classrooms
-        self.load_classrooms().await?;
+        // Log in first if asked to (or there is no token), otherwise load classrooms right away
+        if self.config.login {
+            self.start_device_login().await;
+        } else {
+            self.load_classrooms();
+        }
 
         // Main event loop
         let result = self.event_loop(&mut terminal).await;
@@ -71,24 +356,42 @@ impl App {
         &mut self,
         terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     ) -> Result<()> {
+        // Redraw only when something changed: a key, a resize, progress, a
+        // finished task, or an animation tick while a task is running
+        let mut dirty = true;
+        let mut last_tick = Instant::now();
         loop {
-            // Update spinner for progress indication
-            self.spinner_frame = self.spinner_frame.wrapping_add(1);
-            let spinner = self.spinner_char();
+            // Animate the spinner (and countdowns) at a fixed rate while work is in flight
+            if self.background_task.is_some() && last_tick.elapsed() >= ANIMATION_INTERVAL {
+                self.spinner_frame = self.spinner_frame.wrapping_add(1);
+                last_tick = Instant::now();
+                dirty = true;
+            }
 
-            // Always redraw the UI
-            terminal.draw(|f| render_ui(f, &self.state, spinner))?;
+            if dirty {
+                let spinner = self.spinner_char();
+                let api_status = self.github_client.status();
+                terminal.draw(|f| {
+                    render_ui(f, &self.state, spinner, api_status, self.config.timezone, &self.config.theme, self.student_table)
+                })?;
+                self.viewport_height = terminal.size()?.height;
+                dirty = false;
+            }
 
             // Check for progress updates
             if let Some(rx) = &mut self.progress_rx {
                 while let Ok(progress) = rx.try_recv() {
+                    dirty = true;
                     // Update the progress in the current state
                     match &mut self.state {
                         AppState::FetchingResults { progress: p, .. } => {
-                            *p = progress;
+                            p.update(progress);
                         }
                         AppState::FetchingLateResults { progress: p, .. } => {
-                            *p = progress;
+                            p.update(progress);
+                        }
+                        AppState::FetchingAllAssignments { progress: p, .. } => {
+                            p.update(progress);
                         }
                         _ => {}
                     }
@@ -96,65 +399,298 @@ impl App {
             }
 
             // Check if background task has completed
-            if let Some(task) = &mut self.background_task {
-                if task.is_finished() {
-                    let task = self.background_task.take().unwrap();
-                    self.progress_rx = None; // Clear progress channel
-                    match task.await {
-                        Ok(Ok(new_state)) => {
-                            self.state = new_state;
-                        }
-                        Ok(Err(e)) => {
-                            self.state = AppState::Error {
-                                message: format!("Failed to fetch results: {}", e),
-                            };
-                        }
-                        Err(e) => {
-                            self.state = AppState::Error {
-                                message: format!("Background task failed: {}", e),
-                            };
+            if let Some(task) = &mut self.background_task
+                && task.is_finished()
+            {
+                let task = self.background_task.take().unwrap();
+                self.progress_rx = None; // Clear progress channel
+                dirty = true;
+                match task.await {
+                    Ok(Ok(AppState::LoggedIn { token })) => {
+                        self.finish_device_login(token);
+                    }
+                    Ok(Ok(AppState::JobLogLoaded { log })) => {
+                        self.show_job_log(log);
+                    }
+                    Ok(Ok(AppState::AssignmentSelection {
+                        classroom,
+                        assignments,
+                        ..
+                    })) if self.config.all_assignments => {
+                        self.spawn_fetch_all_assignments(classroom, assignments);
+                    }
+                    Ok(Ok(new_state)) => {
+                        self.state = new_state;
+                    }
+                    Ok(Err(e)) if api::is_unauthorized(&e) => {
+                        match self.interrupted_fetch() {
+                            Some(resume) => self.request_reauth(&e, resume),
+                            None => {
+                                self.state = AppState::Error {
+                                    message: format!("Failed to fetch results: {}", e),
+                                };
+                            }
                         }
                     }
+                    Ok(Err(e)) => {
+                        let action = match self.state {
+                            AppState::DeviceLogin { .. } => "log in",
+                            AppState::LoadingClassrooms => "load classrooms",
+                            AppState::LoadingAssignments { .. } => "load assignments",
+                            _ => "fetch results",
+                        };
+                        self.state = AppState::Error {
+                            message: format!("Failed to {}: {}", action, e),
+                        };
+                    }
+                    Err(e) => {
+                        self.state = AppState::Error {
+                            message: format!("Background task failed: {}", e),
+                        };
+                    }
                 }
             }
 
-            // Check for keyboard events with a short timeout
-            if event::poll(std::time::Duration::from_millis(50))? {
-                if let Event::Key(key) = event::read()? {
-                    if self.handle_key_event(key).await? {
-                        break; // User quit
+            // Wait for input until the next animation frame is due, or longer when idle
+            let timeout = if self.background_task.is_some() {
+                ANIMATION_INTERVAL.saturating_sub(last_tick.elapsed())
+            } else {
+                IDLE_POLL_INTERVAL
+            };
+            if event::poll(timeout)? {
+                match event::read()? {
+                    Event::Key(key) => {
+                        dirty = true;
+                        if self.handle_key_event(key).await? {
+                            break; // User quit
+                        }
                     }
+                    Event::Resize(..) => dirty = true,
+                    _ => {}
                 }
             }
 
-            // Small yield to allow other async tasks to run
-            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            // Let other async tasks run
+            tokio::task::yield_now().await;
         }
 
         Ok(())
     }
 
-    async fn load_classrooms(&mut self) -> Result<()> {
-        match self.classroom_client.list_classrooms().await {
-            Ok(classrooms) => {
-                if classrooms.is_empty() {
-                    self.state = AppState::Error {
-                        message: "No classrooms found. Please check your GitHub token permissions."
-                            .to_string(),
-                    };
-                } else {
-                    self.state = AppState::ClassroomSelection {
-                        classrooms,
-                        selected_index: 0,
-                    };
-                }
+    /// Load classrooms in the background, so the loading spinner keeps turning
+    fn load_classrooms(&mut self) {
+        self.state = AppState::LoadingClassrooms;
+
+        let classroom_client = self.classroom_client.clone();
+        let show_archived = self.config.show_archived;
+        let org = self.config.org.clone();
+        self.background_task = Some(tokio::spawn(async move {
+            let classrooms = match &org {
+                Some(org) => classroom_client.list_classrooms_for_org(org).await?,
+                None => classroom_client.list_classrooms().await?,
+            };
+            if classrooms.is_empty() {
+                let message = match org {
+                    Some(org) => format!("No classrooms found in the {} organization. Check the --org name.", org),
+                    None => "No classrooms found. Please check your GitHub token permissions.".to_string(),
+                };
+                return Ok(AppState::Error { message });
+            }
+            Ok(AppState::ClassroomSelection {
+                classrooms,
+                selected_index: 0,
+                show_archived,
+            })
+        }));
+    }
+
+    /// Load a classroom's assignments in the background, like `load_classrooms`
+    fn load_assignments(&mut self, classroom: Classroom) {
+        self.state = AppState::LoadingAssignments {
+            classroom: classroom.clone(),
+        };
+
+        let classroom_client = self.classroom_client.clone();
+        self.background_task = Some(tokio::spawn(async move {
+            let assignments = classroom_client.list_assignments(classroom.id).await?;
+            Ok(AppState::AssignmentSelection {
+                classroom,
+                assignments,
+                selected_index: 0,
+            })
+        }));
+    }
+
+    /// Parse the assignment's workflow and show its tests, without fetching any student results
+    async fn preview_test_definitions(&mut self, classroom: Classroom, assignment: Assignment) {
+        let test_definitions = async {
+            let details = self.classroom_client.get_assignment(assignment.id).await?;
+            // The roster's grades show the total Classroom expects, and it's where
+            // the workflow is read from when there is no starter repo
+            let accepted_assignments = self.classroom_client.list_accepted_assignments(assignment.id).await?;
+            let test_definitions = fetcher::load_test_definitions(
+                &self.github_client,
+                &details,
+                &accepted_assignments,
+                &TestDefinitionCache::default(),
+                // Any replaced bytes in test names show up in the preview itself
+                |_| {},
+            )
+            .await?;
+            let points_warning = fetcher::points_mismatch(&test_definitions, &accepted_assignments)
+                .map(|(workflow, classroom)| points_mismatch_warning(workflow, classroom));
+            Ok::<_, FetchError>((test_definitions, points_warning))
+        }
+        .await;
+
+        match test_definitions {
+            Ok((test_definitions, points_warning)) => {
+                self.state = AppState::TestDefinitionsPreview {
+                    classroom,
+                    assignment,
+                    test_definitions,
+                    points_warning,
+                };
+            }
+            Err(e @ FetchError::AuthFailed(_)) => {
+                self.request_reauth(&e.into(), ResumeAction::LoadAssignments { classroom });
             }
             Err(e) => {
                 self.state = AppState::Error {
-                    message: format!("Failed to load classrooms: {}", e),
+                    message: format!("Failed to load test definitions: {}", e),
                 };
             }
         }
+    }
+
+    /// Request a device code and wait in the background for the user to approve it on GitHub
+    async fn start_device_login(&mut self) {
+        let Some(client_id) = self.config.oauth_client_id.clone() else {
+            self.state = AppState::Error {
+                message: "Login needs an OAuth app client id (GITHUB_CLIENT_ID or --client-id)".to_string(),
+            };
+            return;
+        };
+
+        let mut client = DeviceFlowClient::new(client_id);
+        if let Some(proxy) = &self.config.proxy {
+            client = client.with_proxy(proxy.clone());
+        }
+        match client.request_device_code().await {
+            Ok(device_code) => {
+                self.state = AppState::DeviceLogin {
+                    user_code: device_code.user_code.clone(),
+                    verification_uri: device_code.verification_uri.clone(),
+                };
+                self.background_task = Some(tokio::spawn(async move {
+                    let token = client.poll_for_token(&device_code).await?;
+                    Ok(AppState::LoggedIn { token })
+                }));
+            }
+            Err(e) => {
+                self.state = AppState::Error {
+                    message: format!("Failed to start login: {}", e),
+                };
+            }
+        }
+    }
+
+    fn finish_device_login(&mut self, token: String) {
+        // A token that can't be saved still works for this session; the next run just asks again
+        let _ = oauth::store_token(&token);
+
+        self.classroom_client.set_token(token.clone());
+        self.github_client.set_token(token.clone());
+        self.config.github_token = token;
+        self.config.login = false;
+        self.load_classrooms();
+    }
+
+    /// Visible rows in the classroom/assignment lists (minus borders and help bar)
+    fn list_page_size(&self) -> usize {
+        (self.viewport_height as usize).saturating_sub(5).max(1)
+    }
+
+    /// Stop the running fetch, dropping its progress updates
+    fn cancel_background_task(&mut self) {
+        if let Some(task) = self.background_task.take() {
+            task.abort();
+        }
+        self.progress_rx = None;
+    }
+
+    /// Visible rows in the fetching screen's status log
+    fn log_page_size(&self) -> usize {
+        (self.viewport_height as usize).saturating_sub(12).max(1)
+    }
+
+    /// Prompt for a fresh token, remembering what to retry afterwards
+    fn request_reauth(&mut self, error: &anyhow::Error, resume: ResumeAction) {
+        self.state = AppState::ReAuth {
+            message: error.to_string(),
+            token_input: String::new(),
+            resume,
+        };
+    }
+
+    /// The background load or fetch that was running in the current state, if any
+    fn interrupted_fetch(&self) -> Option<ResumeAction> {
+        match &self.state {
+            AppState::LoadingClassrooms => Some(ResumeAction::LoadClassrooms),
+            AppState::LoadingAssignments { classroom } => Some(ResumeAction::LoadAssignments {
+                classroom: classroom.clone(),
+            }),
+            AppState::FetchingResults {
+                classroom,
+                assignment,
+                selection,
+                ..
+            } => Some(ResumeAction::FetchResults {
+                classroom: classroom.clone(),
+                assignment: assignment.clone(),
+                selection: *selection,
+            }),
+            AppState::FetchingLateResults {
+                classroom,
+                assignment,
+                deadlines,
+                ..
+            } => Some(ResumeAction::FetchLateResults {
+                classroom: classroom.clone(),
+                assignment: assignment.clone(),
+                deadlines: deadlines.clone(),
+            }),
+            AppState::FetchingAllAssignments {
+                classroom,
+                assignments,
+                ..
+            } => Some(ResumeAction::FetchAllAssignments {
+                classroom: classroom.clone(),
+                assignments: assignments.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    async fn resume(&mut self, action: ResumeAction) -> Result<()> {
+        match action {
+            ResumeAction::LoadClassrooms => self.load_classrooms(),
+            ResumeAction::LoadAssignments { classroom } => self.load_assignments(classroom),
+            ResumeAction::FetchResults {
+                classroom,
+                assignment,
+                selection,
+            } => self.spawn_fetch_results(classroom, assignment, selection),
+            ResumeAction::FetchLateResults {
+                classroom,
+                assignment,
+                deadlines,
+            } => self.spawn_fetch_late_results(classroom, assignment, deadlines),
+            ResumeAction::FetchAllAssignments {
+                classroom,
+                assignments,
+            } => self.spawn_fetch_all_assignments(classroom, assignments),
+        }
         Ok(())
     }
 
@@ -166,56 +702,51 @@ impl App {
             AppState::ClassroomSelection {
                 classrooms,
                 mut selected_index,
+                mut show_archived,
             } => {
+                let visible_count = visible_classrooms(&classrooms, show_archived).len();
                 match key.code {
                     KeyCode::Char('q') => return Ok(true), // Quit
                     KeyCode::Up => {
-                        if selected_index > 0 {
-                            selected_index -= 1;
-                        }
-                        self.state = AppState::ClassroomSelection {
-                            classrooms,
-                            selected_index,
-                        };
+                        selected_index = selected_index.saturating_sub(1);
                     }
-                    KeyCode::Down => {
-                        if selected_index < classrooms.len().saturating_sub(1) {
-                            selected_index += 1;
-                        }
-                        self.state = AppState::ClassroomSelection {
-                            classrooms,
+                    KeyCode::Down if selected_index + 1 < visible_count => {
+                        selected_index += 1;
+                    }
+                    KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End => {
+                        selected_index = page_selection(
+                            key.code,
                             selected_index,
-                        };
+                            visible_count,
+                            self.list_page_size(),
+                        );
+                    }
+                    KeyCode::Char('a') => {
+                        // Toggle archived classrooms, keeping the same classroom selected if it stays visible
+                        let selected_id = visible_classrooms(&classrooms, show_archived)
+                            .get(selected_index)
+                            .map(|c| c.id);
+                        show_archived = !show_archived;
+                        self.config.show_archived = show_archived;
+                        selected_index = visible_classrooms(&classrooms, show_archived)
+                            .iter()
+                            .position(|c| Some(c.id) == selected_id)
+                            .unwrap_or(0);
                     }
                     KeyCode::Enter => {
-                        let classroom = classrooms[selected_index].clone();
-                        self.state = AppState::LoadingAssignments {
-                            classroom: classroom.clone(),
-                        };
-
-                        // Load assignments
-                        match self.classroom_client.list_assignments(classroom.id).await {
-                            Ok(assignments) => {
-                                self.state = AppState::AssignmentSelection {
-                                    classroom,
-                                    assignments,
-                                    selected_index: 0,
-                                };
-                            }
-                            Err(e) => {
-                                self.state = AppState::Error {
-                                    message: format!("Failed to load assignments: {}", e),
-                                };
-                            }
+                        if let Some(classroom) = visible_classrooms(&classrooms, show_archived).get(selected_index) {
+                            let classroom = (*classroom).clone();
+                            self.load_assignments(classroom);
+                            return Ok(false);
                         }
                     }
-                    _ => {
-                        self.state = AppState::ClassroomSelection {
-                            classrooms,
-                            selected_index,
-                        };
-                    }
+                    _ => {}
                 }
+                self.state = AppState::ClassroomSelection {
+                    classrooms,
+                    selected_index,
+                    show_archived,
+                };
             }
             AppState::AssignmentSelection {
                 classroom,
@@ -226,12 +757,10 @@ impl App {
                     KeyCode::Char('q') => return Ok(true),
                     KeyCode::Esc => {
                         // Go back to classroom selection
-                        self.load_classrooms().await?;
+                        self.load_classrooms();
                     }
                     KeyCode::Up => {
-                        if selected_index > 0 {
-                            selected_index -= 1;
-                        }
+                        selected_index = selected_index.saturating_sub(1);
                         self.state = AppState::AssignmentSelection {
                             classroom,
                             assignments,
@@ -248,12 +777,38 @@ impl App {
                             selected_index,
                         };
                     }
+                    KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End => {
+                        selected_index = page_selection(
+                            key.code,
+                            selected_index,
+                            assignments.len(),
+                            self.list_page_size(),
+                        );
+                        self.state = AppState::AssignmentSelection {
+                            classroom,
+                            assignments,
+                            selected_index,
+                        };
+                    }
+                    KeyCode::Char('a') => {
+                        self.spawn_fetch_all_assignments(classroom, assignments);
+                    }
+                    KeyCode::Char('o') => {
+                        open_in_browser(&classroom.url);
+                        self.state = AppState::AssignmentSelection {
+                            classroom,
+                            assignments,
+                            selected_index,
+                        };
+                    }
                     KeyCode::Enter => {
                         let assignment = assignments[selected_index].clone();
                         self.state = AppState::AssignmentOptions {
                             classroom,
                             assignment,
                             selected_index: 0,
+                            student: self.config.fetch.student.clone(),
+                            notice: None,
                         };
                     }
                     _ => {
@@ -269,60 +824,60 @@ impl App {
                 classroom,
                 assignment,
                 mut selected_index,
+                student,
+                ..
             } => {
                 match key.code {
                     KeyCode::Char('q') => return Ok(true),
                     KeyCode::Esc => {
                         // Go back to assignment selection
-                        match self.classroom_client.list_assignments(classroom.id).await {
-                            Ok(assignments) => {
-                                self.state = AppState::AssignmentSelection {
-                                    classroom,
-                                    assignments,
-                                    selected_index: 0,
-                                };
-                            }
-                            Err(e) => {
-                                self.state = AppState::Error {
-                                    message: format!("Failed to load assignments: {}", e),
-                                };
-                            }
-                        }
+                        self.load_assignments(classroom);
                     }
                     KeyCode::Up => {
-                        if selected_index > 0 {
-                            selected_index -= 1;
-                        }
+                        selected_index = selected_index.saturating_sub(1);
                         self.state = AppState::AssignmentOptions {
                             classroom,
                             assignment,
                             selected_index,
+                            student,
+                            notice: None,
                         };
                     }
                     KeyCode::Down => {
-                        if selected_index < 2 {
-                            // 0: Latest, 1: After deadline, 2: Late Grading
+                        if selected_index < 4 {
+                            // 0: Latest, 1: After deadline, 2: Late Grading, 3: Single student, 4: Before deadline
                             selected_index += 1;
                         }
                         self.state = AppState::AssignmentOptions {
                             classroom,
                             assignment,
                             selected_index,
+                            student,
+                            notice: None,
                         };
                     }
-                    KeyCode::Enter => {
+                    KeyCode::Char('t') => {
+                        self.preview_test_definitions(classroom, assignment).await;
+                    }
+                    KeyCode::Enter | KeyCode::Char('1'..='5') => {
+                        // Number keys select and activate the matching option directly
+                        if let KeyCode::Char(c) = key.code {
+                            selected_index = c as usize - '1' as usize;
+                        }
                         match selected_index {
                             0 => {
                                 // Download latest results - spawn as background task
-                                self.spawn_fetch_results(classroom, assignment, None);
+                                self.spawn_fetch_results(classroom, assignment, RunSelection::Latest);
                             }
                             1 => {
                                 // Download results after deadline
+                                let deadline = saved_deadlines(&assignment).deadline.or(assignment.deadline);
+                                let (date_input, time_input) = deadline_inputs(deadline, self.config.timezone);
                                 self.state = AppState::DeadlineInput {
                                     classroom,
                                     assignment,
-                                    date_input: String::new(),
-                                    time_input: String::new(),
+                                    date_input,
+                                    time_input,
                                     focused_field: DeadlineField::Date,
                                 };
                             }
@@ -334,18 +889,132 @@ impl App {
                                     selected_index: 0,
                                 };
                             }
+                            3 => {
+                                // Choose (or clear) the single student to grade
+                                self.state = AppState::StudentInput {
+                                    classroom,
+                                    assignment,
+                                    username_input: student.unwrap_or_default(),
+                                };
+                            }
+                            4 => {
+                                // Latest results before the assignment's Classroom deadline
+                                match assignment.deadline {
+                                    Some(deadline) => self.spawn_fetch_results(
+                                        classroom,
+                                        assignment,
+                                        RunSelection::LatestBefore(deadline),
+                                    ),
+                                    None => {
+                                        self.state = AppState::AssignmentOptions {
+                                            classroom,
+                                            assignment,
+                                            selected_index,
+                                            student,
+                                            notice: Some(NO_DEADLINE_NOTICE.to_string()),
+                                        };
+                                    }
+                                }
+                            }
                             _ => {}
                         }
                     }
+                    KeyCode::Char('o') => {
+                        open_in_browser(&classroom.assignment_url(&assignment.slug));
+                        self.state = AppState::AssignmentOptions {
+                            classroom,
+                            assignment,
+                            selected_index,
+                            student,
+                            notice: None,
+                        };
+                    }
                     _ => {
                         self.state = AppState::AssignmentOptions {
                             classroom,
                             assignment,
                             selected_index,
+                            student,
+                            notice: None,
                         };
                     }
                 }
             }
+            AppState::TestDefinitionsPreview {
+                classroom,
+                assignment,
+                test_definitions,
+                points_warning,
+            } => match key.code {
+                KeyCode::Char('q') => return Ok(true),
+                KeyCode::Esc | KeyCode::Enter => {
+                    self.state = AppState::AssignmentOptions {
+                        classroom,
+                        assignment,
+                        selected_index: 0,
+                        student: self.config.fetch.student.clone(),
+                        notice: None,
+                    };
+                }
+                _ => {
+                    self.state = AppState::TestDefinitionsPreview {
+                        classroom,
+                        assignment,
+                        test_definitions,
+                        points_warning,
+                    };
+                }
+            },
+            AppState::StudentInput {
+                classroom,
+                assignment,
+                mut username_input,
+            } => match key.code {
+                KeyCode::Esc => {
+                    self.state = AppState::AssignmentOptions {
+                        classroom,
+                        assignment,
+                        selected_index: 3,
+                        student: self.config.fetch.student.clone(),
+                        notice: None,
+                    };
+                }
+                KeyCode::Enter => {
+                    // An empty username goes back to grading the whole class
+                    let username = username_input.trim();
+                    self.config.fetch.student = (!username.is_empty()).then(|| username.to_string());
+                    self.state = AppState::AssignmentOptions {
+                        classroom,
+                        assignment,
+                        selected_index: 0,
+                        student: self.config.fetch.student.clone(),
+                        notice: None,
+                    };
+                }
+                KeyCode::Char(c) if !c.is_whitespace() => {
+                    username_input.push(c);
+                    self.state = AppState::StudentInput {
+                        classroom,
+                        assignment,
+                        username_input,
+                    };
+                }
+                KeyCode::Backspace => {
+                    username_input.pop();
+                    self.state = AppState::StudentInput {
+                        classroom,
+                        assignment,
+                        username_input,
+                    };
+                }
+                _ => {
+                    self.state = AppState::StudentInput {
+                        classroom,
+                        assignment,
+                        username_input,
+                    };
+                }
+            },
             AppState::DeadlineInput {
                 classroom,
                 assignment,
@@ -361,6 +1030,8 @@ impl App {
                             classroom,
                             assignment,
                             selected_index: 0,
+                            student: self.config.fetch.student.clone(),
+                            notice: None,
                         };
                     }
                     KeyCode::Tab => {
@@ -419,9 +1090,10 @@ impl App {
                     }
                     KeyCode::Enter => {
                         // Parse and validate deadline
-                        match parse_deadline(&date_input, &time_input) {
+                        match parse_deadline(&date_input, &time_input, self.config.timezone) {
                             Ok(deadline) => {
-                                self.spawn_fetch_results(classroom, assignment, Some(deadline));
+                                remember_deadlines(&assignment, |saved| saved.deadline = Some(deadline));
+                                self.spawn_fetch_results(classroom, assignment, RunSelection::FirstAfter(deadline));
                             }
                             Err(e) => {
                                 self.state = AppState::Error {
@@ -454,12 +1126,12 @@ impl App {
                             classroom,
                             assignment,
                             selected_index: 2,
+                            student: self.config.fetch.student.clone(),
+                            notice: None,
                         };
                     }
                     KeyCode::Up => {
-                        if selected_index > 0 {
-                            selected_index -= 1;
-                        }
+                        selected_index = selected_index.saturating_sub(1);
                         self.state = AppState::GradingModeSelection {
                             classroom,
                             assignment,
@@ -476,28 +1148,41 @@ impl App {
                             selected_index,
                         };
                     }
-                    KeyCode::Enter => {
+                    KeyCode::Enter | KeyCode::Char('1'..='2') => {
+                        // Number keys select and activate the matching mode directly
+                        if let KeyCode::Char(c) = key.code {
+                            selected_index = c as usize - '1' as usize;
+                        }
                         match selected_index {
                             0 => {
                                 // Regular grading - single deadline
+                                let deadline = saved_deadlines(&assignment).deadline.or(assignment.deadline);
+                                let (date_input, time_input) = deadline_inputs(deadline, self.config.timezone);
                                 self.state = AppState::DeadlineInput {
                                     classroom,
                                     assignment,
-                                    date_input: String::new(),
-                                    time_input: String::new(),
+                                    date_input,
+                                    time_input,
                                     focused_field: DeadlineField::Date,
                                 };
                             }
                             1 => {
                                 // Late grading - on-time + late deadlines
+                                let saved = saved_deadlines(&assignment).late_grading;
+                                let (on_time_date, on_time_time) = deadline_inputs(
+                                    saved.as_ref().map(|s| s.on_time_deadline).or(assignment.deadline),
+                                    self.config.timezone,
+                                );
+                                let (late_date, late_time) =
+                                    deadline_inputs(saved.as_ref().map(|s| s.late_deadline), self.config.timezone);
                                 self.state = AppState::LateGradingInput {
                                     classroom,
                                     assignment,
-                                    on_time_date: String::new(),
-                                    on_time_time: String::new(),
-                                    late_date: String::new(),
-                                    late_time: String::new(),
-                                    penalty_input: "20".to_string(),
+                                    on_time_date,
+                                    on_time_time,
+                                    late_date,
+                                    late_time,
+                                    penalty_input: saved.map_or("20".to_string(), |s| s.late_penalty.to_string()),
                                     focused_field: LateGradingField::OnTimeDate,
                                 };
                             }
@@ -651,46 +1336,29 @@ impl App {
                     }
                     KeyCode::Enter => {
                         // Parse and validate inputs
-                        let on_time_deadline = match (
-                            NaiveDate::parse_from_str(&on_time_date, "%Y-%m-%d"),
-                            NaiveTime::parse_from_str(&on_time_time, "%H:%M"),
-                        ) {
-                            (Ok(date), Ok(time)) => {
-                                chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(
-                                    date.and_time(time),
-                                    chrono::Utc,
-                                )
-                            }
-                            _ => {
+                        let timezone = self.config.timezone;
+                        let on_time_deadline = match parse_deadline(&on_time_date, &on_time_time, timezone) {
+                            Ok(deadline) => deadline,
+                            Err(e) => {
                                 self.state = AppState::Error {
-                                    message: "Invalid on-time deadline format. Use YYYY-MM-DD and HH:MM"
-                                        .to_string(),
+                                    message: format!("Invalid on-time deadline: {}", e),
                                 };
                                 return Ok(false);
                             }
                         };
 
-                        let late_deadline = match (
-                            NaiveDate::parse_from_str(&late_date, "%Y-%m-%d"),
-                            NaiveTime::parse_from_str(&late_time, "%H:%M"),
-                        ) {
-                            (Ok(date), Ok(time)) => {
-                                chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(
-                                    date.and_time(time),
-                                    chrono::Utc,
-                                )
-                            }
-                            _ => {
+                        let late_deadline = match parse_deadline(&late_date, &late_time, timezone) {
+                            Ok(deadline) => deadline,
+                            Err(e) => {
                                 self.state = AppState::Error {
-                                    message: "Invalid late deadline format. Use YYYY-MM-DD and HH:MM"
-                                        .to_string(),
+                                    message: format!("Invalid late deadline: {}", e),
                                 };
                                 return Ok(false);
                             }
                         };
 
-                        let late_penalty = match penalty_input.parse::<f64>() {
-                            Ok(p) if p >= 0.0 && p <= 100.0 => p / 100.0,
+                        let late_penalty_percent = match penalty_input.parse::<f64>() {
+                            Ok(p) if (0.0..=100.0).contains(&p) => p,
                             _ => {
                                 self.state = AppState::Error {
                                     message: "Invalid penalty percentage. Use 0-100".to_string(),
@@ -699,14 +1367,17 @@ impl App {
                             }
                         };
 
-                        // Start fetching late results - spawn as background task
-                        self.spawn_fetch_late_results(
-                            classroom,
-                            assignment,
+                        let deadlines = LateGradingDeadlines {
                             on_time_deadline,
                             late_deadline,
-                            late_penalty,
-                        );
+                            late_penalty: late_penalty_percent,
+                        };
+                        remember_deadlines(&assignment, |saved| {
+                            saved.late_grading = Some(deadlines.clone());
+                        });
+
+                        // Start fetching late results - spawn as background task
+                        self.spawn_fetch_late_results(classroom, assignment, deadlines);
                     }
                     _ => {
                         self.state = AppState::LateGradingInput {
@@ -722,19 +1393,139 @@ impl App {
                     }
                 }
             }
-            AppState::ResultsComplete { classroom, assignment, stats, csv_filename } => {
+            AppState::ResultsComplete {
+                classroom,
+                assignment,
+                stats,
+                mut filenames,
+                summary_filename,
+                test_stats_filename,
+                nonsubmitters,
+                nonsubmitters_filename,
+                hardest_tests,
+                warnings,
+                run_log_filename,
+                drift_error,
+                results,
+                test_definitions,
+                mut action_status,
+                mut detail,
+            } => {
                 match key.code {
                     KeyCode::Char('q') => return Ok(true),
+                    _ if detail.is_some() => {
+                        self.handle_student_detail_key(key.code, &mut detail, &results, &mut action_status);
+                    }
+                    KeyCode::Char('s') if !results.is_empty() => detail = Some(StudentDetail::default()),
                     KeyCode::Enter | KeyCode::Esc => {
                         // Go back to classroom selection
-                        self.load_classrooms().await?;
+                        self.load_classrooms();
+                        return Ok(false);
+                    }
+                    KeyCode::Char('c') => {
+                        // Copy the primary file, the one the sidecar files are named after
+                        action_status = Some(self.copy_to_clipboard(&filenames[0]));
+                    }
+                    KeyCode::Char(key @ ('j' | 'x')) => {
+                        let format = if key == 'j' { ExportFormat::Json } else { ExportFormat::Xlsx };
+                        action_status = Some(match self.export_again(&classroom, &assignment, &results, format) {
+                            Ok(path) => {
+                                let path = path.to_string_lossy().to_string();
+                                let status = format!("✓ Exported {}", path);
+                                filenames.push(path);
+                                status
+                            }
+                            Err(e) => format!("Export failed: {:#}", e),
+                        });
+                    }
+                    _ => {}
+                }
+                self.state = AppState::ResultsComplete {
+                    classroom,
+                    assignment,
+                    stats,
+                    filenames,
+                    summary_filename,
+                    test_stats_filename,
+                    nonsubmitters,
+                    nonsubmitters_filename,
+                    hardest_tests,
+                    warnings,
+                    run_log_filename,
+                    drift_error,
+                    results,
+                    test_definitions,
+                    action_status,
+                    detail,
+                };
+            }
+            AppState::AllAssignmentsComplete {
+                classroom,
+                exported,
+                skipped,
+                combined_filename,
+                run_log_filename,
+            } => match key.code {
+                KeyCode::Char('q') => return Ok(true),
+                KeyCode::Enter | KeyCode::Esc => self.load_classrooms(),
+                _ => {
+                    self.state = AppState::AllAssignmentsComplete {
+                        classroom,
+                        exported,
+                        skipped,
+                        combined_filename,
+                        run_log_filename,
+                    };
+                }
+            },
+            AppState::ReAuth {
+                message,
+                mut token_input,
+                resume,
+            } => {
+                // 'q' can appear in tokens, so only Esc leaves this screen
+                match key.code {
+                    KeyCode::Esc => {
+                        self.state = AppState::Error {
+                            message: "Re-authentication cancelled".to_string(),
+                        };
+                    }
+                    KeyCode::Char(c) => {
+                        token_input.push(c);
+                        self.state = AppState::ReAuth {
+                            message,
+                            token_input,
+                            resume,
+                        };
+                    }
+                    KeyCode::Backspace => {
+                        token_input.pop();
+                        self.state = AppState::ReAuth {
+                            message,
+                            token_input,
+                            resume,
+                        };
+                    }
+                    KeyCode::Enter if !token_input.trim().is_empty() => {
+                        let token = token_input.trim().to_string();
+                        if let Err(e) = api::check_token(&token) {
+                            self.state = AppState::ReAuth {
+                                message: e.to_string(),
+                                token_input,
+                                resume,
+                            };
+                            return Ok(false);
+                        }
+                        self.classroom_client.set_token(token.clone());
+                        self.github_client.set_token(token.clone());
+                        self.config.github_token = token;
+                        self.resume(resume).await?;
                     }
                     _ => {
-                        self.state = AppState::ResultsComplete {
-                            classroom,
-                            assignment,
-                            stats,
-                            csv_filename,
+                        self.state = AppState::ReAuth {
+                            message,
+                            token_input,
+                            resume,
                         };
                     }
                 }
@@ -744,15 +1535,69 @@ impl App {
                     KeyCode::Char('q') => return Ok(true),
                     KeyCode::Enter | KeyCode::Esc => {
                         // Go back to classroom selection
-                        self.load_classrooms().await?;
+                        self.load_classrooms();
                     }
                     _ => {
                         self.state = AppState::Error { message };
                     }
                 }
             }
+            AppState::DeviceLogin {
+                user_code,
+                verification_uri,
+            } => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
+                _ => {
+                    self.state = AppState::DeviceLogin {
+                        user_code,
+                        verification_uri,
+                    };
+                }
+            },
+            mut state @ (AppState::FetchingResults { .. }
+            | AppState::FetchingLateResults { .. }
+            | AppState::FetchingAllAssignments { .. }) => match key.code {
+                KeyCode::Char('q') => {
+                    self.cancel_background_task();
+                    return Ok(true);
+                }
+                KeyCode::Esc => {
+                    // Anything already streamed or in the run log stays on disk
+                    self.cancel_background_task();
+                    if let AppState::FetchingResults { classroom, .. }
+                    | AppState::FetchingLateResults { classroom, .. }
+                    | AppState::FetchingAllAssignments { classroom, .. } = state
+                    {
+                        // With --all-assignments, the assignment list would start the fetch again
+                        if self.config.all_assignments {
+                            self.load_classrooms();
+                        } else {
+                            self.load_assignments(classroom);
+                        }
+                    }
+                }
+                KeyCode::Char('v') => {
+                    self.student_table = !self.student_table;
+                    if let Some((_, log_scroll)) = state.fetch_log_mut() {
+                        *log_scroll = None;
+                    }
+                    self.state = state;
+                }
+                _ => {
+                    let page = self.log_page_size();
+                    if let Some((progress, log_scroll)) = state.fetch_log_mut() {
+                        let total = if self.student_table {
+                            progress.students.len()
+                        } else {
+                            progress.status_messages.len()
+                        };
+                        *log_scroll = scroll_log(key.code, *log_scroll, total, page);
+                    }
+                    self.state = state;
+                }
+            },
             state => {
-                // For other states (LoadingClassrooms, LoadingAssignments, FetchingResults),
+                // For other states (LoadingClassrooms, LoadingAssignments),
                 // just restore the state and ignore input
                 self.state = state;
             }
@@ -765,7 +1610,7 @@ impl App {
         &mut self,
         classroom: Classroom,
         assignment: Assignment,
-        deadline: Option<chrono::DateTime<Utc>>,
+        selection: RunSelection,
     ) {
         // Create progress channel
         let (progress_tx, progress_rx) = tokio::sync::mpsc::unbounded_channel();
@@ -774,19 +1619,58 @@ impl App {
         // Set initial fetching state
         let progress = FetchProgress::new(0);
         self.state = AppState::FetchingResults {
-            _classroom: classroom.clone(),
+            classroom: classroom.clone(),
             assignment: assignment.clone(),
-            _deadline: deadline,
+            selection,
             progress,
+            log_scroll: None,
         };
 
         // Clone clients for the background task
         let classroom_client = self.classroom_client.clone();
         let github_client = self.github_client.clone();
+        let config = self.config.clone();
 
         // Spawn background task
         let task = tokio::spawn(async move {
-            Self::do_fetch_results(classroom_client, github_client, classroom, assignment, deadline, progress_tx).await
+            Self::do_fetch_results(
+                classroom_client,
+                github_client,
+                classroom,
+                assignment,
+                selection,
+                config,
+                progress_tx,
+            ).await
+        });
+
+        self.background_task = Some(task);
+    }
+
+    fn spawn_fetch_all_assignments(&mut self, classroom: Classroom, assignments: Vec<Assignment>) {
+        let (progress_tx, progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        self.progress_rx = Some(progress_rx);
+
+        self.state = AppState::FetchingAllAssignments {
+            classroom: classroom.clone(),
+            assignments: assignments.clone(),
+            progress: FetchProgress::new(0),
+            log_scroll: None,
+        };
+
+        let classroom_client = self.classroom_client.clone();
+        let github_client = self.github_client.clone();
+        let config = self.config.clone();
+
+        let task = tokio::spawn(async move {
+            Self::do_fetch_all_assignments(
+                classroom_client,
+                github_client,
+                classroom,
+                assignments,
+                config,
+                progress_tx,
+            ).await
         });
 
         self.background_task = Some(task);
@@ -796,9 +1680,7 @@ impl App {
         &mut self,
         classroom: Classroom,
         assignment: Assignment,
-        on_time_deadline: chrono::DateTime<Utc>,
-        late_deadline: chrono::DateTime<Utc>,
-        late_penalty: f64,
+        deadlines: LateGradingDeadlines,
     ) {
         // Create progress channel
         let (progress_tx, progress_rx) = tokio::sync::mpsc::unbounded_channel();
@@ -807,17 +1689,17 @@ impl App {
         // Set initial fetching state
         let progress = FetchProgress::new(0);
         self.state = AppState::FetchingLateResults {
-            _classroom: classroom.clone(),
+            classroom: classroom.clone(),
             assignment: assignment.clone(),
-            _on_time_deadline: on_time_deadline,
-            _late_deadline: late_deadline,
-            _late_penalty: late_penalty,
+            deadlines: deadlines.clone(),
             progress,
+            log_scroll: None,
         };
 
         // Clone clients for the background task
         let classroom_client = self.classroom_client.clone();
         let github_client = self.github_client.clone();
+        let config = self.config.clone();
 
         // Spawn background task
         let task = tokio::spawn(async move {
@@ -826,9 +1708,8 @@ impl App {
                 github_client,
                 classroom,
                 assignment,
-                on_time_deadline,
-                late_deadline,
-                late_penalty,
+                deadlines,
+                config,
                 progress_tx,
             ).await
         });
@@ -836,107 +1717,333 @@ impl App {
         self.background_task = Some(task);
     }
 
+    /// Fetch every student's results for one assignment, reporting through `progress`
+    async fn fetch_assignment_results(
+        context: FetchContext<'_>,
+        classroom: &Classroom,
+        assignment: &Assignment,
+        selection: RunSelection,
+        config: &Config,
+        progress: &Arc<Mutex<FetchProgress>>,
+        progress_tx: &tokio::sync::mpsc::UnboundedSender<FetchProgress>,
+    ) -> Result<Vec<StudentResult>> {
+        {
+            let mut p = progress.lock().unwrap();
+            p.add_status("Fetching assignment details, students, and tests...".to_string());
+            if let Some(username) = &config.fetch.student {
+                p.add_status(format!("Grading only {}", username));
+            }
+            let _ = progress_tx.send(p.snapshot());
+        }
+
+        let roster = config.roster.roster(context.classroom_client, assignment.id, |message| {
+            let mut p = progress.lock().unwrap();
+            p.add_status(message);
+            let _ = progress_tx.send(p.snapshot());
+        }).await?;
+
+        // Pushes during the run must count as changes next time
+        let started_at = Utc::now();
+        let cache_path = std::path::Path::new(RESULTS_CACHE_FILE);
+        let previous = if config.changed_only {
+            let previous = ResultsCache::load(cache_path)?.get(assignment.id, selection).cloned();
+            if let Some(cached) = &previous {
+                let mut p = progress.lock().unwrap();
+                p.add_status(format!(
+                    "Reusing results of students who haven't pushed since {}",
+                    cached.fetched_at.with_timezone(&config.timezone).format("%Y-%m-%d %H:%M %Z")
+                ));
+                let _ = progress_tx.send(p.snapshot());
+            }
+            previous
+        } else {
+            None
+        };
+
+        let report = results_progress_callback(Arc::clone(progress), progress_tx.clone());
+        let stream = if config.export.stream {
+            let file_name = export_name(assignment, config);
+            let names = export::ExportNames {
+                assignment: &file_name,
+                classroom: &classroom.name,
+                source: Some(assignment),
+            };
+            Some(Arc::new(export::JsonLinesWriter::create(names, &config.export)?))
+        } else {
+            None
+        };
+        let on_event: fetcher::FetchCallback = match &stream {
+            Some(stream) => {
+                let stream = Arc::clone(stream);
+                Box::new(move |event| {
+                    if let FetchEvent::Finished { outcome: StudentOutcome::Graded(result), .. } = &event {
+                        stream.write(result);
+                    }
+                    report(event);
+                })
+            }
+            None => report,
+        };
+
+        let results = fetcher::fetch_all_results(
+            context,
+            assignment.id,
+            roster,
+            selection,
+            previous.as_ref().map(|cached| cached.results.as_slice()),
+            Some(on_event),
+        ).await?;
+
+        // The callback held the only other reference and was dropped with the fetch
+        let stream_path = match stream.and_then(Arc::into_inner) {
+            Some(stream) => Some(stream.finish()?),
+            None => None,
+        };
+
+        if config.changed_only {
+            ResultsCache::update(cache_path, assignment.id, selection, started_at, &results)?;
+        }
+
+        let mut p = progress.lock().unwrap();
+        p.completed = p.total_students;
+        p.add_status(format!("✓ Completed {} students", results.len()));
+        if let Some(path) = stream_path {
+            p.add_status(format!("✓ Streamed results to {}", path.display()));
+        }
+        if let Some(warning) = zero_available_warning(&results) {
+            p.warnings.push(warning);
+        }
+        if let Some(min_runs) = config.export.needs_review {
+            let flagged: Vec<_> = results
+                .iter()
+                .filter_map(|r| r.review_reason(min_runs).map(|reason| (&r.username, reason)))
+                .collect();
+            for (username, reason) in &flagged {
+                p.add_status(format!("  ⚑ {} needs review: {}", username, reason));
+            }
+            if !flagged.is_empty() {
+                p.warnings.push(format!(
+                    "⚠ {} of {} students need review (see the needs_review column)",
+                    flagged.len(),
+                    results.len()
+                ));
+            }
+        }
+        let _ = progress_tx.send(p.snapshot());
+
+        Ok(results)
+    }
+
     async fn do_fetch_results(
         classroom_client: ClassroomClient,
         github_client: GitHubClient,
         classroom: Classroom,
         assignment: Assignment,
-        deadline: Option<chrono::DateTime<Utc>>,
+        selection: RunSelection,
+        config: Config,
         progress_tx: tokio::sync::mpsc::UnboundedSender<FetchProgress>,
     ) -> Result<AppState> {
-        let mut progress = FetchProgress::new(0);
+        let progress = Arc::new(Mutex::new(FetchProgress::new(0)));
+        let file_name = export_name(&assignment, &config);
+        let names = export::ExportNames {
+            assignment: &file_name,
+            classroom: &classroom.name,
+            source: Some(&assignment),
+        };
+        progress.lock().unwrap().run_log = create_run_log(names, &config)?;
+        let context = FetchContext {
+            classroom_client: &classroom_client,
+            github_client: &github_client,
+            options: &config.fetch,
+            test_definition_cache: &TestDefinitionCache::default(),
+        };
+        let results = Self::fetch_assignment_results(
+            context,
+            &classroom,
+            &assignment,
+            selection,
+            &config,
+            &progress,
+            &progress_tx,
+        ).await?;
 
-        // Send initial progress
-        progress.add_status("Fetching assignment details...".to_string());
-        let _ = progress_tx.send(progress.clone());
+        // Export results
+        let filenames = export::export_results(&results, names, &config.export)?;
 
-        // Fetch assignment details
-        let assignment_details = classroom_client
-            .get_assignment(assignment.id)
-            .await
-            .context("Failed to fetch assignment details")?;
+        // Calculate stats
+        let mut stats = ResultStats::calculate(&results);
+        stats.errors = progress.lock().unwrap().errors;
 
-        progress.add_status("✓ Assignment details loaded".to_string());
-        progress.add_status("Fetching list of students...".to_string());
-        let _ = progress_tx.send(progress.clone());
+        let test_stats = TestStats::calculate(&results);
+        let test_stats_filename = export::export_test_stats(&test_stats, &filenames[0], config.export.decimal_separator)?;
+        let nonsubmitters = progress.lock().unwrap().nonsubmitters.len();
+        let nonsubmitters_filename = export_nonsubmitters(&progress.lock().unwrap().nonsubmitters, &filenames[0], &config)?;
 
-        // Get all accepted assignments
-        let accepted_assignments = classroom_client
-            .list_accepted_assignments(assignment.id)
-            .await
-            .context("Failed to fetch accepted assignments")?;
+        let drift_error = check_baseline(
+            &mut progress.lock().unwrap(),
+            &config,
+            results.iter().map(|r| (r.username.as_str(), r.total_awarded as f64)),
+        );
 
-        if accepted_assignments.is_empty() {
-            anyhow::bail!("No students have accepted this assignment yet");
-        }
+        let summary_filename = export::export_summary(
+            &export::RunSummary {
+                assignment_title: assignment.title.clone(),
+                assignment_slug: assignment.slug.clone(),
+                classroom: classroom.name.clone(),
+                deadline: selection.deadline(),
+                late_deadline: None,
+                graded_run: selection.name(),
+                run_timestamp: Utc::now(),
+                stats: stats.clone(),
+            },
+            &filenames[0],
+        )?;
 
-        progress.total_students = accepted_assignments.len();
-        progress.add_status(format!("✓ Found {} students", accepted_assignments.len()));
-        progress.add_status("Loading test definitions...".to_string());
-        let _ = progress_tx.send(progress.clone());
+        let mut p = progress.lock().unwrap();
+        let run_log_filename = finish_run_log(
+            &mut p,
+            filenames.iter().chain([&summary_filename, &test_stats_filename]).chain(&nonsubmitters_filename),
+            Some(&stats),
+        );
 
-        // Fetch test definitions
-        let test_definitions = if let Some(starter_url) = &assignment_details.starter_code_url {
-            fetcher::fetch_test_definitions(&github_client, starter_url).await?
-        } else {
-            let first_student = &accepted_assignments[0];
-            let (owner, repo) = fetcher::parse_repo_url(&first_student.repository.full_name);
-            let workflow_content = github_client
-                .get_file_contents(owner, repo, ".github/workflows/classroom.yml")
-                .await
-                .context("Failed to fetch workflow file from first student's repository")?;
-            parser::parse_workflow(&workflow_content)?
+        Ok(AppState::ResultsComplete {
+            classroom,
+            assignment,
+            stats,
+            filenames: filenames.iter().map(|f| f.to_string_lossy().to_string()).collect(),
+            summary_filename: summary_filename.to_string_lossy().to_string(),
+            test_stats_filename: test_stats_filename.to_string_lossy().to_string(),
+            nonsubmitters,
+            nonsubmitters_filename: nonsubmitters_filename.map(|f| f.to_string_lossy().to_string()),
+            hardest_tests: TestStats::hardest(&test_stats, 3),
+            warnings: p.warnings.clone(),
+            run_log_filename,
+            drift_error,
+            results: FetchedResults::Regular(results),
+            test_definitions: p.test_definitions.clone(),
+            action_status: None,
+            detail: None,
+        })
+    }
+
+    /// Fetch the latest results for every assignment, exporting one file per
+    /// assignment plus a combined file with one row per student
+    async fn do_fetch_all_assignments(
+        classroom_client: ClassroomClient,
+        github_client: GitHubClient,
+        classroom: Classroom,
+        assignments: Vec<Assignment>,
+        config: Config,
+        progress_tx: tokio::sync::mpsc::UnboundedSender<FetchProgress>,
+    ) -> Result<AppState> {
+        let progress = Arc::new(Mutex::new(FetchProgress::new(0)));
+        let names = export::ExportNames {
+            assignment: "all",
+            classroom: &classroom.name,
+            source: None,
+        };
+        progress.lock().unwrap().run_log = create_run_log(names, &config)?;
+        let log = |message: String| {
+            let mut p = progress.lock().unwrap();
+            p.add_status(message);
+            let _ = progress_tx.send(p.snapshot());
         };
+        let mut fetched = Vec::new();
+        let mut skipped = Vec::new();
+        // Assignments built from the same starter repo share one workflow fetch
+        let test_definition_cache = TestDefinitionCache::default();
 
-        progress.add_status(format!("✓ Loaded {} tests", test_definitions.len()));
-        progress.add_status("Fetching student results...".to_string());
-        let _ = progress_tx.send(progress.clone());
-
-        // Fetch results for each student
-        let mut results = Vec::new();
-        for (index, student) in accepted_assignments.iter().enumerate() {
-            let student_name = student
-                .students
-                .first()
-                .map(|s| s.login.as_str())
-                .unwrap_or("unknown");
-
-            progress.completed = index;
-            progress.current_student = student_name.to_string();
-            progress.add_status(format!("[{}/{}] {}", index + 1, accepted_assignments.len(), student_name));
-            let _ = progress_tx.send(progress.clone());
-
-            match fetcher::fetch_student_results(&github_client, student, deadline, &test_definitions).await {
-                Ok(result) => {
-                    results.push(result);
-                    progress.add_status(format!("  ✓ {} - {}/{} points",
-                        student_name,
-                        results.last().unwrap().total_awarded,
-                        results.last().unwrap().total_available));
+        for (index, assignment) in assignments.iter().enumerate() {
+            progress.lock().unwrap().reset_counts(0);
+            log(format!(
+                "━━ Assignment {}/{}: {}",
+                index + 1,
+                assignments.len(),
+                assignment.title
+            ));
+
+            let context = FetchContext {
+                classroom_client: &classroom_client,
+                github_client: &github_client,
+                options: &config.fetch,
+                test_definition_cache: &test_definition_cache,
+            };
+            let results = match Self::fetch_assignment_results(
+                context,
+                &classroom,
+                assignment,
+                RunSelection::Latest,
+                &config,
+                &progress,
+                &progress_tx,
+            ).await {
+                Ok(results) if !results.is_empty() => results,
+                Ok(_) => {
+                    log(format!("  – Skipped {}: no results", assignment.title));
+                    skipped.push((assignment.title.clone(), "no results".to_string()));
+                    continue;
+                }
+                Err(e) if api::is_unauthorized(&e) => return Err(e),
+                Err(e) if matches!(e.downcast_ref(), Some(FetchError::NoAcceptedAssignments)) => {
+                    log(format!("  – Skipped {}: no accepted assignments", assignment.title));
+                    skipped.push((assignment.title.clone(), "no accepted assignments".to_string()));
+                    continue;
                 }
                 Err(e) => {
-                    eprintln!("Error fetching results for {}: {}", student_name, e);
-                    progress.errors += 1;
-                    progress.add_status(format!("  ✗ {} - Error", student_name));
+                    log(format!("  ✗ Skipped {}: {}", assignment.title, e));
+                    skipped.push((assignment.title.clone(), e.to_string()));
+                    continue;
                 }
-            }
+            };
+            let nonsubmitters = std::mem::take(&mut progress.lock().unwrap().nonsubmitters);
+            fetched.push((assignment, results, nonsubmitters));
         }
 
-        progress.completed = accepted_assignments.len();
-        progress.add_status(format!("✓ Completed {} students", results.len()));
-        let _ = progress_tx.send(progress.clone());
+        if fetched.is_empty() {
+            anyhow::bail!("None of the {} assignments had results to export", assignments.len());
+        }
 
-        // Export to CSV
-        let csv_filename = export::export_to_csv(&results, &assignment.slug)?;
+        // Exported once every assignment is in, so a student gets the same
+        // pseudonym in each file and one mapping file covers them all
+        let pseudonyms = Arc::new(export::Pseudonyms::new(
+            fetched.iter().flat_map(|(_, results, _)| results.iter().map(|r| r.username.as_str())),
+        ));
+        let assignment_options = export::ExportOptions {
+            pseudonyms: Some(Arc::clone(&pseudonyms)),
+            keep_mapping: false,
+            ..config.export.clone()
+        };
+        let mut exported = Vec::new();
+        let mut all_results = Vec::new();
+        for (assignment, results, nonsubmitters) in fetched {
+            let file_name = export_name(assignment, &config);
+            let names = export::ExportNames {
+                assignment: &file_name,
+                classroom: &classroom.name,
+                source: Some(assignment),
+            };
+            let filenames = export::export_results(&results, names, &assignment_options)?;
+            export::export_test_stats(&TestStats::calculate(&results), &filenames[0], config.export.decimal_separator)?;
+            export_nonsubmitters(&nonsubmitters, &filenames[0], &config)?;
+            log(format!("  ✓ Exported {}", display_paths(&filenames)));
 
-        // Calculate stats
-        let stats = ResultStats::calculate(&results);
+            exported.push((assignment.title.clone(), display_paths(&filenames)));
+            all_results.push((assignment.slug.clone(), results));
+        }
 
-        Ok(AppState::ResultsComplete {
+        let combined_options = export::ExportOptions {
+            pseudonyms: Some(pseudonyms),
+            ..config.export.clone()
+        };
+        let combined_filename = export::export_combined(&all_results, &classroom.name, &combined_options)?;
+        let run_log_filename = finish_run_log(&mut progress.lock().unwrap(), &combined_filename, None);
+
+        Ok(AppState::AllAssignmentsComplete {
             classroom,
-            assignment,
-            stats,
-            csv_filename: csv_filename.to_string_lossy().to_string(),
+            exported,
+            skipped,
+            combined_filename: display_paths(&combined_filename),
+            run_log_filename,
         })
     }
 
@@ -945,67 +2052,640 @@ impl App {
         github_client: GitHubClient,
         classroom: Classroom,
         assignment: Assignment,
-        on_time_deadline: chrono::DateTime<Utc>,
-        late_deadline: chrono::DateTime<Utc>,
-        late_penalty: f64,
+        deadlines: LateGradingDeadlines,
+        config: Config,
         progress_tx: tokio::sync::mpsc::UnboundedSender<FetchProgress>,
     ) -> Result<AppState> {
-        let mut progress = FetchProgress::new(0);
+        // Shared with the progress callback so the status log keeps its full history
+        let progress = Arc::new(Mutex::new(FetchProgress::new(0)));
+        let file_name = export_name(&assignment, &config);
+        let names = export::ExportNames {
+            assignment: &file_name,
+            classroom: &classroom.name,
+            source: Some(&assignment),
+        };
+        progress.lock().unwrap().run_log = create_run_log(names, &config)?;
 
         // Send initial progress
-        progress.add_status("Starting late grading fetch...".to_string());
-        let _ = progress_tx.send(progress.clone());
-
-        // Create progress callback that sends through the channel
-        let progress_tx_clone = progress_tx.clone();
-        let progress_callback = Box::new(move |completed: usize, total: usize, student: &str| {
-            let mut p = FetchProgress::new(total);
-            p.completed = completed.saturating_sub(1);
-            p.total_students = total;
-            p.current_student = student.to_string();
-            p.add_status(format!("[{}/{}] {}", completed, total, student));
-            let _ = progress_tx_clone.send(p);
-        });
+        {
+            let mut p = progress.lock().unwrap();
+            p.add_status("Starting late grading fetch...".to_string());
+            let _ = progress_tx.send(p.snapshot());
+        }
+
+        let roster = config.roster.roster(&classroom_client, assignment.id, |message| {
+            let mut p = progress.lock().unwrap();
+            p.add_status(message);
+            let _ = progress_tx.send(p.snapshot());
+        }).await?;
+        let on_event = results_progress_callback(Arc::clone(&progress), progress_tx.clone());
 
         // Fetch late grading results
-        let results = fetcher::fetch_all_late_results(
-            &classroom_client,
-            &github_client,
-            assignment.id,
-            on_time_deadline,
-            late_deadline,
-            late_penalty,
-            Some(progress_callback),
-        ).await?;
+        let context = FetchContext {
+            classroom_client: &classroom_client,
+            github_client: &github_client,
+            options: &config.fetch,
+            test_definition_cache: &TestDefinitionCache::default(),
+        };
+        let results = fetcher::fetch_all_late_results(context, assignment.id, roster, &deadlines, Some(on_event)).await?;
 
-        progress.completed = progress.total_students;
-        progress.add_status(format!("✓ Completed {} students", results.len()));
-        let _ = progress_tx.send(progress.clone());
+        let errors = {
+            let mut p = progress.lock().unwrap();
+            p.completed = p.total_students;
+            p.add_status(format!("✓ Completed {} students", results.len()));
+            let _ = progress_tx.send(p.snapshot());
+            p.errors
+        };
 
-        // Export to CSV
-        let csv_filename = export::export_late_grading_to_csv(&results, &assignment.slug)?;
+        // Export results
+        let filenames = export::export_late_grading(&results, names, &config.export)?;
 
         // Calculate stats (using on-time results)
         let regular_results: Vec<_> = results.iter().map(|r| r.on_time_result.clone()).collect();
-        let stats = ResultStats::calculate(&regular_results);
+        let mut stats = ResultStats::calculate(&regular_results);
+        stats.errors = errors;
+        if let Some(warning) = zero_available_warning(&regular_results) {
+            progress.lock().unwrap().warnings.push(warning);
+        }
+
+        let test_stats = TestStats::calculate(&regular_results);
+        let test_stats_filename = export::export_test_stats(&test_stats, &filenames[0], config.export.decimal_separator)?;
+        let nonsubmitters = progress.lock().unwrap().nonsubmitters.len();
+        let nonsubmitters_filename = export_nonsubmitters(&progress.lock().unwrap().nonsubmitters, &filenames[0], &config)?;
+
+        let drift_error = check_baseline(
+            &mut progress.lock().unwrap(),
+            &config,
+            results.iter().map(|r| (r.username.as_str(), r.final_score as f64)),
+        );
+
+        let summary_filename = export::export_summary(
+            &export::RunSummary {
+                assignment_title: assignment.title.clone(),
+                assignment_slug: assignment.slug.clone(),
+                classroom: classroom.name.clone(),
+                deadline: Some(deadlines.on_time_deadline),
+                late_deadline: Some(deadlines.late_deadline),
+                graded_run: RunSelection::FirstAfter(deadlines.on_time_deadline).name(),
+                run_timestamp: Utc::now(),
+                stats: stats.clone(),
+            },
+            &filenames[0],
+        )?;
+
+        let mut p = progress.lock().unwrap();
+        let run_log_filename = finish_run_log(
+            &mut p,
+            filenames.iter().chain([&summary_filename, &test_stats_filename]).chain(&nonsubmitters_filename),
+            Some(&stats),
+        );
 
         Ok(AppState::ResultsComplete {
             classroom,
             assignment,
             stats,
-            csv_filename: csv_filename.to_string_lossy().to_string(),
+            filenames: filenames.iter().map(|f| f.to_string_lossy().to_string()).collect(),
+            summary_filename: summary_filename.to_string_lossy().to_string(),
+            test_stats_filename: test_stats_filename.to_string_lossy().to_string(),
+            nonsubmitters,
+            nonsubmitters_filename: nonsubmitters_filename.map(|f| f.to_string_lossy().to_string()),
+            hardest_tests: TestStats::hardest(&test_stats, 3),
+            warnings: p.warnings.clone(),
+            run_log_filename,
+            drift_error,
+            results: FetchedResults::Late(results),
+            test_definitions: p.test_definitions.clone(),
+            action_status: None,
+            detail: None,
         })
     }
 
 }
 
-fn parse_deadline(date_str: &str, time_str: &str) -> Result<chrono::DateTime<Utc>> {
-    let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
-        .map_err(|e| anyhow::anyhow!("Invalid date format (expected YYYY-MM-DD): {}", e))?;
+/// Flag a student whose points couldn't be read exactly, and warn once on the
+/// completion screen if the token can't read Actions logs at all
+fn report_points_source(p: &mut FetchProgress, student: &str, points_source: PointsSource) {
+    if let PointsSource::Approximate(problem) = points_source {
+        p.add_status(format!("  ⚠ {} - points approximate ({}); failed tests may have lost partial credit", student, problem));
+    }
+    if points_source.logs_problem() == Some(LogsProblem::Forbidden) && !p.warnings.iter().any(|w| w == LOGS_FORBIDDEN_WARNING) {
+        p.add_status(LOGS_FORBIDDEN_WARNING.to_string());
+        p.warnings.push(LOGS_FORBIDDEN_WARNING.to_string());
+    }
+}
+
+/// Start the run log, unless the export is anonymized since the log names
+/// every student
+fn create_run_log(names: export::ExportNames, config: &Config) -> Result<Option<Arc<export::RunLog>>> {
+    if config.export.anonymize {
+        return Ok(None);
+    }
+    Ok(Some(Arc::new(export::RunLog::create(names, &config.export)?)))
+}
+
+/// Write the students who haven't submitted next to the results, unless the
+/// export is anonymized since the list is for contacting them
+fn export_nonsubmitters(
+    nonsubmitters: &[NonSubmitter],
+    results_path: &std::path::Path,
+    config: &Config,
+) -> Result<Option<std::path::PathBuf>> {
+    if config.export.anonymize {
+        return Ok(None);
+    }
+    export::export_nonsubmitters(nonsubmitters, results_path, config.export.decimal_separator).map(Some)
+}
+
+/// Compare each student's total with the `--baseline` results file, listing
+/// the students whose total changed in the status log. Returns why the run
+/// should fail if more changed than `--max-drift` allows or the baseline
+/// couldn't be read; either also becomes a warning.
+fn check_baseline<'a>(
+    p: &mut FetchProgress,
+    config: &Config,
+    totals: impl IntoIterator<Item = (&'a str, f64)>,
+) -> Option<String> {
+    let path = config.baseline.as_ref()?;
+    let error = match export::baseline_drift(path, config.export.column_mapping.as_ref(), totals) {
+        Ok(drift) => {
+            let show = |score: Option<f64>| score.map_or("no result".to_string(), |score| score.to_string());
+            for d in &drift {
+                p.add_status(format!("  ⚠ {} - {} in the baseline, {} now", d.username, show(d.baseline), show(d.current)));
+            }
+            if drift.is_empty() {
+                p.add_status(format!("✓ Every total matches the baseline {}", path.display()));
+                return None;
+            }
+            if drift.len() <= config.max_drift {
+                p.add_status(format!("– {} students' totals differ from the baseline, within --max-drift", drift.len()));
+                return None;
+            }
+            format!(
+                "{} students' totals differ from the baseline {} (at most {} allowed)",
+                drift.len(),
+                path.display(),
+                config.max_drift
+            )
+        }
+        Err(e) => format!("Couldn't compare with the baseline: {:#}", e),
+    };
+    let warning = format!("⚠ {}", error);
+    p.add_status(warning.clone());
+    p.warnings.push(warning);
+    Some(error)
+}
 
-    let time = NaiveTime::parse_from_str(time_str, "%H:%M")
-        .map_err(|e| anyhow::anyhow!("Invalid time format (expected HH:MM): {}", e))?;
+/// Record the exported files and final stats in the run log and close it,
+/// returning its path for the completion screen. A log that couldn't be
+/// written in full becomes a warning.
+fn finish_run_log<'a>(
+    p: &mut FetchProgress,
+    files: impl IntoIterator<Item = &'a std::path::PathBuf>,
+    stats: Option<&ResultStats>,
+) -> Option<String> {
+    let run_log = p.run_log.take()?;
+    for file in files {
+        run_log.write(&format!("✓ Exported {}", file.display()));
+    }
+    if let Some(stats) = stats {
+        run_log.write(&format!(
+            "Final stats: {} students, {} tests each, average {}, median {}, {} errors",
+            stats.total_students,
+            stats.total_tests,
+            ResultStats::format_score(stats.average_score),
+            ResultStats::format_score(stats.median_score),
+            stats.errors
+        ));
+    }
+    for warning in &p.warnings {
+        run_log.write(&format!("Warning: {}", warning));
+    }
+    match run_log.finish() {
+        Ok(path) => Some(path.display().to_string()),
+        Err(e) => {
+            p.warnings.push(format!("⚠ The run log is incomplete: {:#}", e));
+            None
+        }
+    }
+}
 
-    let datetime = NaiveDateTime::new(date, time);
-    Ok(datetime.and_utc())
+/// Progress callback for regular and late grading, mirroring each student's
+/// outcome into the status log
+fn results_progress_callback(
+    progress: Arc<Mutex<FetchProgress>>,
+    progress_tx: tokio::sync::mpsc::UnboundedSender<FetchProgress>,
+) -> fetcher::FetchCallback {
+    Box::new(move |event| {
+        let mut p = progress.lock().unwrap();
+        match event {
+            FetchEvent::Loaded {
+                students,
+                usernames,
+                tests,
+                excluded,
+                skipped_passing,
+                over_cap,
+            } => {
+                p.total_students = students;
+                p.students = usernames
+                    .into_iter()
+                    .map(|username| (username.to_string(), StudentStatus::Pending))
+                    .collect();
+                if excluded > 0 {
+                    p.add_status(format!("– Excluded {} students on the exclude list", excluded));
+                }
+                if skipped_passing > 0 {
+                    p.add_status(format!("– Skipped {} students who are already passing", skipped_passing));
+                }
+                if over_cap > 0 {
+                    p.add_status(format!("– Capped at the first {} students, leaving out {}", students, over_cap));
+                }
+                p.add_status(format!("✓ Found {} students", students));
+                p.test_definitions = tests.to_vec();
+                p.add_status(format!("✓ Loaded {} tests", tests.len()));
+                p.add_status("Fetching student results...".to_string());
+            }
+            FetchEvent::Started { index, total, student } => {
+                p.active += 1;
+                p.current_student = student.to_string();
+                if let Some(status) = p.students.get_mut(student) {
+                    *status = StudentStatus::Fetching;
+                }
+                p.add_status(format!("[{}/{}] {}", index + 1, total, student));
+            }
+            FetchEvent::Finished {
+                completed,
+                total,
+                student,
+                outcome,
+            } => {
+                p.active = p.active.saturating_sub(1);
+                p.completed = completed;
+                p.total_students = total;
+                if let Some(status) = p.students.get_mut(student) {
+                    *status = StudentStatus::from(&outcome);
+                }
+                match outcome {
+                    StudentOutcome::Graded(result) => {
+                        if result.run_interrupted() {
+                            p.add_status(format!("  ⚠ {} - graded run ended as {}; tests that didn't run score 0",
+                                student,
+                                result.run_conclusion));
+                        }
+                        let missing = result.missing_tests();
+                        if !missing.is_empty() {
+                            p.add_status(format!("  ⚠ {} - missing test step(s): {}",
+                                student,
+                                missing.join(", ")));
+                        }
+                        report_points_source(&mut p, student, result.points_source);
+                        p.add_status(format!("  ✓ {} - {}/{} points",
+                            student,
+                            result.total_awarded,
+                            result.total_available));
+                    }
+                    StudentOutcome::LateGraded(result) => {
+                        for (run, graded) in [("on-time", &result.on_time_result), ("late", &result.late_result)] {
+                            let missing = graded.missing_tests();
+                            if !missing.is_empty() {
+                                p.add_status(format!("  ⚠ {} - missing test step(s) in the {} run: {}",
+                                    student,
+                                    run,
+                                    missing.join(", ")));
+                            }
+                        }
+                        report_points_source(&mut p, student, result.on_time_result.points_source);
+                        report_points_source(&mut p, student, result.late_result.points_source);
+                        p.add_status(format!("  ✓ {} - on time {}, late {}, final {}/{} points",
+                            student,
+                            result.on_time_result.total_awarded,
+                            result.late_result.total_awarded,
+                            result.final_score,
+                            result.on_time_result.total_available));
+                    }
+                    StudentOutcome::InProgress => {
+                        p.in_progress += 1;
+                        p.add_status(format!("  ⏳ {} - Grading in progress", student));
+                    }
+                    StudentOutcome::NotSubmitted { repo_url } => {
+                        p.nonsubmitters.push(NonSubmitter {
+                            username: student.to_string(),
+                            repo_url: repo_url.to_string(),
+                        });
+                        p.add_status(format!("  – {} - Not submitted", student));
+                    }
+                    StudentOutcome::Failed(e) => {
+                        eprintln!("Error fetching results for {}: {}", student, e);
+                        p.errors += 1;
+                        p.failures.push(StudentFailure {
+                            student: student.to_string(),
+                            error: e.to_string(),
+                        });
+                        match e {
+                            FetchError::RunInterrupted { conclusion, .. } => {
+                                p.add_status(format!("  ⚠ {} - graded run ended as {}; needs manual review", student, conclusion));
+                            }
+                            _ => p.add_status(format!("  ✗ {} - Error", student)),
+                        }
+                        p.record(&format!("    {}", e));
+                    }
+                }
+            }
+            FetchEvent::PointsMismatch { workflow, classroom } => {
+                let warning = points_mismatch_warning(workflow, classroom);
+                p.add_status(warning.clone());
+                p.warnings.push(warning);
+            }
+            FetchEvent::Unchanged { student } => {
+                p.add_status(format!("  = {} - no pushes since the last run, reusing its result", student));
+            }
+            FetchEvent::WorkflowDrift { student, missing, unknown } => {
+                let mut differences = Vec::new();
+                if !missing.is_empty() {
+                    differences.push(format!("not run: {}", missing.join(", ")));
+                }
+                if !unknown.is_empty() {
+                    differences.push(format!("not in the starter: {}", unknown.join(", ")));
+                }
+                let warning = format!(
+                    "⚠ The starter repo's workflow doesn't match {}'s graded run ({}); students may be running a different workflow",
+                    student,
+                    differences.join("; ")
+                );
+                p.add_status(warning.clone());
+                p.warnings.push(warning);
+            }
+            FetchEvent::WorkflowNotUtf8 { workflow } => {
+                let warning = format!(
+                    "⚠ {} isn't valid UTF-8; its invalid bytes were replaced, so check the test names",
+                    workflow
+                );
+                p.add_status(warning.clone());
+                p.warnings.push(warning);
+            }
+            FetchEvent::DeletedAccounts { repos } => {
+                let warning = format!(
+                    "⚠ {} students deleted their GitHub accounts; their rows are named after their repositories \
+                     and need to be attributed by hand: {}",
+                    repos.len(),
+                    repos.join(", ")
+                );
+                p.add_status(warning.clone());
+                p.warnings.push(warning);
+            }
+            FetchEvent::SecondaryRateLimited { concurrency: 1 } => {
+                p.add_status("  ⚠ Hit GitHub abuse detection, backing off".to_string());
+            }
+            FetchEvent::SecondaryRateLimited { concurrency } => {
+                p.add_status(format!(
+                    "  ⚠ Hit GitHub abuse detection, reducing concurrency to {}",
+                    concurrency
+                ));
+            }
+        }
+        let _ = progress_tx.send(p.snapshot());
+    })
+}
+
+/// Move a list selection by a page or to either end
+fn page_selection(key: KeyCode, selected: usize, len: usize, page: usize) -> usize {
+    let last = len.saturating_sub(1);
+    match key {
+        KeyCode::PageUp => selected.saturating_sub(page),
+        KeyCode::PageDown => (selected + page).min(last),
+        KeyCode::Home => 0,
+        KeyCode::End => last,
+        _ => selected,
+    }
+}
+
+/// Scroll the status log by a page; scrolling to the bottom resumes following new messages
+fn scroll_log(key: KeyCode, scroll: Option<usize>, total: usize, page: usize) -> Option<usize> {
+    let bottom = total.saturating_sub(page);
+    let current = scroll.unwrap_or(bottom);
+    match key {
+        KeyCode::PageUp | KeyCode::Home if bottom == 0 => None,
+        KeyCode::PageUp => Some(current.saturating_sub(page)),
+        KeyCode::PageDown if current + page >= bottom => None,
+        KeyCode::PageDown => Some(current + page),
+        KeyCode::Home => Some(0),
+        KeyCode::End => None,
+        _ => scroll,
+    }
+}
+
+/// Name used in export file names: the assignment slug, plus the username when grading one student
+fn export_name(assignment: &Assignment, config: &Config) -> String {
+    match &config.fetch.student {
+        Some(username) => format!("{}_{}", assignment.slug, username),
+        None => assignment.slug.clone(),
+    }
+}
+
+/// Open a page in the default browser. Failures are ignored, since the URL is
+/// on screen to copy by hand.
+fn open_in_browser(url: &str) {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+
+    // Keep the opener's output from drawing over the terminal UI
+    let _ = command
+        .arg(url)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+}
+
+/// Exported file paths for display, comma-separated
+fn display_paths(paths: &[std::path::PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Deadlines entered for this assignment on earlier runs, or none if the file
+/// of saved deadlines can't be read
+fn saved_deadlines(assignment: &Assignment) -> AssignmentDeadlines {
+    SavedDeadlines::load(std::path::Path::new(SAVED_DEADLINES_FILE))
+        .map(|saved| saved.get(assignment.id))
+        .unwrap_or_default()
+}
+
+/// Remember deadlines for the next run of this assignment. Failures are
+/// ignored, since the deadlines can always be typed again.
+fn remember_deadlines(assignment: &Assignment, change: impl FnOnce(&mut AssignmentDeadlines)) {
+    let _ = SavedDeadlines::update(std::path::Path::new(SAVED_DEADLINES_FILE), assignment.id, change);
+}
+
+/// Date and time inputs pre-filled with `deadline` in `timezone`, blank if there's none
+fn deadline_inputs(deadline: Option<chrono::DateTime<Utc>>, timezone: Tz) -> (String, String) {
+    match deadline {
+        Some(deadline) => {
+            let local = deadline.with_timezone(&timezone);
+            (
+                local.format("%Y-%m-%d").to_string(),
+                local.format("%H:%M").to_string(),
+            )
+        }
+        None => (String::new(), String::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{TestDefinition, TestStatus};
+
+    #[test]
+    fn test_late_progress_callback() {
+        let progress = Arc::new(Mutex::new(FetchProgress::new(0)));
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let callback = results_progress_callback(Arc::clone(&progress), progress_tx);
+
+        let result = |awarded: u32| StudentResult {
+            total_awarded: awarded,
+            ..StudentResult::sample("amy")
+        };
+        let late = crate::models::LateGradingResult::new(result(6), result(8), 0.5);
+
+        // Late grading fetches one student at a time, so each finishes before the next starts
+        for (index, student) in ["amy", "bob"].iter().enumerate() {
+            callback(FetchEvent::Started { index, total: 2, student });
+            callback(FetchEvent::Finished {
+                completed: index + 1,
+                total: 2,
+                student,
+                outcome: StudentOutcome::LateGraded(&late),
+            });
+        }
+
+        let updates: Vec<FetchProgress> = std::iter::from_fn(|| progress_rx.try_recv().ok()).collect();
+        let completed: Vec<usize> = updates.iter().map(|p| p.completed).collect();
+        assert_eq!(completed, [0, 1, 1, 2]);
+
+        let last = updates.last().unwrap();
+        assert_eq!((last.total_students, last.active), (2, 0));
+        assert_eq!(last.current_student, "bob");
+        assert_eq!(last.status_messages.last().unwrap(), "  ✓ bob - on time 6, late 8, final 7/10 points");
+        // History is shared across updates rather than reset each call
+        assert_eq!(progress.lock().unwrap().status_messages.len(), 5);
+    }
+
+    #[test]
+    fn test_late_progress_reports_missing_steps() {
+        let progress = Arc::new(Mutex::new(FetchProgress::new(0)));
+        let (progress_tx, _progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let callback = results_progress_callback(Arc::clone(&progress), progress_tx);
+
+        let test = |status| crate::models::TestResult {
+            _name: String::new(),
+            points_awarded: 0,
+            points_available: 5,
+            status,
+            failure_message: None,
+        };
+        let on_time = StudentResult {
+            tests: [("test_1".to_string(), test(TestStatus::Failed))].into_iter().collect(),
+            ..StudentResult::sample("amy")
+        };
+        let late = StudentResult {
+            tests: [("test_1".to_string(), test(TestStatus::Missing))].into_iter().collect(),
+            ..StudentResult::sample("amy")
+        };
+        let late = crate::models::LateGradingResult::new(on_time, late, 0.5);
+
+        callback(FetchEvent::Finished {
+            completed: 1,
+            total: 1,
+            student: "amy",
+            outcome: StudentOutcome::LateGraded(&late),
+        });
+
+        let p = progress.lock().unwrap();
+        assert!(p.status_messages.contains(&"  ⚠ amy - missing test step(s) in the late run: test_1".to_string()));
+        assert!(!p.status_messages.iter().any(|m| m.contains("on-time run")));
+    }
+
+    #[test]
+    fn test_results_progress_callback() {
+        let progress = Arc::new(Mutex::new(FetchProgress::new(0)));
+        let (progress_tx, _progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let callback = results_progress_callback(Arc::clone(&progress), progress_tx);
+
+        let graded = StudentResult {
+            total_awarded: 8,
+            ..StudentResult::sample("amy")
+        };
+        let error = FetchError::NotFound { url: "https://api.github.com/repos/org/hw1-cat".to_string() };
+        let tests: Vec<TestDefinition> = ["test_a", "test_b"]
+            .into_iter()
+            .map(|name| TestDefinition {
+                name: name.to_string(),
+                id: name.to_string(),
+                max_score: 5,
+            })
+            .collect();
+
+        callback(FetchEvent::Loaded {
+            students: 4,
+            usernames: vec!["amy", "bob", "cat", "dan"],
+            tests: &tests,
+            excluded: 0,
+            skipped_passing: 0,
+            over_cap: 0,
+        });
+        for (index, student) in ["amy", "bob", "cat", "dan"].iter().enumerate() {
+            callback(FetchEvent::Started { index, total: 4, student });
+        }
+        assert_eq!(progress.lock().unwrap().active, 4);
+
+        let outcomes = [
+            ("bob", StudentOutcome::InProgress),
+            ("amy", StudentOutcome::Graded(&graded)),
+            ("cat", StudentOutcome::Failed(&error)),
+            ("dan", StudentOutcome::NotSubmitted { repo_url: "https://github.com/org/hw1-dan" }),
+        ];
+        for (completed, (student, outcome)) in outcomes.into_iter().enumerate() {
+            callback(FetchEvent::Finished {
+                completed: completed + 1,
+                total: 4,
+                student,
+                outcome,
+            });
+        }
+
+        let p = progress.lock().unwrap();
+        assert_eq!((p.completed, p.total_students, p.active), (4, 4, 0));
+        assert_eq!((p.in_progress, p.errors), (1, 1));
+        assert_eq!(p.failures[0].student, "cat");
+        assert_eq!(p.failures[0].error, error.to_string());
+        assert_eq!(p.nonsubmitters.len(), 1);
+        assert_eq!(p.nonsubmitters[0].repo_url, "https://github.com/org/hw1-dan");
+        assert!(p.status_messages.contains(&"  ✓ amy - 8/10 points".to_string()));
+        assert_eq!(p.students["amy"], StudentStatus::Graded { awarded: 8, available: 10 });
+        assert_eq!(p.students["bob"], StudentStatus::InProgress);
+        assert_eq!(p.students["dan"], StudentStatus::NotSubmitted);
+        assert_eq!(p.test_definitions.len(), tests.len());
+        assert_eq!(p.test_definitions[1].name, "test_b");
+    }
+
+    #[test]
+    fn test_parse_deadline_in_timezone() {
+        let deadline = parse_deadline("2025-03-01", "23:59", chrono_tz::America::New_York).unwrap();
+        assert_eq!(deadline.to_rfc3339(), "2025-03-02T04:59:00+00:00");
+
+        // 02:30 is skipped when New York springs forward
+        assert!(parse_deadline("2025-03-09", "02:30", chrono_tz::America::New_York).is_err());
+        assert!(parse_deadline("2025-03-01", "noon", Tz::UTC).is_err());
+    }
 }
@@ -1,5 +1,7 @@
 mod app;
 mod render;
 mod state;
+mod theme;
 
 pub use app::App;
+pub use theme::Theme;
@@ -1,46 +1,74 @@
-use crate::ui::state::{AppState, DeadlineField, LateGradingField};
+use crate::models::{ApiStatus, TestStatus};
+use crate::ui::state::{
+    deadline_warning, parse_deadline, visible_classrooms, AppState, DeadlineField, FetchedResults, JobLog,
+    LateGradingField, StudentDetail, StudentStatus,
+};
+use crate::ui::theme::Theme;
+use chrono::Utc;
+use chrono_tz::Tz;
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, Cell, Gauge, List, ListItem, ListState, Paragraph, Row, Table, Wrap},
     Frame,
 };
 
-pub fn render_ui(frame: &mut Frame, state: &AppState, spinner: char) {
+pub fn render_ui(
+    frame: &mut Frame,
+    state: &AppState,
+    spinner: char,
+    api_status: ApiStatus,
+    timezone: Tz,
+    theme: &Theme,
+    student_table: bool,
+) {
     match state {
-        AppState::LoadingClassrooms => render_loading(frame, "Loading classrooms...", spinner),
+        AppState::LoadingClassrooms => render_loading(frame, theme, "Loading classrooms...", spinner),
         AppState::ClassroomSelection {
             classrooms,
             selected_index,
-        } => render_classroom_selection(frame, classrooms, *selected_index),
+            show_archived,
+        } => render_classroom_selection(frame, theme, classrooms, *selected_index, *show_archived),
         AppState::LoadingAssignments { classroom } => {
-            render_loading(frame, &format!("Loading assignments for {}...", classroom.name), spinner)
+            render_loading(frame, theme, &format!("Loading assignments for {}...", classroom.name), spinner)
         }
         AppState::AssignmentSelection {
             classroom,
             assignments,
             selected_index,
-        } => render_assignment_selection(frame, classroom, assignments, *selected_index),
+        } => render_assignment_selection(frame, theme, classroom, assignments, *selected_index),
         AppState::AssignmentOptions {
             classroom,
             assignment,
             selected_index,
-        } => render_assignment_options(frame, classroom, assignment, *selected_index),
+            student,
+            notice,
+        } => render_assignment_options(frame, theme, classroom, assignment, *selected_index, student.as_deref(), notice.as_deref()),
+        AppState::StudentInput {
+            assignment,
+            username_input,
+            ..
+        } => render_student_input(frame, theme, assignment, username_input),
+        AppState::TestDefinitionsPreview {
+            assignment,
+            test_definitions,
+            points_warning,
+            ..
+        } => render_test_definitions_preview(frame, theme, assignment, test_definitions, points_warning.as_deref()),
         AppState::GradingModeSelection {
             classroom,
             assignment,
             selected_index,
-        } => render_grading_mode_selection(frame, classroom, assignment, *selected_index),
+        } => render_grading_mode_selection(frame, theme, classroom, assignment, *selected_index),
         AppState::DeadlineInput {
-            classroom,
             assignment,
             date_input,
             time_input,
             focused_field,
-        } => render_deadline_input(frame, classroom, assignment, date_input, time_input, *focused_field),
+            ..
+        } => render_deadline_input(frame, theme, assignment, date_input, time_input, *focused_field, timezone),
         AppState::LateGradingInput {
-            classroom,
             assignment,
             on_time_date,
             on_time_time,
@@ -48,43 +76,140 @@ pub fn render_ui(frame: &mut Frame, state: &AppState, spinner: char) {
             late_time,
             penalty_input,
             focused_field,
+            ..
         } => render_late_grading_input(
             frame,
-            classroom,
+            theme,
             assignment,
-            on_time_date,
-            on_time_time,
-            late_date,
-            late_time,
-            penalty_input,
-            *focused_field,
+            LateGradingForm {
+                on_time_date,
+                on_time_time,
+                late_date,
+                late_time,
+                penalty_input,
+                focused_field: *focused_field,
+            },
+            timezone,
         ),
         AppState::FetchingResults {
             assignment,
             progress,
+            log_scroll,
             ..
-        } => render_fetching_results(frame, assignment, progress, spinner),
+        } => render_fetching_results(
+            frame,
+            theme,
+            FetchingView {
+                title: &assignment.title,
+                progress,
+                log_scroll: *log_scroll,
+            },
+            student_table,
+            spinner,
+            api_status,
+        ),
         AppState::FetchingLateResults {
             assignment,
             progress,
+            log_scroll,
+            ..
+        } => render_fetching_results(
+            frame,
+            theme,
+            FetchingView {
+                title: &assignment.title,
+                progress,
+                log_scroll: *log_scroll,
+            },
+            student_table,
+            spinner,
+            api_status,
+        ),
+        AppState::FetchingAllAssignments {
+            classroom,
+            progress,
+            log_scroll,
+            ..
+        } => render_fetching_results(
+            frame,
+            theme,
+            FetchingView {
+                title: &format!("All Assignments in {}", classroom.name),
+                progress,
+                log_scroll: *log_scroll,
+            },
+            student_table,
+            spinner,
+            api_status,
+        ),
+        AppState::ResultsComplete {
+            detail: Some(StudentDetail { log: Some(log), .. }),
+            ..
+        } => render_job_log(frame, theme, log, spinner),
+        AppState::ResultsComplete {
+            results,
+            detail: Some(detail),
+            action_status,
             ..
-        } => render_fetching_results(frame, assignment, progress, spinner),
+        } => render_student_detail(frame, theme, timezone, results, detail, action_status.as_deref()),
         AppState::ResultsComplete {
             assignment,
             stats,
-            csv_filename,
+            filenames,
+            summary_filename,
+            test_stats_filename,
+            nonsubmitters,
+            nonsubmitters_filename,
+            hardest_tests,
+            warnings,
+            run_log_filename,
+            action_status,
             ..
-        } => render_results_complete(frame, assignment, stats, csv_filename),
-        AppState::Error { message } => render_error(frame, message),
+        } => render_results_complete(
+            frame,
+            theme,
+            assignment,
+            CompletionView {
+                stats,
+                filenames,
+                summary_filename,
+                test_stats_filename,
+                nonsubmitters: *nonsubmitters,
+                nonsubmitters_filename: nonsubmitters_filename.as_deref(),
+                hardest_tests,
+                warnings,
+                run_log_filename: run_log_filename.as_deref(),
+                action_status: action_status.as_deref(),
+            },
+        ),
+        AppState::AllAssignmentsComplete {
+            classroom,
+            exported,
+            skipped,
+            combined_filename,
+            run_log_filename,
+        } => render_all_assignments_complete(frame, theme, classroom, exported, skipped, combined_filename, run_log_filename.as_deref()),
+        AppState::ReAuth {
+            message,
+            token_input,
+            ..
+        } => render_reauth(frame, theme, message, token_input),
+        AppState::DeviceLogin {
+            user_code,
+            verification_uri,
+        } => render_device_login(frame, theme, user_code, verification_uri, spinner),
+        AppState::LoggedIn { .. } => render_loading(frame, theme, "Logged in, loading classrooms...", spinner),
+        AppState::JobLogLoaded { .. } => render_loading(frame, theme, "Loading the log...", spinner),
+        AppState::Error { message } => render_error(frame, theme, message),
     }
 }
 
-fn render_loading(frame: &mut Frame, message: &str, spinner: char) {
+fn render_loading(frame: &mut Frame, theme: &Theme, message: &str, spinner: char) {
     let area = frame.area();
     let block = Block::default()
         .title("GitHub Classroom Autograder Fetcher")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(theme.primary));
 
     let loading_text = format!("{} {}", spinner, message);
     let paragraph = Paragraph::new(loading_text)
@@ -97,8 +222,10 @@ fn render_loading(frame: &mut Frame, message: &str, spinner: char) {
 
 fn render_classroom_selection(
     frame: &mut Frame,
+    theme: &Theme,
     classrooms: &[crate::models::Classroom],
     selected_index: usize,
+    show_archived: bool,
 ) {
     let area = frame.area();
 
@@ -107,12 +234,15 @@ fn render_classroom_selection(
         .constraints([Constraint::Min(3), Constraint::Length(3)])
         .split(area);
 
-    let items: Vec<ListItem> = classrooms
+    let visible = visible_classrooms(classrooms, show_archived);
+    let hidden = classrooms.len() - visible.len();
+
+    let items: Vec<ListItem> = visible
         .iter()
         .enumerate()
         .map(|(i, classroom)| {
             let style = if i == selected_index {
-                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)
             } else {
                 Style::default()
             };
@@ -125,19 +255,32 @@ fn render_classroom_selection(
         })
         .collect();
 
+    // Only classrooms listed with --org have their organization
+    let title = match classrooms.first().and_then(|c| c.organization.as_ref()) {
+        Some(org) => format!("Select Classroom in {}", org.login),
+        None => "Select Classroom".to_string(),
+    };
     let list = List::new(items)
         .block(
             Block::default()
-                .title("Select Classroom")
+                .title(title)
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)),
+                .border_style(Style::default().fg(theme.primary)),
         );
 
-    frame.render_widget(list, chunks[0]);
+    // Stateful rendering keeps the selection scrolled into view
+    let mut list_state = ListState::default().with_selected(Some(selected_index));
+    frame.render_stateful_widget(list, chunks[0], &mut list_state);
 
+    let archived_note = if show_archived {
+        "a: Hide Archived".to_string()
+    } else {
+        format!("{} archived hidden, a: Show", hidden)
+    };
     let help = Paragraph::new(format!(
-        "Found: {} classroom(s) | [↑↓: Navigate | Enter: Select | q: Quit]",
-        classrooms.len()
+        "Found: {} classroom(s) ({}) | [↑↓/PgUp/PgDn/Home/End: Navigate | Enter: Select | q: Quit]",
+        visible.len(),
+        archived_note
     ))
     .block(Block::default().borders(Borders::ALL))
     .alignment(Alignment::Center);
@@ -147,6 +290,7 @@ fn render_classroom_selection(
 
 fn render_assignment_selection(
     frame: &mut Frame,
+    theme: &Theme,
     classroom: &crate::models::Classroom,
     assignments: &[crate::models::Assignment],
     selected_index: usize,
@@ -155,15 +299,26 @@ fn render_assignment_selection(
 
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .constraints([Constraint::Length(3), Constraint::Min(3), Constraint::Length(3)])
         .split(area);
 
+    let header = Paragraph::new(Line::from(vec![
+        Span::styled(format!("{} ", classroom.name), Style::default().add_modifier(Modifier::BOLD)),
+        Span::styled(&classroom.url, Style::default().fg(theme.muted)),
+    ]))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.primary)),
+    );
+    frame.render_widget(header, chunks[0]);
+
     let items: Vec<ListItem> = assignments
         .iter()
         .enumerate()
         .map(|(i, assignment)| {
             let style = if i == selected_index {
-                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)
             } else {
                 Style::default()
             };
@@ -173,9 +328,24 @@ fn render_assignment_selection(
                 .deadline
                 .map(|d| format!(" (Due: {})", d.format("%Y-%m-%d")))
                 .unwrap_or_default();
+            // Pass rate among students who submitted
+            let pass_rate = if assignment.submitted > 0 {
+                format!(
+                    " ({:.0}% pass)",
+                    assignment.passing as f64 / assignment.submitted as f64 * 100.0
+                )
+            } else {
+                String::new()
+            };
             let content = format!(
-                "{}{}{} - {}/{} submitted",
-                prefix, assignment.title, deadline, assignment.submitted, assignment.accepted
+                "{}{}{} - {} accepted / {} submitted / {} passing{}",
+                prefix,
+                assignment.title,
+                deadline,
+                assignment.accepted,
+                assignment.submitted,
+                assignment.passing,
+                pass_rate
             );
 
             ListItem::new(content).style(style)
@@ -184,42 +354,36 @@ fn render_assignment_selection(
 
     let list = List::new(items).block(
         Block::default()
-            .title(format!("Classroom: {} - Select Assignment", classroom.name))
+            .title("Select Assignment")
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan)),
+            .border_style(Style::default().fg(theme.primary)),
     );
 
-    frame.render_widget(list, chunks[0]);
+    let mut list_state = ListState::default().with_selected(Some(selected_index));
+    frame.render_stateful_widget(list, chunks[1], &mut list_state);
 
     let help = Paragraph::new(format!(
-        "Found: {} assignment(s) | [↑↓: Navigate | Enter: Select | Esc: Back | q: Quit]",
+        "Found: {} assignment(s) | [↑↓/PgUp/PgDn/Home/End: Navigate | Enter: Select | a: Download All | o: Open Classroom | Esc: Back | q: Quit]",
         assignments.len()
     ))
     .block(Block::default().borders(Borders::ALL))
     .alignment(Alignment::Center);
 
-    frame.render_widget(help, chunks[1]);
+    frame.render_widget(help, chunks[2]);
 }
 
 fn render_assignment_options(
     frame: &mut Frame,
+    theme: &Theme,
     classroom: &crate::models::Classroom,
     assignment: &crate::models::Assignment,
     selected_index: usize,
+    student: Option<&str>,
+    notice: Option<&str>,
 ) {
     let area = frame.area();
 
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(5),
-            Constraint::Min(3),
-            Constraint::Length(3),
-        ])
-        .split(area);
-
-    // Assignment info
-    let info = Paragraph::new(vec![
+    let mut info_lines = vec![
         Line::from(vec![
             Span::styled("Assignment: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(&assignment.title),
@@ -228,33 +392,75 @@ fn render_assignment_options(
             Span::styled("Classroom: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(&classroom.name),
         ]),
+        Line::from(vec![
+            Span::styled("Slug: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(&assignment.slug),
+        ]),
+        Line::from(vec![
+            Span::styled("Classroom URL: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(classroom.assignment_url(&assignment.slug), Style::default().fg(theme.muted)),
+        ]),
         Line::from(vec![
             Span::styled("Starter Repo: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(assignment.starter_code_url.as_deref().unwrap_or("N/A")),
+            Span::raw(assignment.starter_repo().unwrap_or("N/A")),
         ]),
-    ])
+    ];
+    if let Some(student) = student {
+        info_lines.push(Line::from(vec![
+            Span::styled("Grading Only: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(student, Style::default().fg(theme.highlight)),
+        ]));
+    }
+    if let Some(notice) = notice {
+        info_lines.push(Line::from(Span::styled(notice, Style::default().fg(theme.error))));
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(info_lines.len() as u16 + 2),
+            Constraint::Min(3),
+            Constraint::Length(3),
+        ])
+        .split(area);
+
+    // Assignment info
+    let info = Paragraph::new(info_lines)
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan)),
+            .border_style(Style::default().fg(theme.primary)),
     );
 
     frame.render_widget(info, chunks[0]);
 
     // Options
-    let options = vec!["Download Latest Results", "Download Results After Deadline", "Late Grading Mode"];
+    let before_deadline = if assignment.deadline.is_some() {
+        "Download Latest Results Before Deadline"
+    } else {
+        "Download Latest Results Before Deadline (no Classroom deadline)"
+    };
+    let options = [
+        "Download Latest Results",
+        "Download Results After Deadline",
+        "Late Grading Mode",
+        "Grade a Single Student...",
+        before_deadline,
+    ];
     let items: Vec<ListItem> = options
         .iter()
         .enumerate()
         .map(|(i, option)| {
             let style = if i == selected_index {
-                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)
+            } else if i == 4 && assignment.deadline.is_none() {
+                Style::default().fg(theme.muted)
             } else {
                 Style::default()
             };
 
             let prefix = if i == selected_index { "> " } else { "  " };
-            ListItem::new(format!("{}{}", prefix, option)).style(style)
+            ListItem::new(format!("{}{}. {}", prefix, i + 1, option)).style(style)
         })
         .collect();
 
@@ -262,20 +468,137 @@ fn render_assignment_options(
         Block::default()
             .title("Options")
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan)),
+            .border_style(Style::default().fg(theme.primary)),
     );
 
     frame.render_widget(list, chunks[1]);
 
-    let help = Paragraph::new("[↑↓: Navigate | Enter: Select | Esc: Back | q: Quit]")
+    let help = Paragraph::new("[↑↓: Navigate | 1-5: Quick Select | Enter: Select | t: Preview Tests | o: Open in Browser | Esc: Back | q: Quit]")
         .block(Block::default().borders(Borders::ALL))
         .alignment(Alignment::Center);
 
     frame.render_widget(help, chunks[2]);
 }
 
+fn render_student_input(frame: &mut Frame, theme: &Theme, assignment: &crate::models::Assignment, username_input: &str) {
+    let area = frame.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(3),
+        ])
+        .split(area);
+
+    let title = Paragraph::new(format!("Grade a Single Student: {}", assignment.title))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.primary)),
+        )
+        .alignment(Alignment::Center);
+
+    frame.render_widget(title, chunks[0]);
+
+    let input = Paragraph::new(format!("GitHub Username: {}_", username_input))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)),
+        );
+
+    frame.render_widget(input, chunks[1]);
+
+    let note = Paragraph::new("Only this student will be fetched and exported. Leave empty to grade the whole class again.")
+        .style(Style::default().fg(theme.muted))
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(note, chunks[2]);
+
+    let help = Paragraph::new("[Enter: Confirm | Esc: Cancel]")
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    frame.render_widget(help, chunks[3]);
+}
+
+fn render_test_definitions_preview(
+    frame: &mut Frame,
+    theme: &Theme,
+    assignment: &crate::models::Assignment,
+    test_definitions: &[crate::models::TestDefinition],
+    points_warning: Option<&str>,
+) {
+    let area = frame.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(if points_warning.is_some() { 1 } else { 0 }),
+            Constraint::Length(3),
+        ])
+        .split(area);
+
+    let title = Paragraph::new(format!("Tests for: {}", assignment.title))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.primary)),
+        )
+        .alignment(Alignment::Center);
+
+    frame.render_widget(title, chunks[0]);
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let header = Row::new(["Test", "Step id", "Max score"]).style(bold.fg(theme.highlight));
+    let total: u32 = test_definitions.iter().map(|t| t.max_score).sum();
+
+    let mut rows: Vec<Row> = test_definitions
+        .iter()
+        .map(|t| Row::new([t.name.clone(), t.id.clone(), t.max_score.to_string()]))
+        .collect();
+    rows.push(Row::new([
+        Cell::from(format!("Total ({} tests)", test_definitions.len())),
+        Cell::from(""),
+        Cell::from(total.to_string()),
+    ]).style(bold));
+
+    let table = Table::new(
+        rows,
+        [Constraint::Percentage(50), Constraint::Percentage(35), Constraint::Percentage(15)],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .title("Parsed from .github/workflows/classroom.yml")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.success)),
+    );
+
+    frame.render_widget(table, chunks[1]);
+
+    if let Some(warning) = points_warning {
+        let warning = Paragraph::new(warning)
+            .style(Style::default().fg(theme.highlight))
+            .alignment(Alignment::Center);
+        frame.render_widget(warning, chunks[2]);
+    }
+
+    let help = Paragraph::new("[Enter/Esc: Back | q: Quit]")
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    frame.render_widget(help, chunks[3]);
+}
+
 fn render_grading_mode_selection(
     frame: &mut Frame,
+    theme: &Theme,
     classroom: &crate::models::Classroom,
     assignment: &crate::models::Assignment,
     selected_index: usize,
@@ -305,25 +628,25 @@ fn render_grading_mode_selection(
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan)),
+            .border_style(Style::default().fg(theme.primary)),
     );
 
     frame.render_widget(info, chunks[0]);
 
     // Grading mode options
-    let options = vec!["Regular Grading (Single Deadline)", "Late Grading (On-Time + Late Deadline)"];
+    let options = ["Regular Grading (Single Deadline)", "Late Grading (On-Time + Late Deadline)"];
     let items: Vec<ListItem> = options
         .iter()
         .enumerate()
         .map(|(i, option)| {
             let style = if i == selected_index {
-                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)
             } else {
                 Style::default()
             };
 
             let prefix = if i == selected_index { "> " } else { "  " };
-            ListItem::new(format!("{}{}", prefix, option)).style(style)
+            ListItem::new(format!("{}{}. {}", prefix, i + 1, option)).style(style)
         })
         .collect();
 
@@ -331,29 +654,43 @@ fn render_grading_mode_selection(
         Block::default()
             .title("Select Grading Mode")
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan)),
+            .border_style(Style::default().fg(theme.primary)),
     );
 
     frame.render_widget(list, chunks[1]);
 
-    let help = Paragraph::new("[↑↓: Navigate | Enter: Select | Esc: Back | q: Quit]")
+    let help = Paragraph::new("[↑↓: Navigate | 1-2: Quick Select | Enter: Select | Esc: Back | q: Quit]")
         .block(Block::default().borders(Borders::ALL))
         .alignment(Alignment::Center);
 
     frame.render_widget(help, chunks[2]);
 }
 
+/// What has been typed on the late grading screen
+struct LateGradingForm<'a> {
+    on_time_date: &'a str,
+    on_time_time: &'a str,
+    late_date: &'a str,
+    late_time: &'a str,
+    penalty_input: &'a str,
+    focused_field: LateGradingField,
+}
+
 fn render_late_grading_input(
     frame: &mut Frame,
-    _classroom: &crate::models::Classroom,
+    theme: &Theme,
     assignment: &crate::models::Assignment,
-    on_time_date: &str,
-    on_time_time: &str,
-    late_date: &str,
-    late_time: &str,
-    penalty_input: &str,
-    focused_field: LateGradingField,
+    form: LateGradingForm,
+    timezone: Tz,
 ) {
+    let LateGradingForm {
+        on_time_date,
+        on_time_time,
+        late_date,
+        late_time,
+        penalty_input,
+        focused_field,
+    } = form;
     let area = frame.area();
 
     let chunks = Layout::default()
@@ -371,7 +708,7 @@ fn render_late_grading_input(
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)),
+                .border_style(Style::default().fg(theme.primary)),
         )
         .alignment(Alignment::Center);
 
@@ -380,25 +717,25 @@ fn render_late_grading_input(
     // Input form
     let form_text = vec![
         Line::from(vec![
-            Span::styled("On-Time Deadline:", Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(format!("On-Time Deadline ({}):", timezone), Style::default().add_modifier(Modifier::BOLD)),
         ]),
         Line::from(vec![
             Span::styled("  Date (YYYY-MM-DD): ", Style::default()),
             Span::styled(
                 on_time_date,
                 if matches!(focused_field, LateGradingField::OnTimeDate) {
-                    Style::default().fg(Color::Yellow).add_modifier(Modifier::UNDERLINED)
+                    Style::default().fg(theme.highlight).add_modifier(Modifier::UNDERLINED)
                 } else {
                     Style::default()
                 },
             ),
         ]),
         Line::from(vec![
-            Span::styled("  Time (HH:MM UTC):  ", Style::default()),
+            Span::styled("  Time (HH:MM):      ", Style::default()),
             Span::styled(
                 on_time_time,
                 if matches!(focused_field, LateGradingField::OnTimeTime) {
-                    Style::default().fg(Color::Yellow).add_modifier(Modifier::UNDERLINED)
+                    Style::default().fg(theme.highlight).add_modifier(Modifier::UNDERLINED)
                 } else {
                     Style::default()
                 },
@@ -406,25 +743,25 @@ fn render_late_grading_input(
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("Late Deadline:", Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(format!("Late Deadline ({}):", timezone), Style::default().add_modifier(Modifier::BOLD)),
         ]),
         Line::from(vec![
             Span::styled("  Date (YYYY-MM-DD): ", Style::default()),
             Span::styled(
                 late_date,
                 if matches!(focused_field, LateGradingField::LateDate) {
-                    Style::default().fg(Color::Yellow).add_modifier(Modifier::UNDERLINED)
+                    Style::default().fg(theme.highlight).add_modifier(Modifier::UNDERLINED)
                 } else {
                     Style::default()
                 },
             ),
         ]),
         Line::from(vec![
-            Span::styled("  Time (HH:MM UTC):  ", Style::default()),
+            Span::styled("  Time (HH:MM):      ", Style::default()),
             Span::styled(
                 late_time,
                 if matches!(focused_field, LateGradingField::LateTime) {
-                    Style::default().fg(Color::Yellow).add_modifier(Modifier::UNDERLINED)
+                    Style::default().fg(theme.highlight).add_modifier(Modifier::UNDERLINED)
                 } else {
                     Style::default()
                 },
@@ -436,7 +773,7 @@ fn render_late_grading_input(
             Span::styled(
                 penalty_input,
                 if matches!(focused_field, LateGradingField::Penalty) {
-                    Style::default().fg(Color::Yellow).add_modifier(Modifier::UNDERLINED)
+                    Style::default().fg(theme.highlight).add_modifier(Modifier::UNDERLINED)
                 } else {
                     Style::default()
                 },
@@ -448,26 +785,41 @@ fn render_late_grading_input(
         Block::default()
             .title("Input Deadlines and Penalty")
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan)),
+            .border_style(Style::default().fg(theme.primary)),
     );
 
     frame.render_widget(form, chunks[1]);
 
-    // Help text
-    let help_text = vec![
+    // Help text, after any warnings about the entered deadlines
+    let mut help_text = Vec::new();
+    for (label, date, time) in [("On-time", on_time_date, on_time_time), ("Late", late_date, late_time)] {
+        if let Some(warning) = parse_deadline(date, time, timezone)
+            .ok()
+            .and_then(|deadline| deadline_warning(deadline, assignment.deadline, Utc::now()))
+        {
+            help_text.push(Line::styled(
+                format!("⚠ {}: {}", label, warning),
+                Style::default().fg(theme.highlight),
+            ));
+        }
+    }
+    if !help_text.is_empty() {
+        help_text.push(Line::from(""));
+    }
+    help_text.extend([
         Line::from("Enter on-time deadline (first graded submission) and late deadline (final graded submission)."),
         Line::from("Late penalty is deducted from improvements only (e.g., 20% means 80% credit for late work)."),
         Line::from(""),
         Line::from("Example: Student gets 70/100 on-time, 90/100 late with 20% penalty:"),
         Line::from("  Final score = 70 + (90 - 70) * 0.8 = 70 + 16 = 86"),
-    ];
+    ]);
 
     let help_info = Paragraph::new(help_text)
         .block(
             Block::default()
                 .title("How It Works")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Green)),
+                .border_style(Style::default().fg(theme.success)),
         )
         .wrap(Wrap { trim: true });
 
@@ -482,11 +834,12 @@ fn render_late_grading_input(
 
 fn render_deadline_input(
     frame: &mut Frame,
-    _classroom: &crate::models::Classroom,
+    theme: &Theme,
     assignment: &crate::models::Assignment,
     date_input: &str,
     time_input: &str,
     focused_field: DeadlineField,
+    timezone: Tz,
 ) {
     let area = frame.area();
 
@@ -506,7 +859,7 @@ fn render_deadline_input(
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)),
+                .border_style(Style::default().fg(theme.primary)),
         )
         .alignment(Alignment::Center);
 
@@ -514,12 +867,12 @@ fn render_deadline_input(
 
     // Date input
     let date_style = if focused_field == DeadlineField::Date {
-        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)
     } else {
         Style::default()
     };
 
-    let date = Paragraph::new(format!("Date (YYYY-MM-DD): {}_", date_input))
+    let date = Paragraph::new(format!("Date (YYYY-MM-DD, {}): {}_", timezone, date_input))
         .block(
             Block::default()
                 .borders(Borders::ALL)
@@ -530,12 +883,12 @@ fn render_deadline_input(
 
     // Time input
     let time_style = if focused_field == DeadlineField::Time {
-        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)
     } else {
         Style::default()
     };
 
-    let time = Paragraph::new(format!("Time (HH:MM): {}_", time_input))
+    let time = Paragraph::new(format!("Time (HH:MM, {}): {}_", timezone, time_input))
         .block(
             Block::default()
                 .borders(Borders::ALL)
@@ -544,6 +897,24 @@ fn render_deadline_input(
 
     frame.render_widget(time, chunks[2]);
 
+    let mut notes = Vec::new();
+    if let Some(warning) = parse_deadline(date_input, time_input, timezone)
+        .ok()
+        .and_then(|deadline| deadline_warning(deadline, assignment.deadline, Utc::now()))
+    {
+        notes.push(Line::styled(format!("⚠ {}", warning), Style::default().fg(theme.highlight)));
+    }
+    if assignment.deadline.is_some() {
+        notes.push(Line::styled(
+            format!(
+                "Pre-filled from the assignment's Classroom deadline ({}). Edit it to grade against a different time.",
+                timezone
+            ),
+            Style::default().fg(theme.muted),
+        ));
+    }
+    frame.render_widget(Paragraph::new(notes).wrap(Wrap { trim: true }), chunks[3]);
+
     // Help
     let help = Paragraph::new("[Tab: Switch Field | Enter: Confirm | Esc: Cancel | q: Quit]")
         .block(Block::default().borders(Borders::ALL))
@@ -552,12 +923,22 @@ fn render_deadline_input(
     frame.render_widget(help, chunks[4]);
 }
 
+/// The fetch a progress screen shows
+struct FetchingView<'a> {
+    title: &'a str,
+    progress: &'a crate::ui::state::FetchProgress,
+    log_scroll: Option<usize>,
+}
+
 fn render_fetching_results(
     frame: &mut Frame,
-    assignment: &crate::models::Assignment,
-    progress: &crate::ui::state::FetchProgress,
+    theme: &Theme,
+    view: FetchingView,
+    student_table: bool,
     spinner: char,
+    api_status: ApiStatus,
 ) {
+    let FetchingView { title, progress, log_scroll } = view;
     let area = frame.area();
 
     let chunks = Layout::default()
@@ -567,15 +948,16 @@ fn render_fetching_results(
             Constraint::Length(3),
             Constraint::Min(8),
             Constraint::Length(3),
+            Constraint::Length(1),
         ])
         .split(area);
 
     // Title with spinner
-    let title = Paragraph::new(format!("{} Fetching Results: {}", spinner, assignment.title))
+    let title = Paragraph::new(format!("{} Fetching Results: {}", spinner, title))
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)),
+                .border_style(Style::default().fg(theme.primary)),
         )
         .alignment(Alignment::Center);
 
@@ -584,56 +966,217 @@ fn render_fetching_results(
     // Progress bar
     let gauge = Gauge::default()
         .block(Block::default().title("Progress").borders(Borders::ALL))
-        .gauge_style(Style::default().fg(Color::Green))
+        .gauge_style(Style::default().fg(theme.success))
         .percent(progress.percentage() as u16)
-        .label(format!(
-            "{}/{} students | {} errors",
-            progress.completed, progress.total_students, progress.errors
-        ));
+        .label({
+            let mut label = if progress.active > 0 {
+                format!(
+                    "{} active, {}/{} students | {} errors",
+                    progress.active, progress.completed, progress.total_students, progress.errors
+                )
+            } else {
+                format!(
+                    "{}/{} students | {} errors",
+                    progress.completed, progress.total_students, progress.errors
+                )
+            };
+            if progress.in_progress > 0 {
+                label.push_str(&format!(" | {} in progress", progress.in_progress));
+            }
+            label
+        });
 
     frame.render_widget(gauge, chunks[1]);
 
-    // Status messages (scrolling log)
+    if student_table {
+        render_student_table(frame, theme, progress, log_scroll, chunks[2]);
+    } else {
+        render_status_log(frame, theme, progress, log_scroll, chunks[2]);
+    }
+
+    // Summary info with spinner
+    let info_text = if progress.current_student.is_empty() {
+        format!("{} Preparing...", spinner)
+    } else {
+        format!("{} Current student: {}", spinner, progress.current_student)
+    };
+
+    let info = Paragraph::new(info_text)
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(theme.primary).add_modifier(Modifier::BOLD));
+
+    frame.render_widget(info, chunks[3]);
+
+    // API quota status bar, replaced by a countdown while backing off from abuse detection
+    let rate_limit = api_status.rate_limit;
+    let rate_limit_text = match (api_status.backoff_until, rate_limit) {
+        (Some(until), _) => {
+            let seconds = (until - Utc::now()).num_seconds().max(0);
+            format!("Hit GitHub abuse detection, backing off {}s", seconds)
+        }
+        (None, Some(info)) => {
+            let minutes = (info.reset - Utc::now()).num_minutes().max(0);
+            format!("API: {}/{}, resets in {}m", info.remaining, info.limit, minutes)
+        }
+        (None, None) => "API: waiting for first response...".to_string(),
+    };
+    let rate_limit_style = match (api_status.backoff_until, rate_limit) {
+        (Some(_), _) => Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD),
+        (None, Some(info)) if info.remaining < info.limit / 10 => Style::default().fg(theme.error),
+        _ => Style::default().fg(theme.muted),
+    };
+
+    let status_bar = Paragraph::new(rate_limit_text)
+        .style(rate_limit_style)
+        .alignment(Alignment::Right);
+
+    frame.render_widget(status_bar, chunks[4]);
+}
+
+/// Status messages (scrollable log, following the newest messages by default)
+fn render_status_log(
+    frame: &mut Frame,
+    theme: &Theme,
+    progress: &crate::ui::state::FetchProgress,
+    log_scroll: Option<usize>,
+    area: Rect,
+) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let total = progress.status_messages.len();
+    let bottom = total.saturating_sub(visible);
+    let start = log_scroll.unwrap_or(bottom).min(bottom);
+
     let status_items: Vec<ListItem> = progress
         .status_messages
         .iter()
+        .skip(start)
+        .take(visible)
         .map(|msg| {
             ListItem::new(format!("• {}", msg))
-                .style(Style::default().fg(Color::Green))
+                .style(Style::default().fg(theme.success))
         })
         .collect();
 
+    let log_title = if log_scroll.is_some() {
+        format!(
+            "Status Log ({}-{} of {}) [PgUp/PgDn/Home/End: Scroll | v: Students | Esc: Cancel | q: Quit]",
+            start + 1,
+            (start + visible).min(total),
+            total
+        )
+    } else {
+        "Status Log [PgUp/PgDn/Home/End: Scroll | v: Students | Esc: Cancel | q: Quit]".to_string()
+    };
+
     let status_list = List::new(status_items)
         .block(
             Block::default()
-                .title("Status Log")
+                .title(log_title)
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Yellow)),
+                .border_style(Style::default().fg(theme.highlight)),
         );
 
-    frame.render_widget(status_list, chunks[2]);
+    frame.render_widget(status_list, area);
+}
 
-    // Summary info with spinner
-    let info_text = if progress.current_student.is_empty() {
-        format!("{} Preparing...", spinner)
+/// One row per student with where their fetch stands. Unless scrolled, the
+/// window follows the first student still pending or being fetched.
+fn render_student_table(
+    frame: &mut Frame,
+    theme: &Theme,
+    progress: &crate::ui::state::FetchProgress,
+    log_scroll: Option<usize>,
+    area: Rect,
+) {
+    let visible = area.height.saturating_sub(3) as usize;
+    let total = progress.students.len();
+    let bottom = total.saturating_sub(visible);
+    let first_open = progress
+        .students
+        .values()
+        .position(|s| matches!(s, StudentStatus::Pending | StudentStatus::Fetching))
+        .unwrap_or(total);
+    let start = log_scroll.unwrap_or(first_open.saturating_sub(visible / 2)).min(bottom);
+
+    let rows: Vec<Row> = progress
+        .students
+        .iter()
+        .skip(start)
+        .take(visible)
+        .map(|(username, status)| {
+            let (text, score, color) = match status {
+                StudentStatus::Pending => ("pending".to_string(), String::new(), theme.muted),
+                StudentStatus::Fetching => ("fetching...".to_string(), String::new(), theme.highlight),
+                StudentStatus::Graded { awarded, available } => {
+                    ("✓ graded".to_string(), format!("{}/{}", awarded, available), theme.success)
+                }
+                StudentStatus::InProgress => ("⏳ run in progress".to_string(), String::new(), theme.highlight),
+                StudentStatus::NotSubmitted => ("not submitted".to_string(), String::new(), theme.muted),
+                StudentStatus::Failed(error) => (format!("✗ {}", error), String::new(), theme.error),
+            };
+            Row::new([username.clone(), text, score]).style(Style::default().fg(color))
+        })
+        .collect();
+
+    let table_title = if log_scroll.is_some() {
+        format!(
+            "Students ({}-{} of {}) [PgUp/PgDn/Home/End: Scroll | v: Status Log | Esc: Cancel | q: Quit]",
+            start + 1,
+            (start + visible).min(total),
+            total
+        )
     } else {
-        format!("{} Current student: {}", spinner, progress.current_student)
+        "Students [PgUp/PgDn/Home/End: Scroll | v: Status Log | Esc: Cancel | q: Quit]".to_string()
     };
 
-    let info = Paragraph::new(info_text)
-        .block(Block::default().borders(Borders::ALL))
-        .alignment(Alignment::Center)
-        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+    let table = Table::new(
+        rows,
+        [Constraint::Percentage(30), Constraint::Percentage(55), Constraint::Percentage(15)],
+    )
+    .header(Row::new(["Student", "Status", "Score"]).style(Style::default().add_modifier(Modifier::BOLD)))
+    .block(
+        Block::default()
+            .title(table_title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.highlight)),
+    );
 
-    frame.render_widget(info, chunks[3]);
+    frame.render_widget(table, area);
+}
+
+/// What a finished fetch produced, as shown on the completion screen
+struct CompletionView<'a> {
+    stats: &'a crate::models::ResultStats,
+    filenames: &'a [String],
+    summary_filename: &'a str,
+    test_stats_filename: &'a str,
+    nonsubmitters: usize,
+    nonsubmitters_filename: Option<&'a str>,
+    hardest_tests: &'a [crate::models::TestStats],
+    warnings: &'a [String],
+    run_log_filename: Option<&'a str>,
+    action_status: Option<&'a str>,
 }
 
 fn render_results_complete(
     frame: &mut Frame,
+    theme: &Theme,
     assignment: &crate::models::Assignment,
-    stats: &crate::models::ResultStats,
-    csv_filename: &str,
+    view: CompletionView,
 ) {
+    let CompletionView {
+        stats,
+        filenames,
+        summary_filename,
+        test_stats_filename,
+        nonsubmitters,
+        nonsubmitters_filename,
+        hardest_tests,
+        warnings,
+        run_log_filename,
+        action_status,
+    } = view;
     let area = frame.area();
 
     let chunks = Layout::default()
@@ -641,9 +1184,9 @@ fn render_results_complete(
         .constraints([Constraint::Min(3), Constraint::Length(3)])
         .split(area);
 
-    let text = vec![
+    let mut text = vec![
         Line::from(vec![
-            Span::styled("Results Exported!", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled("Results Exported!", Style::default().fg(theme.success).add_modifier(Modifier::BOLD)),
         ]),
         Line::from(""),
         Line::from(vec![
@@ -651,9 +1194,28 @@ fn render_results_complete(
             Span::raw(&assignment.title),
         ]),
         Line::from(vec![
-            Span::styled("File: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(csv_filename),
+            Span::styled(
+                if filenames.len() > 1 { "Files: " } else { "File: " },
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(filenames.join(", ")),
+        ]),
+        Line::from(vec![
+            Span::styled("Summary: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(summary_filename),
         ]),
+        Line::from(vec![
+            Span::styled("Test Stats: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(test_stats_filename),
+        ]),
+    ];
+    if let Some(run_log_filename) = run_log_filename {
+        text.push(Line::from(vec![
+            Span::styled("Run Log: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(run_log_filename),
+        ]));
+    }
+    text.extend([
         Line::from(""),
         Line::from(vec![
             Span::styled("Students processed: ", Style::default().add_modifier(Modifier::BOLD)),
@@ -665,24 +1227,342 @@ fn render_results_complete(
         ]),
         Line::from(vec![
             Span::styled("Average score: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(format!("{:.2}%", stats.average_score)),
+            Span::raw(crate::models::ResultStats::format_score(stats.average_score)),
         ]),
         Line::from(vec![
             Span::styled("Median score: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(format!("{:.2}%", stats.median_score)),
+            Span::raw(crate::models::ResultStats::format_score(stats.median_score)),
         ]),
-    ];
+        Line::from(vec![
+            Span::styled("Not submitted: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(match nonsubmitters_filename {
+                Some(filename) if nonsubmitters > 0 => format!("{} (listed in {})", nonsubmitters, filename),
+                _ => nonsubmitters.to_string(),
+            }),
+        ]),
+    ]);
+
+    if !hardest_tests.is_empty() {
+        text.push(Line::from(""));
+        text.push(Line::from(vec![
+            Span::styled("Hardest tests:", Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)),
+        ]));
+        for test in hardest_tests {
+            text.push(Line::from(format!(
+                "{}: {:.0}% passed ({}/{}), avg {:.1}/{} pts",
+                test.test_name, test.pass_rate, test.passed, test.students, test.average_points, test.points_available
+            )));
+        }
+    }
+
+    if !warnings.is_empty() {
+        text.push(Line::from(""));
+        for warning in warnings {
+            text.push(Line::from(Span::styled(warning.as_str(), Style::default().fg(theme.highlight))));
+        }
+    }
+
+    if let Some(status) = action_status {
+        text.push(Line::from(""));
+        text.push(Line::from(Span::styled(status, Style::default().fg(theme.highlight))));
+    }
 
     let paragraph = Paragraph::new(text)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)),
+                .border_style(Style::default().fg(theme.primary)),
         )
         .alignment(Alignment::Center);
 
     frame.render_widget(paragraph, chunks[0]);
 
+    let help = Paragraph::new(
+        "[Enter: Continue | s: Students | c: Copy file path | j: Also export JSON | x: Also export XLSX | q: Quit]",
+    )
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    frame.render_widget(help, chunks[1]);
+}
+
+/// One student's graded run next to the list of students, for checking a score
+fn render_student_detail(
+    frame: &mut Frame,
+    theme: &Theme,
+    timezone: Tz,
+    results: &FetchedResults,
+    detail: &StudentDetail,
+    action_status: Option<&str>,
+) {
+    let area = frame.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(area);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(chunks[0]);
+
+    // Late grading lists the final score, which neither run has on its own
+    let students: Vec<(&str, String)> = match results {
+        FetchedResults::Regular(results) => results
+            .iter()
+            .map(|r| (r.username.as_str(), format!("{}/{}", r.total_awarded, r.total_available)))
+            .collect(),
+        FetchedResults::Late(results) => results
+            .iter()
+            .map(|r| (r.username.as_str(), format!("{}/{}", r.final_score, r.on_time_result.total_available)))
+            .collect(),
+    };
+    let items: Vec<ListItem> = students
+        .iter()
+        .enumerate()
+        .map(|(i, (username, score))| {
+            let style = if i == detail.selected {
+                Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let prefix = if i == detail.selected { "> " } else { "  " };
+            ListItem::new(format!("{}{} {}", prefix, username, score)).style(style)
+        })
+        .collect();
+    let list = List::new(items).block(
+        Block::default()
+            .title(format!("Students ({})", students.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.primary)),
+    );
+    let mut list_state = ListState::default().with_selected(Some(detail.selected));
+    frame.render_stateful_widget(list, columns[0], &mut list_state);
+
+    let Some(run) = results.run(detail.selected, detail.late_run) else {
+        return;
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let field = |label: &'static str, value: String| Line::from(vec![Span::styled(label, bold), Span::raw(value)]);
+    let mut text = vec![
+        field(
+            "Student: ",
+            match &run.display_name {
+                Some(name) => format!("{} ({})", run.username, name),
+                None => run.username.clone(),
+            },
+        ),
+        field("Repository: ", run.repo_url.clone()),
+    ];
+    if let FetchedResults::Late(results) = results {
+        let result = &results[detail.selected];
+        text.push(field(
+            "Final score: ",
+            format!(
+                "{}/{} (on time {}, late {})",
+                result.final_score,
+                result.on_time_result.total_available,
+                result.on_time_result.total_awarded,
+                result.late_result.total_awarded
+            ),
+        ));
+        text.push(field("Showing: ", if detail.late_run { "late run" } else { "on-time run" }.to_string()));
+    }
+    text.extend([
+        field(
+            "Run: ",
+            format!(
+                "{} ({})",
+                run.workflow_run_timestamp.with_timezone(&timezone).format("%Y-%m-%d %H:%M %Z"),
+                run.run_conclusion
+            ),
+        ),
+        field("Commit: ", run.commit_sha.chars().take(7).collect()),
+        field("Run URL: ", run.run_url.clone()),
+        field(
+            "Points: ",
+            format!("{}/{} from {}", run.total_awarded, run.total_available, run.points_source),
+        ),
+    ]);
+    if let Some(status) = action_status {
+        text.push(Line::from(Span::styled(status, Style::default().fg(theme.highlight))));
+    }
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(text.len() as u16 + 2), Constraint::Min(3)])
+        .split(columns[1]);
+
+    let info = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.primary)),
+    );
+    frame.render_widget(info, rows[0]);
+
+    let tests: Vec<Row> = run
+        .tests
+        .iter()
+        .map(|(name, test)| {
+            let (status, color) = match test.status {
+                TestStatus::Passed => ("✓ passed", theme.success),
+                TestStatus::Failed => ("✗ failed", theme.error),
+                TestStatus::Missing => ("missing", theme.highlight),
+                TestStatus::Skipped => ("skipped", theme.muted),
+            };
+            let message = test.failure_message.as_deref().and_then(|m| m.lines().next()).unwrap_or_default();
+            Row::new([
+                name.clone(),
+                status.to_string(),
+                format!("{}/{}", test.points_awarded, test.points_available),
+                message.to_string(),
+            ])
+            .style(Style::default().fg(color))
+        })
+        .collect();
+    let table = Table::new(
+        tests,
+        [
+            Constraint::Percentage(35),
+            Constraint::Percentage(15),
+            Constraint::Percentage(10),
+            Constraint::Percentage(40),
+        ],
+    )
+    .header(Row::new(["Test", "Status", "Points", "Failure message"]).style(bold))
+    .block(
+        Block::default()
+            .title("Tests")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.success)),
+    );
+    frame.render_widget(table, rows[1]);
+
+    let help = if matches!(results, FetchedResults::Late(_)) {
+        "[↑↓/PgUp/PgDn/Home/End: Student | Tab: On-time/Late Run | l: Raw Log | o: Open Run | c: Copy Run URL \
+         | Esc: Back | q: Quit]"
+    } else {
+        "[↑↓/PgUp/PgDn/Home/End: Student | l: Raw Log | o: Open Run | c: Copy Run URL | Esc: Back | q: Quit]"
+    };
+    let help = Paragraph::new(help)
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+    frame.render_widget(help, chunks[1]);
+}
+
+/// A graded run's raw log, drawing only the lines on screen so long logs stay cheap
+fn render_job_log(frame: &mut Frame, theme: &Theme, log: &JobLog, spinner: char) {
+    let area = frame.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(area);
+
+    if log.loading {
+        let paragraph = Paragraph::new(format!("{} Fetching the log...", spinner)).block(
+            Block::default()
+                .title(log.title.as_str())
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.primary)),
+        );
+        frame.render_widget(paragraph, chunks[0]);
+        let help = Paragraph::new("[Esc: Cancel | q: Quit]")
+            .block(Block::default().borders(Borders::ALL))
+            .alignment(Alignment::Center);
+        frame.render_widget(help, chunks[1]);
+        return;
+    }
+
+    let visible = chunks[0].height.saturating_sub(2) as usize;
+    let start = log.scroll.min(log.lines.len());
+    let end = (start + visible).min(log.lines.len());
+    let lines: Vec<Line> = log.lines[start..end].iter().map(|line| Line::raw(line.as_str())).collect();
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title(format!("{} (lines {}-{} of {})", log.title, start + 1, end, log.lines.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.primary)),
+    );
+    frame.render_widget(paragraph, chunks[0]);
+
+    let help = Paragraph::new("[↑↓/PgUp/PgDn/Home/End: Scroll | Esc: Back | q: Quit]")
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+    frame.render_widget(help, chunks[1]);
+}
+
+fn render_all_assignments_complete(
+    frame: &mut Frame,
+    theme: &Theme,
+    classroom: &crate::models::Classroom,
+    exported: &[(String, String)],
+    skipped: &[(String, String)],
+    combined_filename: &str,
+    run_log_filename: Option<&str>,
+) {
+    let area = frame.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(area);
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut text = vec![
+        Line::from(vec![
+            Span::styled("All Assignments Exported!", Style::default().fg(theme.success).add_modifier(Modifier::BOLD)),
+        ]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Classroom: ", bold),
+            Span::raw(&classroom.name),
+        ]),
+        Line::from(vec![
+            Span::styled("Combined: ", bold),
+            Span::raw(combined_filename),
+        ]),
+    ];
+    if let Some(run_log_filename) = run_log_filename {
+        text.push(Line::from(vec![
+            Span::styled("Run Log: ", bold),
+            Span::raw(run_log_filename),
+        ]));
+    }
+    text.push(Line::from(""));
+
+    for (title, filename) in exported {
+        text.push(Line::from(vec![
+            Span::styled(format!("{}: ", title), bold),
+            Span::raw(filename),
+        ]));
+    }
+
+    if !skipped.is_empty() {
+        text.push(Line::from(""));
+        text.push(Line::from(vec![
+            Span::styled("Skipped:", Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)),
+        ]));
+        for (title, reason) in skipped {
+            text.push(Line::from(vec![
+                Span::styled(format!("{}: ", title), bold),
+                Span::raw(reason),
+            ]));
+        }
+    }
+
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.primary)),
+        )
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(paragraph, chunks[0]);
+
     let help = Paragraph::new("[Enter: Continue | q: Quit]")
         .block(Block::default().borders(Borders::ALL))
         .alignment(Alignment::Center);
@@ -690,7 +1570,98 @@ fn render_results_complete(
     frame.render_widget(help, chunks[1]);
 }
 
-fn render_error(frame: &mut Frame, message: &str) {
+fn render_reauth(frame: &mut Frame, theme: &Theme, message: &str, token_input: &str) {
+    let area = frame.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(5),
+            Constraint::Length(3),
+            Constraint::Length(3),
+        ])
+        .split(area);
+
+    let text = vec![
+        Line::from(vec![
+            Span::styled("Token Rejected", Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)),
+        ]),
+        Line::from(""),
+        Line::from(message),
+        Line::from(""),
+        Line::from("Enter a fresh GitHub token to continue where you left off."),
+    ];
+
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.highlight)),
+        )
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(paragraph, chunks[0]);
+
+    // Mask the token so it isn't left visible on screen
+    let input = Paragraph::new(format!("Token: {}_", "*".repeat(token_input.chars().count())))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)),
+        );
+
+    frame.render_widget(input, chunks[1]);
+
+    let help = Paragraph::new("[Enter: Retry with New Token | Esc: Cancel]")
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    frame.render_widget(help, chunks[2]);
+}
+
+fn render_device_login(frame: &mut Frame, theme: &Theme, user_code: &str, verification_uri: &str, spinner: char) {
+    let area = frame.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(3)])
+        .split(area);
+
+    let text = vec![
+        Line::from(vec![
+            Span::styled("Log In to GitHub", Style::default().fg(theme.primary).add_modifier(Modifier::BOLD)),
+        ]),
+        Line::from(""),
+        Line::from(format!("Open {} in your browser and enter this code:", verification_uri)),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(user_code, Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)),
+        ]),
+        Line::from(""),
+        Line::from(format!("{} Waiting for authorization...", spinner)),
+    ];
+
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .title("GitHub Classroom Autograder Fetcher")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.primary)),
+        )
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(paragraph, chunks[0]);
+
+    let help = Paragraph::new("[q/Esc: Quit]")
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    frame.render_widget(help, chunks[1]);
+}
+
+fn render_error(frame: &mut Frame, theme: &Theme, message: &str) {
     let area = frame.area();
 
     let chunks = Layout::default()
@@ -700,7 +1671,7 @@ fn render_error(frame: &mut Frame, message: &str) {
 
     let text = vec![
         Line::from(vec![
-            Span::styled("Error", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled("Error", Style::default().fg(theme.error).add_modifier(Modifier::BOLD)),
         ]),
         Line::from(""),
         Line::from(message),
@@ -710,7 +1681,7 @@ fn render_error(frame: &mut Frame, message: &str) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Red)),
+                .border_style(Style::default().fg(theme.error)),
         )
         .alignment(Alignment::Center)
         .wrap(Wrap { trim: true });
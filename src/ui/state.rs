@@ -1,12 +1,121 @@
-use crate::models::{Assignment, Classroom, ResultStats};
-use chrono::{DateTime, Utc};
+use crate::deadlines::LateGradingDeadlines;
+use crate::export::RunLog;
+use crate::fetcher::{RunSelection, StudentOutcome};
+use crate::models::{
+    Assignment, Classroom, LateGradingResult, NonSubmitter, ResultStats, StudentResult, TestDefinition, TestStats,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Results of a finished fetch, as exported
+#[derive(Debug, Clone)]
+pub enum FetchedResults {
+    Regular(Vec<StudentResult>),
+    Late(Vec<LateGradingResult>),
+}
+
+impl FetchedResults {
+    pub fn len(&self) -> usize {
+        match self {
+            FetchedResults::Regular(results) => results.len(),
+            FetchedResults::Late(results) => results.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `index`th student's graded run; for late grading, their late run
+    /// if `late_run` is set and their on-time run otherwise
+    pub fn run(&self, index: usize, late_run: bool) -> Option<&StudentResult> {
+        match self {
+            FetchedResults::Regular(results) => results.get(index),
+            FetchedResults::Late(results) => results
+                .get(index)
+                .map(|r| if late_run { &r.late_result } else { &r.on_time_result }),
+        }
+    }
+}
+
+/// The completion screen's view of one student's graded run
+#[derive(Debug, Clone, Default)]
+pub struct StudentDetail {
+    /// Index into the fetched results
+    pub selected: usize,
+    /// For late grading, show the late run instead of the on-time one
+    pub late_run: bool,
+    /// The shown run's raw log, once fetched
+    pub log: Option<JobLog>,
+}
+
+/// Raw log lines of a graded run, shown a screenful at a time
+#[derive(Debug, Clone)]
+pub struct JobLog {
+    pub title: String,
+    pub lines: Vec<String>,
+    /// First visible line
+    pub scroll: usize,
+    /// Still being downloaded in the background
+    pub loading: bool,
+}
+
+impl JobLog {
+    /// Split a downloaded log into lines, dropping the color codes and other
+    /// control characters Actions logs are full of, which would garble the terminal
+    pub fn new(title: String, logs: &str) -> Self {
+        let lines = logs
+            .lines()
+            .map(|line| {
+                let mut clean = String::with_capacity(line.len());
+                let mut chars = line.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        // CSI sequences run up to a final byte in '@'..='~'
+                        '\x1b' => {
+                            if chars.next() == Some('[') {
+                                chars.by_ref().find(|c| ('@'..='~').contains(c));
+                            }
+                        }
+                        '\t' => clean.push_str("    "),
+                        c if c.is_control() => {}
+                        c => clean.push(c),
+                    }
+                }
+                clean
+            })
+            .collect();
+        Self {
+            title,
+            lines,
+            scroll: 0,
+            loading: false,
+        }
+    }
+
+    /// Placeholder shown while the log downloads
+    pub fn loading(title: String) -> Self {
+        Self {
+            title,
+            lines: Vec::new(),
+            scroll: 0,
+            loading: true,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum AppState {
     LoadingClassrooms,
     ClassroomSelection {
         classrooms: Vec<Classroom>,
+        /// Index into the visible classrooms
         selected_index: usize,
+        show_archived: bool,
     },
     LoadingAssignments {
         classroom: Classroom,
@@ -20,6 +129,23 @@ pub enum AppState {
         classroom: Classroom,
         assignment: Assignment,
         selected_index: usize,
+        /// Only this student will be graded, if set
+        student: Option<String>,
+        /// Why the last choice did nothing, e.g. no Classroom deadline to grade before
+        notice: Option<String>,
+    },
+    StudentInput {
+        classroom: Classroom,
+        assignment: Assignment,
+        username_input: String,
+    },
+    /// Tests parsed from the assignment's workflow, shown before fetching anything
+    TestDefinitionsPreview {
+        classroom: Classroom,
+        assignment: Assignment,
+        test_definitions: Vec<TestDefinition>,
+        /// Set when the tests' total differs from what GitHub Classroom grades out of
+        points_warning: Option<String>,
     },
     GradingModeSelection {
         classroom: Classroom,
@@ -44,30 +170,200 @@ pub enum AppState {
         focused_field: LateGradingField,
     },
     FetchingResults {
-        _classroom: Classroom,
+        classroom: Classroom,
         assignment: Assignment,
-        _deadline: Option<DateTime<Utc>>,
+        selection: RunSelection,
         progress: FetchProgress,
+        /// First visible status log line, or `None` to follow the newest messages
+        log_scroll: Option<usize>,
     },
     FetchingLateResults {
-        _classroom: Classroom,
+        classroom: Classroom,
         assignment: Assignment,
-        _on_time_deadline: DateTime<Utc>,
-        _late_deadline: DateTime<Utc>,
-        _late_penalty: f64,
+        deadlines: LateGradingDeadlines,
         progress: FetchProgress,
+        /// First visible status log line, or `None` to follow the newest messages
+        log_scroll: Option<usize>,
+    },
+    /// Fetching every assignment in a classroom, one after another
+    FetchingAllAssignments {
+        classroom: Classroom,
+        assignments: Vec<Assignment>,
+        progress: FetchProgress,
+        /// First visible status log line, or `None` to follow the newest messages
+        log_scroll: Option<usize>,
     },
     ResultsComplete {
         classroom: Classroom,
         assignment: Assignment,
         stats: ResultStats,
-        csv_filename: String,
+        /// One exported file per requested format, primary first
+        filenames: Vec<String>,
+        summary_filename: String,
+        test_stats_filename: String,
+        /// Tests with the lowest pass rates, hardest first
+        hardest_tests: Vec<TestStats>,
+        /// Problems worth checking before the grades are published
+        warnings: Vec<String>,
+        /// Students with no completed grading run
+        nonsubmitters: usize,
+        /// List of those students, unless the export is anonymized
+        nonsubmitters_filename: Option<String>,
+        /// Log of the whole run, if it could be written
+        run_log_filename: Option<String>,
+        /// Why the results don't match `--baseline` closely enough, failing a headless run
+        drift_error: Option<String>,
+        /// The exported results, kept to export them again in another format
+        results: FetchedResults,
+        /// Tests the results were graded against, in definition order
+        test_definitions: Vec<TestDefinition>,
+        /// Outcome of the last copy-to-clipboard or extra export
+        action_status: Option<String>,
+        /// The per-student view, while it's open
+        detail: Option<StudentDetail>,
+    },
+    AllAssignmentsComplete {
+        classroom: Classroom,
+        /// (assignment title, exported file) for each assignment with results
+        exported: Vec<(String, String)>,
+        /// (assignment title, why) for each assignment that had nothing to export
+        skipped: Vec<(String, String)>,
+        combined_filename: String,
+        run_log_filename: Option<String>,
+    },
+    ReAuth {
+        message: String,
+        token_input: String,
+        resume: ResumeAction,
+    },
+    /// Waiting for the user to approve a device-flow login on GitHub
+    DeviceLogin {
+        user_code: String,
+        verification_uri: String,
+    },
+    /// Device-flow login finished; the token still has to be handed to the clients
+    LoggedIn {
+        token: String,
+    },
+    /// A run's log finished downloading (or failed to); it still has to be
+    /// shown in the student view it was opened from
+    JobLogLoaded {
+        log: Result<JobLog, String>,
     },
     Error {
         message: String,
     },
 }
 
+impl AppState {
+    /// Progress and status log scroll position, for the fetching screens
+    pub fn fetch_log_mut(&mut self) -> Option<(&FetchProgress, &mut Option<usize>)> {
+        match self {
+            AppState::FetchingResults {
+                progress,
+                log_scroll,
+                ..
+            }
+            | AppState::FetchingLateResults {
+                progress,
+                log_scroll,
+                ..
+            }
+            | AppState::FetchingAllAssignments {
+                progress,
+                log_scroll,
+                ..
+            } => Some((progress, log_scroll)),
+            _ => None,
+        }
+    }
+}
+
+/// Classrooms shown in the selection list, leaving out archived ones unless requested
+pub fn visible_classrooms(classrooms: &[Classroom], show_archived: bool) -> Vec<&Classroom> {
+    classrooms
+        .iter()
+        .filter(|c| show_archived || !c.archived)
+        .collect()
+}
+
+/// Parse a deadline entered as local time in `timezone`
+pub fn parse_deadline(date_str: &str, time_str: &str, timezone: Tz) -> Result<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+        .map_err(|e| anyhow::anyhow!("Invalid date format (expected YYYY-MM-DD): {}", e))?;
+
+    let time = NaiveTime::parse_from_str(time_str, "%H:%M")
+        .map_err(|e| anyhow::anyhow!("Invalid time format (expected HH:MM): {}", e))?;
+
+    // A time skipped by a DST change doesn't exist; an ambiguous one takes the earlier instant
+    let datetime = NaiveDateTime::new(date, time)
+        .and_local_timezone(timezone)
+        .earliest()
+        .with_context(|| format!("{} {} doesn't exist in {}", date_str, time_str, timezone))?;
+    Ok(datetime.with_timezone(&Utc))
+}
+
+/// A warning for an entered deadline that looks like a typo: one before the
+/// assignment's Classroom deadline, or one in the future, after which no runs exist yet
+pub fn deadline_warning(
+    entered: DateTime<Utc>,
+    classroom_deadline: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    if let Some(classroom_deadline) = classroom_deadline
+        && entered < classroom_deadline
+    {
+        return Some(format!(
+            "Entered deadline is {} before the Classroom deadline",
+            describe_gap(classroom_deadline - entered)
+        ));
+    }
+
+    if entered > now {
+        return Some(format!(
+            "Entered deadline is {} in the future, so no runs after it exist yet",
+            describe_gap(entered - now)
+        ));
+    }
+
+    None
+}
+
+/// A time span in its largest whole unit, e.g. "3 days" or "1 hour"
+fn describe_gap(gap: chrono::TimeDelta) -> String {
+    let (count, unit) = if gap.num_days() > 0 {
+        (gap.num_days(), "day")
+    } else if gap.num_hours() > 0 {
+        (gap.num_hours(), "hour")
+    } else {
+        (gap.num_minutes(), "minute")
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// Operation to retry once a fresh token has been entered
+#[derive(Debug, Clone)]
+pub enum ResumeAction {
+    LoadClassrooms,
+    LoadAssignments {
+        classroom: Classroom,
+    },
+    FetchResults {
+        classroom: Classroom,
+        assignment: Assignment,
+        selection: RunSelection,
+    },
+    FetchLateResults {
+        classroom: Classroom,
+        assignment: Assignment,
+        deadlines: LateGradingDeadlines,
+    },
+    FetchAllAssignments {
+        classroom: Classroom,
+        assignments: Vec<Assignment>,
+    },
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DeadlineField {
     Date,
@@ -83,13 +379,102 @@ pub enum LateGradingField {
     Penalty,
 }
 
+/// Warning for a workflow whose tests add up to different points than
+/// GitHub Classroom grades out of, e.g. after the workflow was edited
+pub fn points_mismatch_warning(workflow: u32, classroom: u32) -> String {
+    format!(
+        "⚠ The workflow's tests add up to {} points, but GitHub Classroom grades out of {}",
+        workflow, classroom
+    )
+}
+
+/// Warning for results with no points available, whose percentages are
+/// exported as N/A: all of them usually means every test has `max_score: 0`
+pub fn zero_available_warning(results: &[StudentResult]) -> Option<String> {
+    let zero = results.iter().filter(|r| r.total_available == 0).count();
+    if zero == 0 {
+        None
+    } else if zero == results.len() {
+        Some(
+            "⚠ No points are available in this assignment (do its tests all have max-score: 0?); \
+             percentages are exported as N/A"
+                .to_string(),
+        )
+    } else {
+        Some(format!(
+            "⚠ {} of {} students had no points available (no tests reached them); their percentages are exported as N/A",
+            zero,
+            results.len()
+        ))
+    }
+}
+
+/// Upper bound on retained status log lines
+const MAX_STATUS_MESSAGES: usize = 10_000;
+
 #[derive(Debug, Clone)]
 pub struct FetchProgress {
     pub total_students: usize,
     pub completed: usize,
+    /// Number of students currently being fetched
+    pub active: usize,
     pub current_student: String,
     pub errors: usize,
+    /// Students whose grading run is still queued or running
+    pub in_progress: usize,
+    /// Why each of the `errors` students failed
+    pub failures: Vec<StudentFailure>,
+    /// Students with no completed grading run, who aren't counted as errors
+    pub nonsubmitters: Vec<NonSubmitter>,
+    /// Problems worth repeating on the completion screen
+    pub warnings: Vec<String>,
     pub status_messages: Vec<String>,
+    /// How many of the last `status_messages` no snapshot has carried yet
+    unsent: usize,
+    /// Each student of the current assignment by username, in roster order
+    pub students: IndexMap<String, StudentStatus>,
+    /// Tests of the current assignment, once loaded
+    pub test_definitions: Vec<TestDefinition>,
+    /// Receives every status message as it is added
+    pub run_log: Option<Arc<RunLog>>,
+}
+
+/// Where one student's fetch stands, for the fetching screens' student table
+#[derive(Debug, Clone, PartialEq)]
+pub enum StudentStatus {
+    Pending,
+    Fetching,
+    /// Total points (final points for late grading)
+    Graded { awarded: u32, available: u32 },
+    /// Their grading run is still queued or running
+    InProgress,
+    NotSubmitted,
+    Failed(String),
+}
+
+impl From<&StudentOutcome<'_>> for StudentStatus {
+    fn from(outcome: &StudentOutcome<'_>) -> Self {
+        match outcome {
+            StudentOutcome::Graded(result) => StudentStatus::Graded {
+                awarded: result.total_awarded,
+                available: result.total_available,
+            },
+            StudentOutcome::LateGraded(result) => StudentStatus::Graded {
+                awarded: result.final_score,
+                available: result.on_time_result.total_available,
+            },
+            StudentOutcome::InProgress => StudentStatus::InProgress,
+            StudentOutcome::NotSubmitted { .. } => StudentStatus::NotSubmitted,
+            StudentOutcome::Failed(e) => StudentStatus::Failed(e.to_string()),
+        }
+    }
+}
+
+/// A student whose results couldn't be fetched
+#[derive(Debug, Clone, Serialize)]
+pub struct StudentFailure {
+    pub student: String,
+    pub error: String,
 }
 
 impl FetchProgress {
@@ -97,17 +482,76 @@ impl FetchProgress {
         Self {
             total_students,
             completed: 0,
+            active: 0,
             current_student: String::new(),
             errors: 0,
+            in_progress: 0,
+            failures: Vec::new(),
+            nonsubmitters: Vec::new(),
+            warnings: Vec::new(),
             status_messages: vec!["Initializing...".to_string()],
+            unsent: 0,
+            students: IndexMap::new(),
+            test_definitions: Vec::new(),
+            run_log: None,
         }
     }
 
+    /// Zero the per-assignment counters, keeping the status log
+    pub fn reset_counts(&mut self, total_students: usize) {
+        self.total_students = total_students;
+        self.completed = 0;
+        self.active = 0;
+        self.errors = 0;
+        self.in_progress = 0;
+        self.failures.clear();
+        self.nonsubmitters.clear();
+        self.warnings.clear();
+        self.students.clear();
+        self.test_definitions.clear();
+    }
+
     pub fn add_status(&mut self, message: String) {
+        self.record(&message);
         self.status_messages.push(message);
-        // Keep only the last 20 messages to avoid memory issues
-        if self.status_messages.len() > 20 {
-            self.status_messages.remove(0);
+        self.unsent += 1;
+        self.trim_status();
+    }
+
+    /// Keep the full run as an audit trail, bounded only for pathological runs
+    fn trim_status(&mut self) {
+        if self.status_messages.len() > MAX_STATUS_MESSAGES {
+            let excess = self.status_messages.len() - MAX_STATUS_MESSAGES;
+            self.status_messages.drain(..excess);
+        }
+        self.unsent = self.unsent.min(self.status_messages.len());
+    }
+
+    /// A copy to send to the UI carrying only the status messages added since
+    /// the last snapshot, so an event doesn't copy the whole log
+    pub fn snapshot(&mut self) -> FetchProgress {
+        let log = std::mem::take(&mut self.status_messages);
+        let mut snapshot = self.clone();
+        snapshot.status_messages = log[log.len() - self.unsent..].to_vec();
+        self.status_messages = log;
+        self.unsent = 0;
+        snapshot
+    }
+
+    /// Take over a [`FetchProgress::snapshot`], appending its status messages to this log
+    pub fn update(&mut self, snapshot: FetchProgress) {
+        let mut log = std::mem::take(&mut self.status_messages);
+        *self = snapshot;
+        log.append(&mut self.status_messages);
+        self.status_messages = log;
+        self.trim_status();
+        self.unsent = 0;
+    }
+
+    /// Write a line to the run log only, for details too long for the status log
+    pub fn record(&self, message: &str) {
+        if let Some(run_log) = &self.run_log {
+            run_log.write(message);
         }
     }
 
@@ -119,3 +563,60 @@ impl FetchProgress {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_log_keeps_history() {
+        let mut progress = FetchProgress::new(0);
+        for i in 0..MAX_STATUS_MESSAGES + 5 {
+            progress.add_status(format!("message {}", i));
+        }
+
+        assert_eq!(progress.status_messages.len(), MAX_STATUS_MESSAGES);
+        assert_eq!(progress.status_messages[0], "message 5");
+    }
+
+    #[test]
+    fn test_snapshot_carries_new_status_only() {
+        let mut progress = FetchProgress::new(0);
+        let mut shown = FetchProgress::new(0);
+        progress.add_status("one".to_string());
+        shown.update(progress.snapshot());
+        progress.add_status("two".to_string());
+        progress.completed = 1;
+
+        let snapshot = progress.snapshot();
+        assert_eq!(snapshot.status_messages, ["two"]);
+        shown.update(snapshot);
+        assert_eq!(shown.status_messages, ["Initializing...", "one", "two"]);
+        assert_eq!(shown.completed, 1);
+        assert!(progress.snapshot().status_messages.is_empty());
+    }
+
+    #[test]
+    fn test_job_log_drops_control_characters() {
+        let log = JobLog::new(String::new(), "\x1b[36;1mecho hi\x1b[0m\r\n\tdone\x07\n");
+        assert_eq!(log.lines, ["echo hi", "    done"]);
+    }
+
+    #[test]
+    fn test_deadline_warning() {
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        let classroom_deadline = Some(at("2025-03-04T23:59:00Z"));
+        let now = at("2025-03-10T12:00:00Z");
+
+        assert_eq!(
+            deadline_warning(at("2025-03-01T23:59:00Z"), classroom_deadline, now).as_deref(),
+            Some("Entered deadline is 3 days before the Classroom deadline")
+        );
+        assert_eq!(
+            deadline_warning(at("2025-03-10T13:00:00Z"), None, now).as_deref(),
+            Some("Entered deadline is 1 hour in the future, so no runs after it exist yet")
+        );
+        assert_eq!(deadline_warning(at("2025-03-04T23:59:00Z"), classroom_deadline, now), None);
+        assert_eq!(deadline_warning(at("2025-03-06T08:00:00Z"), classroom_deadline, now), None);
+    }
+}
@@ -0,0 +1,78 @@
+use ratatui::style::Color;
+use std::str::FromStr;
+
+/// Named colors used by every screen, selected with `THEME`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Borders and titles
+    pub primary: Color,
+    /// Selected items, focused inputs, headings, and warnings
+    pub highlight: Color,
+    /// Success messages, the progress bar, and the status log
+    pub success: Color,
+    pub error: Color,
+    /// Hints and other secondary text
+    pub muted: Color,
+}
+
+impl Theme {
+    /// The original palette, for dark terminal backgrounds
+    pub const DARK: Theme = Theme {
+        primary: Color::Cyan,
+        highlight: Color::Yellow,
+        success: Color::Green,
+        error: Color::Red,
+        muted: Color::DarkGray,
+    };
+
+    /// Darker colors that stay readable on light terminal backgrounds
+    pub const LIGHT: Theme = Theme {
+        primary: Color::Blue,
+        highlight: Color::Magenta,
+        success: Color::Indexed(22),
+        error: Color::Red,
+        muted: Color::Gray,
+    };
+
+    /// Bright colors only, with blue/magenta instead of green/red so success and
+    /// errors stay distinguishable with red-green color blindness
+    pub const HIGH_CONTRAST: Theme = Theme {
+        primary: Color::White,
+        highlight: Color::LightYellow,
+        success: Color::LightBlue,
+        error: Color::LightMagenta,
+        muted: Color::Gray,
+    };
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DARK
+    }
+}
+
+impl FromStr for Theme {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> anyhow::Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "dark" => Ok(Theme::DARK),
+            "light" => Ok(Theme::LIGHT),
+            "high-contrast" => Ok(Theme::HIGH_CONTRAST),
+            _ => anyhow::bail!("Unknown theme '{}', expected dark, light, or high-contrast", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_theme() {
+        assert_eq!("dark".parse::<Theme>().unwrap(), Theme::DARK);
+        assert_eq!("Light".parse::<Theme>().unwrap(), Theme::LIGHT);
+        assert_eq!("high-contrast".parse::<Theme>().unwrap(), Theme::HIGH_CONTRAST);
+        assert!("solarized".parse::<Theme>().is_err());
+    }
+}